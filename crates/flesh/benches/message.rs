@@ -0,0 +1,65 @@
+//! Timings for the work done on every message: encoding, signing, encrypting and splitting.
+//!
+//! `cargo bench -p flesh` measures each for an ack sized (16 byte) body and a 1 KB one.
+//! Run as a test (as `cargo test --all-targets` does), each case is only run once to check it still works.
+
+use {
+    criterion::{Criterion, criterion_group, criterion_main},
+    flesh::transport::{encoding::FLESHMessage, fragment::InternalMessage, keys::SigningKey, status::Status},
+    std::hint::black_box,
+    uuid::Uuid,
+};
+
+/// Chunk size the large message is split into, about what fits in a LoRa frame.
+const CHUNK: usize = 200;
+
+fn identity(seed: u8) -> (Uuid, SigningKey) { (Uuid::from_u128(seed as u128), SigningKey::from_bytes(&[seed; 32])) }
+
+fn message(body: usize) -> FLESHMessage {
+    FLESHMessage::new(Status::Acknowledge).with_sender(identity(1).0).with_target(identity(2).0).with_body(vec![0x5a; body])
+}
+
+fn encoding(c: &mut Criterion) {
+    for (size, body) in [("ack", 16), ("1kb", 1024)] {
+        let m = message(body);
+        let bytes = m.serialize().unwrap();
+        c.bench_function(&format!("serialize/{size}"), |b| b.iter(|| m.serialize().unwrap()));
+        c.bench_function(&format!("deserialize/{size}"), |b| {
+            b.iter(|| FLESHMessage::deserialize(black_box(&bytes)).unwrap())
+        });
+    }
+}
+
+#[cfg(feature = "crypto")]
+fn crypto(c: &mut Criterion) {
+    let (me, peer) = (identity(1), identity(2));
+    for (size, body) in [("ack", 16), ("1kb", 1024)] {
+        let m = message(body);
+        let signed = m.clone().sign(me.clone()).unwrap();
+        c.bench_function(&format!("sign/{size}"), |b| b.iter(|| m.clone().sign(me.clone()).unwrap()));
+        c.bench_function(&format!("verify/{size}"), |b| b.iter(|| signed.verify(&me.1.verifying_key()).unwrap()));
+
+        let encrypted = m.clone().encrypt_body(&peer.1.verifying_key()).unwrap();
+        c.bench_function(&format!("encrypt_body/{size}"), |b| {
+            b.iter(|| m.clone().encrypt_body(&peer.1.verifying_key()).unwrap())
+        });
+        c.bench_function(&format!("decrypt_body/{size}"), |b| b.iter(|| encrypted.clone().decrypt_body(&peer).unwrap()));
+    }
+}
+
+#[cfg(not(feature = "crypto"))]
+fn crypto(_: &mut Criterion) {}
+
+/// What `Network::send_with_splitting` does to a message before putting the parts on the air.
+fn splitting(c: &mut Criterion) {
+    let large = message(1024);
+    c.bench_function("split/1kb", |b| {
+        b.iter(|| {
+            let (_, parts) = InternalMessage::split(&large, CHUNK).unwrap();
+            parts.iter().map(|p| p.serialize().unwrap()).collect::<Vec<_>>()
+        })
+    });
+}
+
+criterion_group!(benches, encoding, crypto, splitting);
+criterion_main!(benches);
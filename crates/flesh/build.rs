@@ -11,10 +11,17 @@ const TARGET_FILE: &str = "./src/transport/status.rs";
 
 fn main() {
     let mut rdr = csv::Reader::from_reader(OpenOptions::new().read(true).open(CSV_TARGET).unwrap());
-    let (enum_fields, (into_arms, cat_arms)): (Vec<TokenStream>, (Vec<TokenStream>, Vec<TokenStream>)) = rdr
+    let rows = rdr
         .deserialize::<(String, String, String, String, String)>()
-        .filter_map(|v| v.ok().and_then(|v| v.3.is_empty().not().then_some(parse_csv_line(v))))
-        .unzip();
+        .filter_map(|v| v.ok().and_then(|v| v.3.is_empty().not().then(|| parse_csv_line(v))))
+        .collect_vec();
+
+    let enum_fields = rows.iter().map(|r| r.enum_field.clone()).collect_vec();
+    let into_arms = rows.iter().map(|r| r.to_u8_arm.clone()).collect_vec();
+    let cat_arms = rows.iter().map(|r| r.cat_arm.clone()).collect_vec();
+    let name_arms = rows.iter().map(|r| r.name_arm.clone()).collect_vec();
+    let reason_arms = rows.iter().map(|r| r.reason_arm.clone()).collect_vec();
+    let http_arms = rows.iter().filter_map(|r| r.http_arm.clone()).collect_vec();
 
     let len = enum_fields.len();
     let selfs = into_arms.clone().iter().map(|v| v.clone().into_iter().take(4).collect::<TokenStream>()).collect_vec();
@@ -40,16 +47,57 @@ fn main() {
             pub fn as_type(&self) -> StatusType {
                 match self {
                     #(#cat_arms)*
-                    Self::Custom(_) => StatusType::Unknown
+                    Self::Custom(int) => crate::transport::custom::lookup(*int).map_or(StatusType::Unknown, |(_, kind)| kind)
                 }
             }
 
-            pub fn is_ok(&self) -> bool {
-                match self.as_type() {
-                    StatusType::Routing | StatusType::Hints | StatusType::Oks => true,
-                    _ => false
+            /// Human readable name of the status
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms)*
+                    Self::Custom(int) => crate::transport::custom::lookup(*int).map_or("Custom", |(name, _)| name)
+                }
+            }
+
+            /// Short description of what the status means
+            pub fn reason(&self) -> &'static str {
+                match self {
+                    #(#reason_arms)*
+                    Self::Custom(_) => ""
                 }
             }
+
+            /// Every standard status as (code, name, category, reason)
+            pub fn standard_with_metadata() -> impl Iterator<Item = (u8, &'static str, StatusType, &'static str)> {
+                Self::STANDARD.into_iter().map(|s| (s.as_u8(), s.name(), s.as_type(), s.reason()))
+            }
+
+            /// The HTTP status code listed as equivalent, if any
+            fn http(&self) -> Option<u16> {
+                match self {
+                    #(#http_arms)*
+                    _ => None
+                }
+            }
+
+            /// HTTP status code for bridging to HTTP, falling back to a generic code for the category
+            pub fn to_http(&self) -> u16 {
+                self.http().unwrap_or(match self.as_type() {
+                    StatusType::Routing | StatusType::Hints | StatusType::Oks => 200,
+                    StatusType::RoutingError => 502,
+                    StatusType::ClientErrors => 400,
+                    StatusType::ServerErrors | StatusType::Unknown => 500,
+                })
+            }
+
+            /// The standard status equivalent to an HTTP status code, if there is one
+            pub fn from_http(code: u16) -> Option<Self> {
+                Self::STANDARD.into_iter().find(|s| s.http() == Some(code))
+            }
+
+            pub fn is_ok(&self) -> bool {
+                matches!(self.as_type(), StatusType::Routing | StatusType::Hints | StatusType::Oks)
+            }
         }
 
         //
@@ -68,10 +116,25 @@ fn main() {
             ClientErrors,
             /// 051 -> 060
             ServerErrors,
-            /// Currently unbound or in custom range 061->254(~)
+            /// Currently unbound, or in custom range 061->254(~) and not registered with `Status::register_custom`
             Unknown
         }
 
+        impl StatusType {
+            /// The codes set aside for the category, `None` for `Unknown`
+            pub fn codes(&self) -> Option<std::ops::RangeInclusive<u8>> {
+                match self {
+                    Self::Routing => Some(1..=14),
+                    Self::RoutingError => Some(15..=20),
+                    Self::Hints => Some(21..=30),
+                    Self::Oks => Some(31..=40),
+                    Self::ClientErrors => Some(41..=50),
+                    Self::ServerErrors => Some(51..=60),
+                    Self::Unknown => None,
+                }
+            }
+        }
+
     };
 
     OpenOptions::new()
@@ -86,14 +149,41 @@ fn main() {
     Command::new("rustfmt").arg(TARGET_FILE).spawn().unwrap().wait().unwrap();
 }
 
-fn parse_csv_line(rec: (String, String, String, String, String)) -> (TokenStream, (TokenStream, TokenStream)) {
-    let (int, cat, equiv, ident, mut note) = rec;
-    if ident.is_empty() {
-        return (TokenStream::new(), (TokenStream::new(), TokenStream::new()));
-    }
+/// Generated code for a single status
+struct Row {
+    enum_field: TokenStream,
+    to_u8_arm: TokenStream,
+    cat_arm: TokenStream,
+    name_arm: TokenStream,
+    reason_arm: TokenStream,
+    http_arm: Option<TokenStream>,
+}
+
+fn parse_csv_line(rec: (String, String, String, String, String)) -> Row {
+    let (int, cat, equiv, name, mut note) = rec;
 
     let int = int.parse::<u8>().unwrap();
-    let ident = format_ident!("{}", AsPascalCase(ident).to_string());
+
+    // A blank note, or a ditto mark copied along from a spreadsheet, would leave the status without a reason
+    if note.trim().is_empty() || note.trim() == "\"" {
+        note = name.clone();
+    }
+
+    let ident = format_ident!("{}", AsPascalCase(&name).to_string());
+
+    let reason_arm = quote! {
+        Self::#ident => #note,
+    };
+
+    let name_arm = quote! {
+        Self::#ident => #name,
+    };
+
+    let http_arm = equiv.parse::<u16>().ok().map(|code| {
+        quote! {
+            Self::#ident => Some(#code),
+        }
+    });
 
     if !equiv.is_empty() {
         note = format!("{note} (HTTP Equivalent {equiv})");
@@ -114,5 +204,5 @@ fn parse_csv_line(rec: (String, String, String, String, String)) -> (TokenStream
         Self::#ident => StatusType::#cat,
     };
 
-    (enum_field, (to_u8_arm, cat_arm))
+    Row { enum_field, to_u8_arm, cat_arm, name_arm, reason_arm, http_arm }
 }
@@ -0,0 +1,40 @@
+//! Everything a received frame goes through before it's trusted.
+//!
+//! `cargo +nightly fuzz run decode` from `crates/flesh`
+
+#![no_main]
+
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        fragment::{InternalMessage, Reassembler},
+        network::RoutingMessage,
+    },
+    libfuzzer_sys::fuzz_target,
+};
+
+fn decode(frame: &[u8]) {
+    if let Ok(message) = FLESHMessage::deserialize(frame) {
+        let _ = RoutingMessage::from_message(&message);
+        let _ = message.content_id();
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    decode(data);
+
+    // The input is also read as a run of frames, each prefixed with its length, so parts of several
+    // messages meet in the one reassembler
+    let mut reassembler = Reassembler::default();
+    let mut rest = data;
+    while let Some((&len, tail)) = rest.split_first() {
+        let (frame, tail) = tail.split_at((len as usize).min(tail.len()));
+        rest = tail;
+
+        if let Ok(frame) = InternalMessage::from_frame(frame.to_vec())
+            && let Some(InternalMessage::Complete(whole)) = reassembler.insert(frame)
+        {
+            decode(&whole);
+        }
+    }
+});
@@ -7,33 +7,31 @@ use {
         fmt::Debug,
         ops::Deref,
         pin::Pin,
-        sync::{Arc, RwLock},
+        sync::{Arc, RwLock, Weak},
         task::{Context, Poll},
     },
-    tokio::sync::{
-        Mutex,
-        mpsc::{self, UnboundedReceiver, unbounded_channel},
-    },
+    tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel},
     tracing::instrument,
 };
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+type Listeners<T> = RwLock<HashMap<Uuid, Arc<Subscription<T>>>>;
+
+/// A handle to a set of listeners. Every clone shares the same set, so an event emitted on any
+/// clone reaches every handler and stream subscribed through any other, and cloning is cheap.
+///
+/// Each `as_stream` is an independent receiver that sees everything emitted after it was created,
+/// and unsubscribes when dropped.
+#[derive(Debug)]
 pub struct EventTarget<T: Debug> {
-    listeners: Arc<RwLock<HashMap<Uuid, Arc<Subscription<T>>>>>,
-    sender: Arc<mpsc::UnboundedSender<Arc<T>>>,
-    receiver: Arc<Mutex<mpsc::UnboundedReceiver<Arc<T>>>>,
+    listeners: Arc<Listeners<T>>,
+}
+
+impl<T: Debug> Clone for EventTarget<T> {
+    fn clone(&self) -> Self { Self { listeners: self.listeners.clone() } }
 }
 
 impl<T: Debug> EventTarget<T> {
-    pub fn new() -> Self {
-        let (sender, receiver) = mpsc::unbounded_channel();
-        Self {
-            listeners: Arc::new(RwLock::new(HashMap::new())),
-            sender: sender.into(),
-            receiver: Arc::new(Mutex::new(receiver)),
-        }
-    }
+    pub fn new() -> Self { Self { listeners: Arc::new(RwLock::new(HashMap::new())) } }
 
     #[instrument(level = "trace")]
     pub fn emit(&self, v: impl Into<Arc<T>> + Debug) {
@@ -43,9 +41,6 @@ impl<T: Debug> EventTarget<T> {
         if let Ok(listeners) = self.listeners.read() {
             listeners.values().for_each(|s| s.update(v.clone()));
         }
-
-        // Send to stream (ignore error if receiver is dropped)
-        let _ = self.sender.send(v);
     }
 
     pub fn on(&self, handler: impl Fn(Arc<T>) + Send + Sync + 'static) -> Arc<Subscription<T>> {
@@ -56,8 +51,10 @@ impl<T: Debug> EventTarget<T> {
         sub
     }
 
-    pub fn off(&self, sub: &Subscription<T>) {
-        if let Ok(mut listeners) = self.listeners.write() {
+    pub fn off(&self, sub: &Subscription<T>) { Self::remove(&self.listeners, sub) }
+
+    fn remove(listeners: &Listeners<T>, sub: &Subscription<T>) {
+        if let Ok(mut listeners) = listeners.write() {
             listeners.remove(&sub.id);
         }
     }
@@ -77,28 +74,23 @@ impl<T: Debug> Default for EventTarget<T> {
 pub struct Subscription<T: Debug> {
     id: Uuid,
     handler: Box<dyn Fn(Arc<T>) + Send + Sync>,
-    to: *const EventTarget<T>, // Using raw pointer to avoid lifetime issues
+    to: Weak<Listeners<T>>,
 }
 
 impl<T: Debug> Debug for Subscription<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Subscription").field("id", &self.id).field("handler", &"<function>").field("to", &self.to).finish()
+        f.debug_struct("Subscription").field("id", &self.id).field("handler", &"<function>").finish()
     }
 }
 
-unsafe impl<T: Debug> Send for Subscription<T> {}
-unsafe impl<T: Debug> Sync for Subscription<T> {}
-
 impl<T: Debug> Subscription<T> {
     pub fn new(to: &EventTarget<T>, handler: impl Fn(Arc<T>) + Send + Sync + 'static) -> Self {
-        Self { id: Uuid::new_v4(), handler: Box::new(handler), to: to as *const _ }
+        Self { id: Uuid::new_v4(), handler: Box::new(handler), to: Arc::downgrade(&to.listeners) }
     }
 
     pub fn off(&self) {
-        unsafe {
-            if let Some(target) = self.to.as_ref() {
-                target.off(self);
-            }
+        if let Some(listeners) = self.to.upgrade() {
+            EventTarget::remove(&listeners, self);
         }
     }
 
@@ -107,14 +99,9 @@ impl<T: Debug> Subscription<T> {
 }
 
 impl<T: Debug> Drop for Subscription<T> {
-    fn drop(&mut self) {
-        unsafe {
-            self.to.read().off(self);
-        }
-    }
+    fn drop(&mut self) { self.off() }
 }
 
-#[allow(dead_code)]
 pub struct EventStream<T: Debug> {
     sub: Arc<Subscription<T>>,
     ch: UnboundedReceiver<Arc<T>>,
@@ -135,6 +122,11 @@ where
     }
 }
 
+// The target holds the subscription too, so it has to be removed explicitly
+impl<T: Debug> Drop for EventStream<T> {
+    fn drop(&mut self) { self.sub.off() }
+}
+
 impl<T: Debug> Deref for EventStream<T> {
     type Target = UnboundedReceiver<Arc<T>>;
 
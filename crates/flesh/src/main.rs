@@ -8,23 +8,22 @@ use {
 };
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     let lora = Lora::new(
         Path::new("/dev/serial/by-id/usb-Silicon_Labs_CP2102_USB_to_UART_Bridge_Controller_0001-if00-port0").to_path_buf(),
         9600,
-        LoraSettings { spread_factor: 9, frequency_hz: 915_000_000, bandwidth_khz: 10 },
+        LoraSettings { spread_factor: 9, frequency_hz: 915_000_000, bandwidth_khz: 10, ..Default::default() },
         false,
     )
-    .await
-    .unwrap();
+    .await?;
 
     let network = Network::new(lora.clone());
+    let mut messages = network.as_stream();
 
-    loop {
-        let message = network.as_stream().next().await;
-        if let Some(message) = message {
-            let body = message.body.clone();
-            println!("{}b -- {}", body.len(), String::from_utf8_lossy(&body));
-        }
+    while let Some(message) = messages.next().await {
+        let body = message.body.clone();
+        println!("{}b -- {}", body.len(), String::from_utf8_lossy(&body));
     }
+
+    Ok(())
 }
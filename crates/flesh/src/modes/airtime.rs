@@ -0,0 +1,73 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Symbols sent ahead of every packet.
+const PREAMBLE_SYMBOLS: f64 = 8.0;
+/// Coding rate 4/5, as `CR` in Semtech's time-on-air formula.
+const CODING_RATE: f64 = 1.0;
+
+/// A regulatory limit on how long the radio may transmit.
+#[derive(Debug, Clone, Copy)]
+pub struct DutyCycle {
+    /// Fraction (0.0 -> 1.0) of the window that may be spent transmitting, e.g. 0.01 for the EU's 1%
+    pub fraction: f64,
+    /// The rolling window the fraction applies over
+    pub window: Duration,
+}
+
+impl DutyCycle {
+    fn allowance(&self) -> Duration { self.window.mul_f64(self.fraction.clamp(0.0, 1.0)) }
+}
+
+/// How long a packet of `len` bytes occupies the air, per Semtech's LoRa modem calculator
+/// (explicit header, CRC on, coding rate 4/5).
+pub fn time_on_air(spread_factor: u8, bandwidth_khz: u16, len: usize) -> Duration {
+    let sf = spread_factor as f64;
+    let symbol = 2f64.powf(sf) / (bandwidth_khz.max(1) as f64 * 1000.0);
+    let low_data_rate = if symbol > 0.016 { 1.0 } else { 0.0 };
+
+    let bits = 8.0 * len as f64 - 4.0 * sf + 28.0 + 16.0;
+    let payload_symbols = 8.0 + ((bits / (4.0 * (sf - 2.0 * low_data_rate))).ceil() * (CODING_RATE + 4.0)).max(0.0);
+
+    Duration::from_secs_f64((PREAMBLE_SYMBOLS + 4.25 + payload_symbols) * symbol)
+}
+
+/// Tracks transmissions against a `DutyCycle` over a rolling window.
+#[derive(Debug, Clone)]
+pub struct AirtimeBudget {
+    limit: Option<DutyCycle>,
+    sent: VecDeque<(Instant, Duration)>,
+}
+
+impl AirtimeBudget {
+    pub fn new(limit: Option<DutyCycle>) -> Self { Self { limit, sent: VecDeque::new() } }
+
+    /// Transmit time left in the current window, `Duration::MAX` when unlimited.
+    pub fn remaining(&mut self) -> Duration {
+        let Some(limit) = self.limit else {
+            return Duration::MAX;
+        };
+
+        while self.sent.front().is_some_and(|(at, _)| at.elapsed() >= limit.window) {
+            self.sent.pop_front();
+        }
+
+        limit.allowance().saturating_sub(self.sent.iter().map(|(_, airtime)| *airtime).sum())
+    }
+
+    /// Records a transmission if it fits within the budget, returning whether it did.
+    pub fn spend(&mut self, airtime: Duration) -> bool {
+        if self.limit.is_none() {
+            return true;
+        }
+
+        if airtime > self.remaining() {
+            return false;
+        }
+
+        self.sent.push_back((Instant::now(), airtime));
+        true
+    }
+}
@@ -0,0 +1,200 @@
+use {
+    bytes::{Buf, Bytes, BytesMut},
+    std::io,
+    tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec, LinesCodec, LinesCodecError},
+    tracing::warn,
+};
+
+const LINE_RECEIVE_PREFIX: &str = "+RCV=";
+
+/// Most hex characters a module takes in a single `AT+SEND`, see `Framing::Lines`.
+pub const LINE_MAX_HEX: usize = 240;
+
+/// Bytes a link-layer checksum adds to each frame, see `with_checksum`.
+pub const CHECKSUM_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+/// How payloads are delimited on the serial line.
+#[derive(Debug, Clone, Copy)]
+pub enum Framing {
+    /// Raw frames prefixed with their length, for modules in transparent mode
+    LengthDelimited { bytes: usize, endian: Endian },
+    /// The module's line-based AT interface: payloads are sent hex encoded with `AT+SEND`
+    /// and arrive as `+RCV=<address>,<length>,<data>,<rssi>,<snr>` lines, where the length is of the hex
+    Lines,
+}
+
+impl Default for Framing {
+    fn default() -> Self { Self::LengthDelimited { bytes: 1, endian: Endian::Little } }
+}
+
+impl Framing {
+    /// The largest payload the framing can carry, however much the module could take.
+    pub fn max_payload(&self) -> usize {
+        match self {
+            // Anything longer would overflow the length header
+            Self::LengthDelimited { bytes, .. } => {
+                (bytes * 8).try_into().ok().and_then(|bits| 1usize.checked_shl(bits)).map_or(usize::MAX, |n| n - 1)
+            }
+            Self::Lines => LINE_MAX_HEX / 2,
+        }
+    }
+}
+
+/// Encodes and decodes payloads according to a `Framing`.
+#[derive(Debug, Clone)]
+pub struct LoraCodec {
+    framing: Framing,
+    length: LengthDelimitedCodec,
+    lines: LinesCodec,
+    /// Corrupt headers and lines stepped past since last asked, see `take_skipped`
+    skipped: usize,
+    /// Signal strength the last frame arrived at, where the module reported it, see `take_rssi`
+    rssi: Option<i16>,
+}
+
+impl LoraCodec {
+    pub fn new(framing: Framing, max_payload: usize) -> Self {
+        let max_payload = max_payload.min(framing.max_payload());
+        let (bytes, endian) = match framing {
+            Framing::LengthDelimited { bytes, endian } => (bytes, endian),
+            Framing::Lines => (1, Endian::Little),
+        };
+
+        let mut length = LengthDelimitedCodec::builder();
+        length.length_field_length(bytes).max_frame_length(max_payload);
+        let length = match endian {
+            Endian::Little => length.little_endian().new_codec(),
+            Endian::Big => length.big_endian().new_codec(),
+        };
+
+        // Hex doubles the payload, and the rest of the line is bounded by the module's own fields
+        Self { framing, length, lines: LinesCodec::new_with_max_length(max_payload * 2 + 64), skipped: 0, rssi: None }
+    }
+
+    /// Corrupt length headers and received lines skipped since this was last called.
+    pub fn take_skipped(&mut self) -> usize { std::mem::take(&mut self.skipped) }
+
+    /// Signal strength (in dBm) the frame just decoded arrived at, if the module reports it. Only `Framing::Lines`
+    /// carries it, transparent mode passes nothing but the payload.
+    pub fn take_rssi(&mut self) -> Option<i16> { self.rssi.take() }
+
+    /// Pulls the payload and its RSSI out of a `+RCV=` line, `None` for anything else the module says (`OK`, errors, etc).
+    fn parse_line(line: &str) -> Option<(Vec<u8>, Option<i16>)> {
+        let (_address, rest) = line.trim().strip_prefix(LINE_RECEIVE_PREFIX)?.split_once(',')?;
+        let (length, rest) = rest.split_once(',')?;
+        let mut fields = rest.rsplitn(3, ',');
+        let (_snr, rssi, data) = (fields.next()?, fields.next()?, fields.next()?);
+
+        // The module counts the characters it carried, which are the hex digits rather than the bytes they encode
+        (data.len() == length.parse::<usize>().ok()?).then_some(())?;
+        Some((hex_decode(data)?, rssi.trim().parse().ok()))
+    }
+}
+
+impl Decoder for LoraCodec {
+    type Error = io::Error;
+    type Item = Vec<u8>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // A corrupt frame is skipped rather than returned as an error, which would end the stream
+        match self.framing {
+            Framing::LengthDelimited { .. } => loop {
+                match self.length.decode(src) {
+                    Ok(frame) => {
+                        self.rssi = None;
+                        return Ok(frame.map(|frame| frame.to_vec()));
+                    }
+                    Err(e) => {
+                        // The length was garbage, so step past it and look for the next plausible header
+                        warn!("Skipping corrupt frame: {e}");
+                        src.advance(1);
+                        self.skipped += 1;
+                    }
+                }
+            },
+            Framing::Lines => loop {
+                match self.lines.decode(src) {
+                    Ok(None) => return Ok(None),
+                    Ok(Some(line)) => {
+                        if let Some((data, rssi)) = Self::parse_line(&line) {
+                            self.rssi = rssi;
+                            return Ok(Some(data));
+                        }
+
+                        if line.trim().starts_with(LINE_RECEIVE_PREFIX) {
+                            warn!("Skipping malformed line");
+                            self.skipped += 1;
+                        }
+                    }
+                    // The codec discards the rest of the line itself
+                    Err(LinesCodecError::MaxLineLengthExceeded) => {
+                        warn!("Skipping overlong line");
+                        self.skipped += 1;
+                    }
+                    Err(LinesCodecError::Io(e)) => return Err(e),
+                }
+            },
+        }
+    }
+}
+
+impl Encoder<Bytes> for LoraCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        match self.framing {
+            Framing::LengthDelimited { .. } => self.length.encode(item, dst),
+            Framing::Lines => {
+                // Address 0 is the module's broadcast address, FLESH does its own addressing
+                let hex = hex_encode(&item);
+                dst.extend_from_slice(format!("AT+SEND=0,{},{hex}\r\n", hex.len()).as_bytes());
+                Ok(())
+            }
+        }
+    }
+}
+
+fn hex_encode(data: &[u8]) -> String { data.iter().map(|b| format!("{b:02X}")).collect() }
+
+fn hex_decode(data: &str) -> Option<Vec<u8>> {
+    data.len().is_multiple_of(2).then_some(())?;
+    (0..data.len()).step_by(2).map(|i| u8::from_str_radix(data.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// CRC-32 (IEEE) of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg()))
+    })
+}
+
+/// Appends the CRC-32 of `data` to it.
+pub fn with_checksum(data: &[u8]) -> Vec<u8> { [data, &crc32(data).to_le_bytes()].concat() }
+
+/// XORs `data` with a PN9 sequence (x^9 + x^5 + 1, seeded with all ones), breaking up long runs of
+/// the same byte. Whitening twice gives back the original, so this also undoes it.
+pub fn whiten(data: &mut [u8]) {
+    let mut state = 0x1FFu16;
+    for byte in data {
+        *byte ^= state as u8;
+        for _ in 0..8 {
+            let bit = (state ^ (state >> 5)) & 1;
+            state = (state >> 1) | (bit << 8);
+        }
+    }
+}
+
+/// Strips and checks the trailing CRC-32 added by `with_checksum`, `None` if the frame was corrupted.
+pub fn verify_checksum(mut frame: Vec<u8>) -> Option<Vec<u8>> {
+    let at = frame.len().checked_sub(CHECKSUM_LEN)?;
+    let crc = u32::from_le_bytes(frame[at..].try_into().ok()?);
+    frame.truncate(at);
+    (crc32(&frame) == crc).then_some(frame)
+}
@@ -1,62 +1,339 @@
 use {
-    crate::{events::EventTarget, transport::PacketTransport},
+    crate::{
+        events::{EventStream, EventTarget},
+        modes::{
+            airtime::{AirtimeBudget, DutyCycle, time_on_air},
+            framing::{CHECKSUM_LEN, Framing, LoraCodec, verify_checksum, whiten, with_checksum},
+        },
+        transport::{PacketTransport, TransportId},
+    },
     async_trait::async_trait,
     bytes::Bytes,
     futures::{SinkExt, StreamExt},
-    std::{io, ops::Deref, path::PathBuf, time::Duration},
+    std::{
+        io,
+        ops::Deref,
+        path::{Path, PathBuf},
+        sync::{
+            Arc, Mutex, PoisonError,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::{Duration, Instant},
+    },
     tokio::{
-        io::{ReadHalf, WriteHalf, split},
-        spawn,
-        sync::mpsc::{UnboundedSender, unbounded_channel},
-        time::timeout,
+        io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf, split},
+        select, spawn,
+        sync::{
+            mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender, channel, error::TrySendError, unbounded_channel},
+            oneshot, watch,
+        },
+        time::{sleep_until, timeout},
     },
-    tokio_serial::{SerialPortBuilderExt, SerialStream},
-    tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec, LinesCodec},
-    tracing::debug,
+    thiserror::Error,
+    tokio_serial::SerialPortBuilderExt,
+    tokio_util::codec::{FramedRead, FramedWrite, LinesCodec},
+    tracing::{debug, error},
 };
 
-const LENGTH_FIELD_SIZE: usize = 1;
 const MAX_PAYLOAD_SIZE: usize = 1200;
+pub const DEFAULT_SEND_QUEUE: usize = 32;
+
+/// Spread factors LoRa modules can be set to.
+const SPREAD_FACTORS: std::ops::RangeInclusive<u8> = 5..=12;
+/// Bandwidths, in kHz, LoRa modules can be set to that are whole numbers of kHz.
+const BANDWIDTHS_KHZ: [u16; 3] = [125, 250, 500];
+/// Frequencies, in Hz, covered by the common LoRa transceivers.
+const FREQUENCIES_HZ: std::ops::RangeInclusive<u32> = 137_000_000..=1_020_000_000;
+/// Transmit powers, in dBm, covered by the common LoRa transceivers.
+const TX_POWERS_DBM: std::ops::RangeInclusive<i8> = -9..=22;
+/// Within the limit of most bands, and easy on the battery.
+pub const DEFAULT_TX_POWER_DBM: i8 = 14;
+/// Bad frames in a row it takes to conclude we've lost sync with the module.
+pub const DEFAULT_RESYNC_AFTER: usize = 16;
+
+/// New settings for the writer task to apply, and where to report how it went.
+type Reconfigure = (LoraSettings, oneshot::Sender<io::Result<()>>);
+/// A received frame, with the RSSI the module reported it at.
+type Received = (Vec<u8>, Option<i16>);
+
+/// What `send` does when the queue of frames waiting for the radio is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueFull {
+    /// Wait for room, passing the backpressure on to the caller
+    #[default]
+    Wait,
+    /// Fail straight away with `io::ErrorKind::WouldBlock`
+    Error,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct LoraSettings {
     pub spread_factor: u8,
     pub frequency_hz: u32,
     pub bandwidth_khz: u16,
+    /// Transmit power, trading battery life for range. Check what your band allows
+    pub tx_power_dbm: i8,
+    pub framing: Framing,
+    /// Transmit time limit for the band, if any
+    pub duty_cycle: Option<DutyCycle>,
+    /// Initial capacity of the serial read buffer, in bytes
+    pub read_buffer: usize,
+    /// Append a CRC-32 to every frame, dropping received frames that fail it rather than passing
+    /// corrupted payloads up. Every node on the channel needs the same setting
+    pub checksum: bool,
+    /// Scramble every frame (checksum included) with `framing::whiten`, so payloads that are mostly
+    /// zeros don't go out as long constant runs. Every node on the channel needs the same setting
+    pub whitening: bool,
+    /// Most frames waiting to be written to the module
+    pub send_queue: usize,
+    /// What `send` does once `send_queue` is full
+    pub when_full: QueueFull,
+    /// Bad frames (corrupt headers, or failed checksums) in a row before concluding we've lost
+    /// sync with the module, as happens when it resets or the baud rate's wrong. What's buffered
+    /// is thrown away and, if we configured it, the module's configured again. `None` never resyncs
+    pub resync_after: Option<usize>,
+}
+
+impl LoraSettings {
+    /// Checks the spread factor, frequency, bandwidth and transmit power are ones a module can be set to.
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |what: String| Err(io::Error::new(io::ErrorKind::InvalidInput, what));
+        if !SPREAD_FACTORS.contains(&self.spread_factor) {
+            return invalid(format!("Spread factor {} is outside {SPREAD_FACTORS:?}", self.spread_factor));
+        }
+
+        if !BANDWIDTHS_KHZ.contains(&self.bandwidth_khz) {
+            return invalid(format!("Bandwidth of {} kHz isn't one of {BANDWIDTHS_KHZ:?}", self.bandwidth_khz));
+        }
+
+        if !FREQUENCIES_HZ.contains(&self.frequency_hz) {
+            return invalid(format!("Frequency of {} Hz is outside {FREQUENCIES_HZ:?}", self.frequency_hz));
+        }
+
+        if !TX_POWERS_DBM.contains(&self.tx_power_dbm) {
+            return invalid(format!("Transmit power of {} dBm is outside {TX_POWERS_DBM:?}", self.tx_power_dbm));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for LoraSettings {
+    fn default() -> Self {
+        Self {
+            spread_factor: 9,
+            frequency_hz: 915_000_000,
+            bandwidth_khz: 125,
+            tx_power_dbm: DEFAULT_TX_POWER_DBM,
+            framing: Framing::default(),
+            duty_cycle: None,
+            read_buffer: 4096,
+            checksum: false,
+            whitening: false,
+            send_queue: DEFAULT_SEND_QUEUE,
+            when_full: QueueFull::Wait,
+            resync_after: Some(DEFAULT_RESYNC_AFTER),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoraError {
+    #[error("Can't open LoRa device {}: {}", .0.display(), .1)]
+    DeviceUnavailable(PathBuf, String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl LoraError {
+    /// Explains why `path` couldn't be opened, with what to check next.
+    fn unavailable(path: &Path, e: tokio_serial::Error) -> Self {
+        let hint = match e.kind {
+            _ if !path.exists() => "it doesn't exist. Check the module is plugged in, and look under /dev/serial/by-id for its path".into(),
+            tokio_serial::ErrorKind::Io(io::ErrorKind::PermissionDenied) => {
+                "permission denied. Add your user to the group that owns it (often `dialout` or `uucp`) and log in again".into()
+            }
+            _ => format!("{e}. Check nothing else has it open"),
+        };
+
+        Self::DeviceUnavailable(path.to_path_buf(), hint)
+    }
+}
+
+impl From<LoraError> for io::Error {
+    fn from(e: LoraError) -> Self {
+        match e {
+            LoraError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::NotFound, e),
+        }
+    }
+}
+
+/// Diagnostic events about the radio, separate from received frames.
+#[derive(Debug, Clone)]
+pub enum LoraEvent {
+    /// A frame was refused because it would overrun the duty cycle
+    BudgetExhausted { needed: Duration, remaining: Duration },
+    /// A received frame failed its checksum and was dropped
+    Corrupted { len: usize },
+    /// A frame couldn't be written to the module and wasn't sent
+    WriteFailed { len: usize, error: String },
+    /// The module took new radio parameters, see `Lora::reconfigure`
+    Reconfigured { settings: LoraSettings },
+    /// `errors` bad frames arrived in a row, see `LoraSettings::resync_after`
+    Desync { errors: usize },
+    /// What was buffered from the module after a `Desync` was dropped, and it was configured
+    /// again if `reconfigured`
+    Resynced { reconfigured: bool },
 }
 
 #[derive(Clone)]
 pub struct Lora {
-    writer: UnboundedSender<Vec<u8>>,
+    settings: Arc<Mutex<LoraSettings>>,
+    writer: Sender<Vec<u8>>,
+    control: UnboundedSender<Reconfigure>,
     reader: EventTarget<Vec<u8>>,
+    /// Frames for `recv`, with the RSSI the module reported them at, subscribed up front so none arrive unseen
+    /// between calls
+    inbox: Arc<tokio::sync::Mutex<EventStream<Received>>>,
+    budget: Arc<Mutex<AirtimeBudget>>,
+    ready: watch::Receiver<Option<Result<(), String>>>,
+    events: EventTarget<LoraEvent>,
+    corrupted: Arc<AtomicUsize>,
+    backlog: Arc<watch::Sender<Backlog>>,
+}
+
+/// What's still to go out, see `Lora::flush`.
+#[derive(Debug, Clone, Copy)]
+struct Backlog {
+    /// Frames handed to the writer task that it's yet to write
+    queued: usize,
+    /// When the module will have finished transmitting what's been written to it
+    on_air_until: Instant,
 }
 
 impl Lora {
-    pub async fn new(device: PathBuf, baud: u32, settings: LoraSettings, configure: bool) -> io::Result<Self> {
+    /// Opens the module on the serial port at `device`, failing with `LoraError::DeviceUnavailable`
+    /// if it can't be opened.
+    pub async fn new(device: PathBuf, baud: u32, settings: LoraSettings, configure: bool) -> Result<Self, LoraError> {
         debug!("Initializing LoRa with settings: {:?}", settings);
 
-        let serial = tokio_serial::new(device.display().to_string(), baud).open_native_async()?;
-        let (mut reader, mut writer) = split(serial);
+        let serial = tokio_serial::new(device.display().to_string(), baud)
+            .open_native_async()
+            .map_err(|e| LoraError::unavailable(&device, e))?;
+        Ok(Self::from_stream(serial, settings, configure).await?)
+    }
 
-        let data_codec = LengthDelimitedCodec::builder()
-            .length_field_length(LENGTH_FIELD_SIZE)
-            .max_frame_length(MAX_PAYLOAD_SIZE)
-            .little_endian()
-            .new_codec();
+    /// Runs over any byte stream that speaks like the module's serial port, such as a mock module.
+    /// Configuration carries on in the background, see `ready`.
+    pub async fn from_stream<S>(stream: S, settings: LoraSettings, configure: bool) -> io::Result<Self>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, mut writer) = split(stream);
+        let (tx, rx) = channel::<Vec<u8>>(settings.send_queue.max(1));
+        let (control, control_rx) = unbounded_channel();
+        let (ready_tx, ready) = watch::channel(None);
+        let (target, measured) = (EventTarget::new(), EventTarget::new());
+        let (events, corrupted) = (EventTarget::new(), Arc::new(AtomicUsize::new(0)));
+        let backlog = Arc::new(watch::Sender::new(Backlog { queued: 0, on_air_until: Instant::now() }));
 
-        if configure {
-            Self::configure(settings, &mut writer, &mut reader).await?;
-        }
+        spawn({
+            let target = target.clone();
+            let link = Link {
+                checksum: settings.checksum,
+                whitening: settings.whitening,
+                configure,
+                measured: measured.clone(),
+                events: events.clone(),
+                corrupted: corrupted.clone(),
+                backlog: backlog.clone(),
+            };
+            async move {
+                let mut lines = FramedRead::new(reader, LinesCodec::new());
+                let configured = match configure {
+                    true => Self::configure(settings, &mut writer, &mut lines).await,
+                    false => Ok(()),
+                };
+
+                if let Err(e) = &configured {
+                    error!("Failed to configure LoRa module: {e}");
+                }
+
+                let _ = ready_tx.send(Some(configured.as_ref().map(|_| ()).map_err(ToString::to_string)));
+                if configured.is_ok() {
+                    let data_codec = LoraCodec::new(settings.framing, MAX_PAYLOAD_SIZE);
+                    let writer = FramedWrite::new(writer, data_codec);
+                    Self::inner(Self::frames(lines, &settings), writer, rx, control_rx, target, link, settings);
+                }
+            }
+        });
+
+        let budget = Arc::new(Mutex::new(AirtimeBudget::new(settings.duty_cycle)));
+        let inbox = Arc::new(tokio::sync::Mutex::new(measured.as_stream()));
+        let settings = Arc::new(Mutex::new(settings));
+        Ok(Self { settings, writer: tx, control, reader: target, inbox, budget, ready, events, corrupted, backlog })
+    }
+
+    /// The settings in use, including any radio parameters changed by `reconfigure`.
+    pub fn settings(&self) -> LoraSettings { *self.settings.lock().unwrap_or_else(PoisonError::into_inner) }
+
+    /// Moves the module to the spread factor, frequency, bandwidth and transmit power in `settings`, without
+    /// restarting the transport. Frames already queued are held back while the module is told,
+    /// then sent with the new parameters. The rest of `settings` is fixed once running, so ignored.
+    /// Emits `LoraEvent::Reconfigured` once the module has acknowledged every command.
+    pub async fn reconfigure(&self, settings: LoraSettings) -> io::Result<()> {
+        settings.validate()?;
+
+        let settings = LoraSettings {
+            spread_factor: settings.spread_factor,
+            frequency_hz: settings.frequency_hz,
+            bandwidth_khz: settings.bandwidth_khz,
+            tx_power_dbm: settings.tx_power_dbm,
+            ..self.settings()
+        };
+
+        let ended = || io::Error::new(io::ErrorKind::BrokenPipe, "LoRa writer task ended");
+        let (done, result) = oneshot::channel();
+        self.control.send((settings, done)).map_err(|_| ended())?;
+        result.await.map_err(|_| ended())??;
+
+        *self.settings.lock().unwrap_or_else(PoisonError::into_inner) = settings;
+        self.events.emit(LoraEvent::Reconfigured { settings });
+        Ok(())
+    }
+
+    /// Transmit time left in the current duty cycle window, `Duration::MAX` when unlimited.
+    pub fn airtime_budget(&self) -> Duration { self.budget.lock().map(|mut b| b.remaining()).unwrap_or_default() }
+
+    /// Frames waiting to be written to the module, at most `LoraSettings::send_queue`.
+    pub fn queue_depth(&self) -> usize { self.writer.max_capacity() - self.writer.capacity() }
+
+    /// Diagnostic events about the radio, separate from received frames.
+    pub fn events(&self) -> &EventTarget<LoraEvent> { &self.events }
 
-        let writer = FramedWrite::new(writer, data_codec.clone());
-        let reader = FramedRead::new(reader, data_codec);
+    /// Frames dropped for failing their checksum since starting, see `LoraSettings::checksum`.
+    pub fn corrupted_frames(&self) -> usize { self.corrupted.load(Ordering::Relaxed) }
 
-        let (writer, reader) = Self::inner(reader, writer);
-        Ok(Self { writer, reader })
+    /// Spends the airtime needed to send `len` bytes, or reports that the budget can't cover it.
+    fn spend_airtime(&self, len: usize) -> io::Result<()> {
+        let settings = self.settings();
+        let needed = time_on_air(settings.spread_factor, settings.bandwidth_khz, len);
+        let mut budget = self.budget.lock().map_err(|_| io::Error::other("Airtime budget poisoned"))?;
+        if budget.spend(needed) {
+            return Ok(());
+        }
+
+        let remaining = budget.remaining();
+        self.events.emit(LoraEvent::BudgetExhausted { needed, remaining });
+        Err(io::Error::new(
+            io::ErrorKind::QuotaExceeded,
+            format!("Frame needs {needed:?} of airtime but only {remaining:?} remains"),
+        ))
     }
 
-    async fn _wait_for_ok(
-        reader: &mut FramedRead<ReadHalf<SerialStream>, LinesCodec>,
+    async fn wait_for_ok<S: AsyncRead>(
+        reader: &mut FramedRead<ReadHalf<S>, LinesCodec>,
         command_name: &str,
     ) -> io::Result<()> {
         match timeout(Duration::from_secs(5), reader.next()).await {
@@ -74,65 +351,149 @@ impl Lora {
         }
     }
 
-    async fn configure(
-        _settings: LoraSettings,
-        _writer: &mut WriteHalf<SerialStream>,
-        _reader: &mut ReadHalf<SerialStream>,
+    async fn configure<S: AsyncRead + AsyncWrite>(
+        settings: LoraSettings,
+        writer: &mut WriteHalf<S>,
+        reader: &mut FramedRead<ReadHalf<S>, LinesCodec>,
     ) -> io::Result<()> {
-        todo!()
-        // // 1. Send the Spread Factor (SF) command
-        // let sf_command = format!("AT+SF={}\r\n", settings.spread_factor);
-        // writer
-        //     .send(sf_command.as_bytes().to_vec().into())
-        //     .await
-        //     .map_err(|e| io::Error::other(format!("Failed to send SF command: {}", e)))?;
-        // Self::wait_for_ok(reader, "SF").await?;
-
-        // // 2. Send the Frequency command
-        // let freq_command = format!("AT+FREQ={}\r\n", settings.frequency_hz);
-        // writer
-        //     .send(freq_command.as_bytes().to_vec().into())
-        //     .await
-        //     .map_err(|e| io::Error::other(format!("Failed to send FREQ command: {}", e)))?;
-        // Self::wait_for_ok(reader, "FREQ").await?;
-
-        // // 3. Send the Bandwidth command
-        // let bw_command = format!("AT+BW={}\r\n", settings.bandwidth_khz);
-        // writer
-        //     .send(bw_command.as_bytes().to_vec().into())
-        //     .await
-        //     .map_err(|e| io::Error::other(format!("Failed to send BW command: {}", e)))?;
-        // Self::wait_for_ok(reader, "BW").await?;
-
-        // Ok(())
-    }
-
-    fn inner(
-        mut reader: FramedRead<ReadHalf<SerialStream>, LengthDelimitedCodec>,
-        mut writer: FramedWrite<WriteHalf<SerialStream>, LengthDelimitedCodec>,
-    ) -> (UnboundedSender<Vec<u8>>, EventTarget<Vec<u8>>) {
-        let (tx, mut rx) = unbounded_channel::<Vec<u8>>();
-        let target = EventTarget::new();
+        let commands = [
+            ("SF", format!("AT+SF={}\r\n", settings.spread_factor)),
+            ("FREQ", format!("AT+FREQ={}\r\n", settings.frequency_hz)),
+            ("BW", format!("AT+BW={}\r\n", settings.bandwidth_khz)),
+            ("PWR", format!("AT+PWR={}\r\n", settings.tx_power_dbm)),
+        ];
 
-        spawn({
-            let target = target.clone();
-            async move {
-                while let Ok(v) = Self::recv(&mut reader).await {
-                    target.emit(v);
+        for (name, command) in commands {
+            writer
+                .write_all(command.as_bytes())
+                .await
+                .map_err(|e| io::Error::other(format!("Failed to send {} command: {}", name, e)))?;
+            writer.flush().await?;
+            Self::wait_for_ok(reader, name).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Switches a reader from configuration responses to frames.
+    fn frames<S: AsyncRead>(
+        lines: FramedRead<ReadHalf<S>, LinesCodec>,
+        settings: &LoraSettings,
+    ) -> FramedRead<ReadHalf<S>, LoraCodec> {
+        // Anything the module sent after its last OK belongs to the data stream
+        let data_codec = LoraCodec::new(settings.framing, MAX_PAYLOAD_SIZE);
+        let buffered = lines.read_buffer().clone();
+        let mut reader = FramedRead::with_capacity(lines.into_inner(), data_codec, settings.read_buffer);
+        reader.read_buffer_mut().extend_from_slice(&buffered);
+        reader
+    }
+
+    /// Switches a reader from frames back to configuration responses.
+    fn lines<S: AsyncRead>(frames: FramedRead<ReadHalf<S>, LoraCodec>) -> FramedRead<ReadHalf<S>, LinesCodec> {
+        let buffered = frames.read_buffer().clone();
+        let mut lines = FramedRead::new(frames.into_inner(), LinesCodec::new());
+        lines.read_buffer_mut().extend_from_slice(&buffered);
+        lines
+    }
+
+    fn inner<S: AsyncRead + AsyncWrite + Send + 'static>(
+        mut reader: FramedRead<ReadHalf<S>, LoraCodec>,
+        mut writer: FramedWrite<WriteHalf<S>, LoraCodec>,
+        mut rx: Receiver<Vec<u8>>,
+        mut control: UnboundedReceiver<Reconfigure>,
+        target: EventTarget<Vec<u8>>,
+        link: Link,
+        mut settings: LoraSettings,
+    ) {
+        // One task owns both halves, so reconfiguring can have the module to itself between frames
+        spawn(async move {
+            let (mut reading, mut errors) = (true, 0);
+            loop {
+                select! {
+                    frame = Self::recv(&mut reader), if reading => match frame {
+                        Ok(v) => {
+                            errors += reader.decoder_mut().take_skipped();
+                            let rssi = reader.decoder_mut().take_rssi();
+                            match link.check(v) {
+                                Some(v) => {
+                                    errors = 0;
+                                    link.measured.emit((v.clone(), rssi));
+                                    target.emit(v);
+                                }
+                                None => errors += 1,
+                            }
+
+                            if settings.resync_after.is_some_and(|after| errors >= after) {
+                                link.events.emit(LoraEvent::Desync { errors });
+                                let reconfigured;
+                                (reader, writer, reconfigured) = Self::resync(reader, writer, &settings, link.configure).await;
+                                link.events.emit(LoraEvent::Resynced { reconfigured });
+                                errors = 0;
+                            }
+                        }
+                        Err(_) => reading = false,
+                    },
+                    Some(v) = rx.recv() => {
+                        // `send` is fire and forget, so failures are reported here rather than to the caller
+                        let sent = Self::send(&mut writer, &v).await;
+                        if let Err(e) = &sent {
+                            error!("Failed to write {} byte frame: {e}", v.len());
+                            link.events.emit(LoraEvent::WriteFailed { len: v.len(), error: e.to_string() });
+                        }
+
+                        let airtime = time_on_air(settings.spread_factor, settings.bandwidth_khz, v.len());
+                        link.backlog.send_modify(|backlog| {
+                            backlog.queued -= 1;
+                            if sent.is_ok() {
+                                backlog.on_air_until = backlog.on_air_until.max(Instant::now()) + airtime;
+                            }
+                        });
+                    }
+                    Some((new, done)) = control.recv() => {
+                        let mut lines = Self::lines(reader);
+                        let mut raw = writer.into_inner();
+                        let configured = Self::configure(new, &mut raw, &mut lines).await;
+                        match &configured {
+                            Ok(()) => settings = new,
+                            Err(e) => error!("Failed to reconfigure LoRa module: {e}"),
+                        }
+
+                        reader = Self::frames(lines, &settings);
+                        writer = FramedWrite::new(raw, LoraCodec::new(settings.framing, MAX_PAYLOAD_SIZE));
+                        let _ = done.send(configured);
+                    }
+                    else => break,
                 }
             }
         });
+    }
 
-        spawn(async move {
-            while let Some(v) = rx.recv().await {
-                Self::send(&mut writer, &v).await.unwrap();
+    /// Drops whatever's buffered from the module, along with any frame half read, then configures
+    /// it again if `configure`. Returns the reader and writer to carry on with, and whether it was configured.
+    async fn resync<S: AsyncRead + AsyncWrite>(
+        reader: FramedRead<ReadHalf<S>, LoraCodec>,
+        writer: FramedWrite<WriteHalf<S>, LoraCodec>,
+        settings: &LoraSettings,
+        configure: bool,
+    ) -> (FramedRead<ReadHalf<S>, LoraCodec>, FramedWrite<WriteHalf<S>, LoraCodec>, bool) {
+        debug!("Lost sync with LoRa module, dropping {} buffered bytes", reader.read_buffer().len());
+        let mut lines = Self::lines(reader);
+        lines.read_buffer_mut().clear();
+
+        let mut raw = writer.into_inner();
+        let reconfigured = configure && {
+            let configured = Self::configure(*settings, &mut raw, &mut lines).await;
+            if let Err(e) = &configured {
+                error!("Failed to reconfigure LoRa module while resyncing: {e}");
             }
-        });
+            configured.is_ok()
+        };
 
-        (tx, target)
+        let writer = FramedWrite::new(raw, LoraCodec::new(settings.framing, MAX_PAYLOAD_SIZE));
+        (Self::frames(lines, settings), writer, reconfigured)
     }
 
-    async fn send(stream: &mut FramedWrite<WriteHalf<SerialStream>, LengthDelimitedCodec>, data: &[u8]) -> io::Result<()> {
+    async fn send<S: AsyncWrite>(stream: &mut FramedWrite<WriteHalf<S>, LoraCodec>, data: &[u8]) -> io::Result<()> {
         let len = data.len();
         if len > MAX_PAYLOAD_SIZE {
             return Err(io::Error::new(
@@ -149,11 +510,11 @@ impl Lora {
         Ok(())
     }
 
-    async fn recv(reader: &mut FramedRead<ReadHalf<SerialStream>, LengthDelimitedCodec>) -> io::Result<Vec<u8>> {
+    async fn recv<S: AsyncRead>(reader: &mut FramedRead<ReadHalf<S>, LoraCodec>) -> io::Result<Vec<u8>> {
         match reader.next().await {
             Some(Ok(frame)) => {
                 debug!("Received frame with {} bytes:\n{:?}", frame.len(), String::from_utf8_lossy(&frame));
-                Ok(frame.to_vec())
+                Ok(frame)
             }
             Some(Err(e)) => {
                 debug!("Frame decode error: {}", e);
@@ -169,15 +530,115 @@ impl Lora {
 
 #[async_trait]
 impl PacketTransport for Lora {
-    async fn send(&self, data: &[u8]) -> io::Result<()> { self.writer.send(data.to_vec()).map_err(std::io::Error::other) }
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        // Refused here rather than by the writer task, which would only log it
+        if let Some(max) = self.max_frame().filter(|max| data.len() > *max) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Frame of {} bytes exceeds the {max} the module and framing can carry", data.len()),
+            ));
+        }
+
+        let settings = self.settings();
+        let mut frame = match settings.checksum {
+            true => with_checksum(data),
+            false => data.to_vec(),
+        };
+        if settings.whitening {
+            whiten(&mut frame);
+        }
+
+        // Room is reserved first, so a frame that can't be queued doesn't spend any airtime
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "LoRa writer task ended");
+        let permit = match settings.when_full {
+            QueueFull::Wait => self.writer.reserve().await.map_err(|_| closed())?,
+            QueueFull::Error => self.writer.try_reserve().map_err(|e| match e {
+                TrySendError::Full(_) => io::Error::new(io::ErrorKind::WouldBlock, "LoRa send queue is full"),
+                TrySendError::Closed(_) => closed(),
+            })?,
+        };
+
+        self.spend_airtime(frame.len())?;
+        self.backlog.send_modify(|backlog| backlog.queued += 1);
+        permit.send(frame);
+        Ok(())
+    }
 
-    async fn recv(&mut self) -> io::Result<Vec<u8>> {
-        self.reader
-            .as_stream()
+    /// Resolves once every frame queued so far has been written to the module, and the module's had
+    /// time to transmit them.
+    async fn flush(&self) -> io::Result<()> {
+        let mut backlog = self.backlog.subscribe();
+        let on_air_until = backlog.wait_for(|b| b.queued == 0).await.map_err(|_| io::Error::other("LoRa task ended"))?.on_air_until;
+        sleep_until(on_air_until.into()).await;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { Ok(self.recv_measured().await?.1) }
+
+    /// Reports the RSSI from each `+RCV` line with `Framing::Lines`, transparent mode doesn't pass it on.
+    async fn recv_measured(&mut self) -> io::Result<(TransportId, Vec<u8>, Option<i16>)> {
+        let received = self
+            .inbox
+            .lock()
+            .await
             .next()
             .await
-            .ok_or(std::io::Error::new(io::ErrorKind::BrokenPipe, "Reader channel was disconnected"))
-            .map(|v| Vec::clone(&*v))
+            .ok_or(std::io::Error::new(io::ErrorKind::BrokenPipe, "Reader channel was disconnected"))?;
+        Ok((TransportId::default(), received.0.clone(), received.1))
+    }
+
+    /// What fits through both the module and the framing, less room for the checksum if one's added.
+    fn max_frame(&self) -> Option<usize> {
+        let settings = self.settings();
+        let checksum = if settings.checksum { CHECKSUM_LEN } else { 0 };
+        Some(MAX_PAYLOAD_SIZE.min(settings.framing.max_payload()).saturating_sub(checksum))
+    }
+
+    /// Resolves once the module has acknowledged every configuration command.
+    async fn ready(&self) -> io::Result<()> {
+        let mut ready = self.ready.clone();
+        let state = ready.wait_for(Option::is_some).await.map_err(|_| io::Error::other("LoRa task ended"))?;
+        match &*state {
+            Some(Err(e)) => Err(io::Error::other(e.clone())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Link-layer checking of received frames, and accounting for sent ones, split out of `Lora` for
+/// the background task.
+struct Link {
+    checksum: bool,
+    whitening: bool,
+    /// Whether we configured the module, so should again when resyncing
+    configure: bool,
+    /// Frames that passed, with their RSSI, for `recv`
+    measured: EventTarget<Received>,
+    events: EventTarget<LoraEvent>,
+    corrupted: Arc<AtomicUsize>,
+    backlog: Arc<watch::Sender<Backlog>>,
+}
+
+impl Link {
+    /// The frame's payload, or `None` if it was corrupted in transit.
+    fn check(&self, mut frame: Vec<u8>) -> Option<Vec<u8>> {
+        if self.whitening {
+            whiten(&mut frame);
+        }
+
+        if !self.checksum {
+            return Some(frame);
+        }
+
+        let len = frame.len();
+        let payload = verify_checksum(frame);
+        if payload.is_none() {
+            debug!("Dropping {len} byte frame that failed its checksum");
+            self.corrupted.fetch_add(1, Ordering::Relaxed);
+            self.events.emit(LoraEvent::Corrupted { len });
+        }
+
+        payload
     }
 }
 
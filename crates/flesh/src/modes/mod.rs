@@ -4,6 +4,9 @@ use {
     std::ops::Deref,
 };
 
+pub mod airtime;
+pub mod framing;
+#[cfg(feature = "lora")]
 pub mod lora;
 
 #[async_trait]
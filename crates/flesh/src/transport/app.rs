@@ -0,0 +1,31 @@
+use {
+    crate::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        network::{Network, SendError},
+    },
+    futures::Stream,
+    std::sync::Arc,
+};
+
+/// One application's share of a `Network`, like a UDP port over the mesh: what it sends is tagged
+/// with its port, and it only hears messages tagged the same. See `Network::app`.
+#[derive(Clone)]
+pub struct App<T: PacketTransport> {
+    network: Network<T>,
+    port: u16,
+}
+
+impl<T: PacketTransport + Clone + 'static> App<T> {
+    pub(crate) fn new(network: Network<T>, port: u16) -> Self { Self { network, port } }
+
+    pub fn port(&self) -> u16 { self.port }
+
+    pub fn network(&self) -> &Network<T> { &self.network }
+
+    /// Sends `m` tagged with our port, see `Network::send`.
+    pub async fn send(&self, m: FLESHMessage) -> Result<(), SendError> { self.network.send(m.with_app(self.port)).await }
+
+    /// Every message we receive tagged with our port.
+    pub fn subscribe(&self) -> impl Stream<Item = Arc<FLESHMessage>> + use<T> { self.network.subscribe_app(self.port) }
+}
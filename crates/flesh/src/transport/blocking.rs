@@ -0,0 +1,59 @@
+use {
+    crate::{
+        events::EventStream,
+        transport::{
+            PacketTransport,
+            encoding::FLESHMessage,
+            keys::VerifyingKey,
+            network::{Network, SendError},
+        },
+    },
+    futures::StreamExt,
+    std::{
+        future::Future,
+        io,
+        sync::{Arc, Mutex},
+    },
+    tokio::runtime::{Builder, Runtime},
+    uuid::Uuid,
+};
+
+/// A synchronous facade over `Network` for embedders that don't run their own Tokio runtime,
+/// such as C-ABI modules loaded by the manager onto a plain `std::thread`.
+///
+/// Every call blocks the current thread on a runtime owned by this wrapper, so it must not be
+/// used from within an async context.
+pub struct BlockingNetwork<T: PacketTransport> {
+    runtime: Runtime,
+    network: Network<T>,
+    stream: Mutex<EventStream<FLESHMessage>>,
+}
+
+impl<T: PacketTransport + Clone + 'static> BlockingNetwork<T> {
+    /// Starts a `Network` over the given transport on a dedicated runtime.
+    pub fn new(transport: T) -> io::Result<Self> { Self::connect(async { Ok(transport) }) }
+
+    /// Like `new`, but for transports that need a runtime to be constructed (e.g. `Lora::new`).
+    pub fn connect(transport: impl Future<Output = io::Result<T>>) -> io::Result<Self> {
+        let runtime = Builder::new_multi_thread().enable_all().build()?;
+        let network = runtime.block_on(async { transport.await.map(Network::new) })?;
+        let stream = Mutex::new(network.as_stream());
+
+        Ok(Self { runtime, network, stream })
+    }
+
+    /// Sends a message, blocking until it has been handed to the transport.
+    pub fn send(&self, m: FLESHMessage) -> Result<(), SendError> { self.runtime.block_on(self.network.send(m)) }
+
+    /// Blocks until the next message arrives, or `None` if the network has shut down.
+    pub fn recv(&self) -> Option<Arc<FLESHMessage>> {
+        let mut stream = self.stream.lock().ok()?;
+        self.runtime.block_on(stream.next())
+    }
+
+    /// Resolves a node's verifying key, blocking until it's known or the resolution times out.
+    pub fn resolve(&self, id: Uuid) -> anyhow::Result<VerifyingKey> { self.runtime.block_on(self.network.resolve(id)) }
+
+    /// The async network this facade drives.
+    pub fn network(&self) -> &Network<T> { &self.network }
+}
@@ -0,0 +1,232 @@
+use {
+    crate::transport::{
+        PacketTransport,
+        network::{
+            ANNOUNCE_JITTER_MS, CLOCK_SKEW_SECS, DEFAULT_MAX_HOPS, MAX_ANNOUNCE_INTERVAL_SECS, MIN_ANNOUNCE_INTERVAL_SECS, Network, NetworkState, RESOLVE_BURST,
+            RESOLVE_RATE_PER_SEC, SharedAnnouncements, SharedResolution,
+        },
+    },
+    std::{ops::Range, time::Duration},
+    uuid::Uuid,
+};
+
+/// Tunables for a `Network`, set through `NetworkBuilder`.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// Tag targeted sends with a per-peer `seq` header, so receivers can detect lost messages
+    pub sequence: bool,
+    /// How many relays a message may pass through by default
+    pub max_hops: u8,
+    /// Split messages too large for the transport into parts, rather than failing with `SendError::TooLarge`
+    pub fragment: bool,
+    /// Shortest and longest time between announcements, see `NetworkBuilder::announce_interval`
+    pub announce_interval: Range<Duration>,
+    /// Most time added at random to each wait between announcements
+    pub announce_jitter: Duration,
+    /// Time between presence beacons, if we send them, see `NetworkBuilder::beacon_interval`
+    pub beacon_interval: Option<Duration>,
+    /// Key requests we'll put on the air per second, see `NetworkBuilder::resolve_rate`
+    pub resolve_rate: u32,
+    /// Key requests we'll send back to back before being held to `resolve_rate`
+    pub resolve_burst: u32,
+    /// Most messages we'll relay at once, see `NetworkBuilder::max_concurrent_relays`
+    pub max_relays: Option<usize>,
+    /// Pass on messages we're asked to relay, see `NetworkBuilder::forward`
+    pub forward: bool,
+    /// Never transmit, see `NetworkBuilder::passive`
+    pub passive: bool,
+    /// Drop received messages that fail their signature or can't be decrypted, see `NetworkBuilder::check_inbound`
+    pub check_inbound: bool,
+    /// Furthest a received message's timestamp may be from our clock, see `NetworkBuilder::clock_skew`
+    pub clock_skew: Option<Duration>,
+    /// Largest frame addressed to us we'll accept, see `NetworkBuilder::max_inbound_frame`
+    pub max_inbound_frame: Option<usize>,
+    /// Most messages held for offline peers, and for how long, see `NetworkBuilder::mailbox`
+    pub mailbox: Option<(usize, Duration)>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            sequence: false,
+            max_hops: DEFAULT_MAX_HOPS,
+            fragment: true,
+            announce_interval: Duration::from_secs(MIN_ANNOUNCE_INTERVAL_SECS)..Duration::from_secs(MAX_ANNOUNCE_INTERVAL_SECS),
+            announce_jitter: Duration::from_millis(ANNOUNCE_JITTER_MS),
+            beacon_interval: None,
+            resolve_rate: RESOLVE_RATE_PER_SEC,
+            resolve_burst: RESOLVE_BURST,
+            max_relays: None,
+            forward: true,
+            passive: false,
+            check_inbound: false,
+            clock_skew: Some(Duration::from_secs(CLOCK_SKEW_SECS)),
+            max_inbound_frame: None,
+            mailbox: None,
+        }
+    }
+}
+
+/// Configures a `Network` before starting it.
+pub struct NetworkBuilder<T: PacketTransport> {
+    transport: T,
+    config: NetworkConfig,
+    state: Option<NetworkState>,
+    id: Option<Uuid>,
+    shared: Option<SharedResolution>,
+    announcements: Option<SharedAnnouncements>,
+}
+
+impl<T: PacketTransport + Clone + 'static> NetworkBuilder<T> {
+    pub fn new(transport: T) -> Self {
+        Self { transport, config: NetworkConfig::default(), state: None, id: None, shared: None, announcements: None }
+    }
+
+    /// Number targeted messages per peer, emitting `NetworkEvent::Gap` on the receiver when some go missing.
+    pub fn sequence(mut self, enabled: bool) -> Self {
+        self.config.sequence = enabled;
+        self
+    }
+
+    /// Relays a message may pass through before being dropped, bounding how far a relay loop can spread.
+    /// Individual sends can override this with `Network::send_with_hops`.
+    pub fn max_hops(mut self, hops: u8) -> Self {
+        self.config.max_hops = hops;
+        self
+    }
+
+    /// Whether messages over the transport's frame limit are split automatically (the default).
+    /// When disabled, sending one fails with `SendError::TooLarge`.
+    pub fn fragment(mut self, enabled: bool) -> Self {
+        self.config.fragment = enabled;
+        self
+    }
+
+    /// Bounds for how often we announce ourselves. The interval grows towards `max` while the
+    /// nodes around us are stable, saving airtime, and drops to `min` when they change.
+    ///
+    /// Panics if `min` is greater than `max`.
+    pub fn announce_interval(mut self, min: Duration, max: Duration) -> Self {
+        assert!(min <= max, "Announce interval minimum {min:?} is over its maximum {max:?}");
+        self.config.announce_interval = min..max;
+        self
+    }
+
+    /// Adds up to `max` to each wait between announcements, chosen at random, so nodes powered
+    /// up together spread their announcements out. Waits never drop below the interval's minimum.
+    pub fn announce_jitter(mut self, max: Duration) -> Self {
+        self.config.announce_jitter = max;
+        self
+    }
+
+    /// Sends a beacon every `every` (plus jitter) between announcements, a few bytes saying we're still
+    /// here. Nodes that know us take it as proof we're in range, keeping their routes to us warm, while
+    /// those that don't ignore it rather than asking for our key, so it's far lighter than announcing.
+    pub fn beacon_interval(mut self, every: Duration) -> Self {
+        self.config.beacon_interval = Some(every);
+        self
+    }
+
+    /// Paces key requests to `per_sec`, after an initial `burst`, so a crowd of new nodes appearing
+    /// at once doesn't swamp the link. Nodes being resolved for a send go ahead of background discovery.
+    pub fn resolve_rate(mut self, per_sec: u32, burst: u32) -> Self {
+        self.config.resolve_rate = per_sec;
+        self.config.resolve_burst = burst;
+        self
+    }
+
+    /// Declines to relay more than `max` messages at once, answering the sender with a `RelayFailure`
+    /// instead, so a node on a busy mesh isn't run ragged forwarding for everyone else.
+    pub fn max_concurrent_relays(mut self, max: usize) -> Self {
+        self.config.max_relays = Some(max);
+        self
+    }
+
+    /// Whether we pass on messages other nodes ask us to relay (the default). When disabled, every
+    /// one is refused with a `RelayFailure`, for nodes that mustn't spend their battery or airtime
+    /// on others' traffic. We still answer relay requests for the nodes we can reach, so routes are
+    /// still learned through us, and messages relayed to us are still delivered; only forwarding
+    /// them on is refused. Takes precedence over `max_concurrent_relays`.
+    pub fn forward(mut self, enabled: bool) -> Self {
+        self.config.forward = enabled;
+        self
+    }
+
+    /// Only listens: no announcements, no answering key requests or pings, and no relaying, for
+    /// sniffers and other nodes that mustn't be heard on the air. Messages are still decoded and
+    /// delivered, and keys overheard in other nodes' exchanges are still learned, but sending fails.
+    pub fn passive(mut self, enabled: bool) -> Self {
+        self.config.passive = enabled;
+        self
+    }
+
+    /// Checks received messages before delivering them, dropping those signed by a known node whose
+    /// signature doesn't hold up, or encrypted for someone else. Senders that asked for a receipt
+    /// are told why, see `Network::send_reliable`.
+    pub fn check_inbound(mut self, enabled: bool) -> Self {
+        self.config.check_inbound = enabled;
+        self
+    }
+
+    /// Drops received messages timestamped more than `max` before or after our own clock, five minutes
+    /// by default, so a sender can't dodge expiry or jump the queue by lying about when it sent something.
+    /// Drops are counted by `Network::mistimed_messages`. `None` accepts any timestamp, for nodes
+    /// without a reliable clock.
+    pub fn clock_skew(mut self, max: Option<Duration>) -> Self {
+        self.config.clock_skew = max;
+        self
+    }
+
+    /// Refuses frames addressed to us (or to be relayed by us) over `max` bytes, answering their
+    /// sender with a `TooLarge` naming the limit. `Network::send_reliable` splits refused messages
+    /// to fit and tries again. Broadcasts are never refused.
+    pub fn max_inbound_frame(mut self, max: usize) -> Self {
+        self.config.max_inbound_frame = Some(max);
+        self
+    }
+
+    /// Holds on to up to `capacity` messages we're asked to relay to a node that's gone quiet, for
+    /// up to `ttl` each, rather than passing them on to no one. They're sent on when the node next
+    /// announces itself or asks for them, see `Network::pull_mail`. A node in range counts as quiet
+    /// once it's gone longer than the announcement interval's maximum (plus jitter) without announcing.
+    pub fn mailbox(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.config.mailbox = Some((capacity, ttl));
+        self
+    }
+
+    /// Resumes from a snapshot taken with `Network::export_state` rather than starting with a new identity.
+    pub fn state(mut self, state: NetworkState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Uses `id` rather than a random one, for nodes that need a stable, meaningful id, such as one
+    /// derived from a name. Ignored when resuming from `state`, which carries its own.
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Shares what `other` knows about other nodes, and learns, see `Network::share_resolution`.
+    /// Routes are shared as they are, so this suits networks that reach the same nodes, such as
+    /// a node's LoRa and IP links to the same mesh.
+    pub fn share_resolution(mut self, other: SharedResolution) -> Self {
+        self.shared = Some(other);
+        self
+    }
+
+    /// Skips our scheduled announcements when `other` (or another network sharing it) has just
+    /// announced the same node, for networks sharing one transport, such as one per app on a node
+    /// resumed from the same state. Between them the node is announced about as often as one would.
+    /// Networks on different transports need their own announcements, so shouldn't share.
+    pub fn share_announcements(mut self, other: SharedAnnouncements) -> Self {
+        self.announcements = Some(other);
+        self
+    }
+
+    /// Starts the network.
+    pub fn build(self) -> Network<T> {
+        let state = self.state.unwrap_or_else(|| NetworkState::new(self.id.unwrap_or_else(Uuid::new_v4)));
+        Network::restore(self.transport, self.config, state, self.shared.unwrap_or_default(), self.announcements.unwrap_or_default())
+    }
+}
@@ -0,0 +1,334 @@
+use {
+    crate::transport::{PacketTransport, encoding::FLESHMessage, network::Network, status::Status},
+    std::{
+        collections::{BTreeMap, HashMap},
+        io,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+        time::{Duration, Instant},
+    },
+    tokio::{
+        io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf, split},
+        select,
+        sync::{
+            Mutex,
+            mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+        },
+        time::{interval, sleep_until},
+    },
+    tracing::{debug, warn},
+    uuid::Uuid,
+};
+
+pub const CHANNEL_SEGMENT_BYTES: usize = 256;
+pub const CHANNEL_WINDOW: usize = 16;
+pub const CHANNEL_RTO_MS: u64 = 500;
+pub const CHANNEL_MAX_RETRIES: u32 = 20;
+/// Longest we'll hold back an ack hoping to cover more segments with it, well within `CHANNEL_RTO_MS`.
+pub const CHANNEL_ACK_DELAY_MS: u64 = 100;
+/// How long a closed channel's id is remembered, as long as the other end could keep resending to it.
+pub const CHANNEL_TIME_WAIT_MS: u64 = CHANNEL_RTO_MS * CHANNEL_MAX_RETRIES as u64;
+
+/// Bytes buffered between the application and the channel's task, each way.
+const PIPE_BYTES: usize = 64 * 1024;
+/// Most out of order segments listed in a single ack.
+const MAX_SACK: usize = 32;
+
+/// An ordered, reliable byte stream to another node, see `Network::open_channel`.
+///
+/// Writes are split into numbered segments, retransmitted until the other end acknowledges them,
+/// and put back in order on arrival. Acks are cumulative and held back briefly, so a burst of
+/// segments is acknowledged all at once rather than one by one. Shutting down (or dropping) the
+/// channel ends the stream for the other node, once everything written before it has been delivered.
+pub struct Channel {
+    id: Uuid,
+    peer: Uuid,
+    pipe: DuplexStream,
+}
+
+impl Channel {
+    pub fn id(&self) -> Uuid { self.id }
+
+    /// The node on the other end.
+    pub fn peer(&self) -> Uuid { self.peer }
+}
+
+impl AsyncRead for Channel {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.pipe).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Channel {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.pipe).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.pipe).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.pipe).poll_shutdown(cx)
+    }
+}
+
+/// A piece of a channel's conversation, carried in a `Status::Channel` message.
+#[derive(Debug, Clone)]
+pub(crate) enum Segment {
+    /// The `at`th piece of the stream, the last if `fin` is set
+    Data { at: u32, data: Vec<u8>, fin: bool },
+    /// Everything before `next` arrived, as did the (later) segments in `sack`
+    Ack { next: u32, sack: Vec<u32> },
+}
+
+impl Segment {
+    pub(crate) fn to_message(&self, channel: Uuid, peer: Uuid) -> FLESHMessage {
+        let message = FLESHMessage::new(Status::Channel).with_target(peer).with_header("channel", channel);
+        match self {
+            Segment::Data { at, data, fin } => {
+                let message = message.with_header("at", at.to_le_bytes().to_vec()).with_body(data.clone());
+                match fin {
+                    true => message.with_header("fin", []),
+                    false => message,
+                }
+            }
+            Segment::Ack { next, sack } => message
+                .with_header("ack", next.to_le_bytes().to_vec())
+                .with_header("sack", sack.iter().flat_map(|at| at.to_le_bytes()).collect::<Vec<_>>()),
+        }
+    }
+
+    /// The channel a message belongs to and what it says, `None` if it isn't a valid segment.
+    pub(crate) fn from_message(m: &FLESHMessage) -> Option<(Uuid, Self)> {
+        let number = |h: &str| Some(u32::from_le_bytes(m.headers.get(h)?.as_slice().try_into().ok()?));
+        let channel = Uuid::from_slice(m.headers.get("channel")?).ok()?;
+
+        let segment = match (number("at"), number("ack")) {
+            (Some(at), _) => Segment::Data { at, data: m.body.clone(), fin: m.headers.contains_key("fin") },
+            (None, Some(next)) => Segment::Ack {
+                next,
+                sack: m.headers.get("sack")?.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect(),
+            },
+            (None, None) => return None,
+        };
+
+        Some((channel, segment))
+    }
+}
+
+/// Open channels, and those opened by other nodes waiting to be accepted.
+pub(crate) struct ChannelTable {
+    /// Each open channel's peer, and where its segments go
+    open: Mutex<HashMap<Uuid, (Uuid, UnboundedSender<Segment>)>>,
+    /// Channels closed within `CHANNEL_TIME_WAIT_MS`, with their peer and the segment they were waiting on next
+    closed: Mutex<HashMap<Uuid, (Uuid, u32, Instant)>>,
+    incoming: UnboundedSender<Channel>,
+    accepted: Mutex<UnboundedReceiver<Channel>>,
+}
+
+impl ChannelTable {
+    pub(crate) fn new() -> Self {
+        let (incoming, accepted) = unbounded_channel();
+        Self { open: Default::default(), closed: Default::default(), incoming, accepted: Mutex::new(accepted) }
+    }
+
+    /// Passes a segment from `from` to its channel, `false` if there's no such channel. Segments from
+    /// anyone but the channel's peer are dropped, as anyone who learns its id could otherwise write to it.
+    pub(crate) async fn route(&self, id: Uuid, from: Uuid, segment: Segment) -> bool {
+        let open = self.open.lock().await;
+        let Some((peer, tx)) = open.get(&id) else { return false };
+        match *peer == from {
+            true => {
+                let _ = tx.send(segment);
+            }
+            false => warn!("Dropping a segment for channel {id} from {from}, who isn't on it"),
+        }
+
+        true
+    }
+
+    /// The peer of channel `id` and the segment it was waiting on next, if it closed within
+    /// `CHANNEL_TIME_WAIT_MS`. Segments still arriving for it are stragglers, not a new channel.
+    pub(crate) async fn closed(&self, id: Uuid) -> Option<(Uuid, u32)> {
+        let mut closed = self.closed.lock().await;
+        closed.retain(|_, (.., at)| at.elapsed() < Duration::from_millis(CHANNEL_TIME_WAIT_MS));
+        closed.get(&id).map(|(peer, next, _)| (*peer, *next))
+    }
+
+    pub(crate) async fn accept(&self) -> Option<Channel> { self.accepted.lock().await.recv().await }
+
+    pub(crate) fn offer(&self, channel: Channel) { let _ = self.incoming.send(channel); }
+}
+
+/// Starts the task behind a channel, returning the application's end of it.
+pub(crate) async fn start<T: PacketTransport + Clone + 'static>(
+    network: Network<T>,
+    table: Arc<ChannelTable>,
+    id: Uuid,
+    peer: Uuid,
+) -> Channel {
+    let (ours, theirs) = tokio::io::duplex(PIPE_BYTES);
+    let (tx, rx) = unbounded_channel();
+    table.open.lock().await.insert(id, (peer, tx));
+
+    tokio::spawn(async move {
+        let next_in = ChannelTask::new(network, id, peer).run(theirs, rx).await;
+        table.open.lock().await.remove(&id);
+        table.closed.lock().await.insert(id, (peer, next_in, Instant::now()));
+    });
+
+    Channel { id, peer, pipe: ours }
+}
+
+/// A segment we've sent that's yet to be acknowledged.
+struct Unacked {
+    segment: Segment,
+    sent: Instant,
+    tries: u32,
+}
+
+struct ChannelTask<T: PacketTransport> {
+    network: Network<T>,
+    id: Uuid,
+    peer: Uuid,
+    /// Number of the next segment we'll send
+    next_out: u32,
+    /// Number of the next segment we're waiting on
+    next_in: u32,
+    unacked: BTreeMap<u32, Unacked>,
+    /// Segments that arrived ahead of `next_in`
+    early: BTreeMap<u32, (Vec<u8>, bool)>,
+    /// Segments received since we last acknowledged any
+    unacked_in: usize,
+    /// When the ack held back for them has to go out by
+    ack_due: Option<Instant>,
+    finished_writing: bool,
+    finished_reading: bool,
+}
+
+impl<T: PacketTransport + Clone + 'static> ChannelTask<T> {
+    fn new(network: Network<T>, id: Uuid, peer: Uuid) -> Self {
+        Self {
+            network,
+            id,
+            peer,
+            next_out: 0,
+            next_in: 0,
+            unacked: BTreeMap::new(),
+            early: BTreeMap::new(),
+            unacked_in: 0,
+            ack_due: None,
+            finished_writing: false,
+            finished_reading: false,
+        }
+    }
+
+    /// Runs the channel until it's closed, returning the number of the segment it was waiting on next.
+    async fn run(mut self, pipe: DuplexStream, mut segments: UnboundedReceiver<Segment>) -> u32 {
+        // Whoever opened the channel may not have been resolved yet, and we can't answer until they are
+        if let Err(e) = self.network.resolve(self.peer).await {
+            warn!("Channel {} can't reach {}: {e}", self.id, self.peer);
+            return self.next_in;
+        }
+
+        let (mut written, mut readable) = split(pipe);
+        let mut retransmit = interval(Duration::from_millis(CHANNEL_RTO_MS / 2));
+        let mut buf = vec![0; CHANNEL_SEGMENT_BYTES];
+
+        while !(self.finished_writing && self.unacked.is_empty() && self.finished_reading) {
+            select! {
+                read = written.read(&mut buf), if !self.finished_writing && self.unacked.len() < CHANNEL_WINDOW => {
+                    let data = read.map(|n| buf[..n].to_vec()).unwrap_or_default();
+                    self.finished_writing = data.is_empty();
+                    self.transmit(Segment::Data { at: self.next_out, data, fin: self.finished_writing }).await;
+                    self.next_out += 1;
+                }
+                segment = segments.recv() => match segment {
+                    Some(Segment::Ack { next, sack }) => self.unacked.retain(|at, _| *at >= next && !sack.contains(at)),
+                    Some(Segment::Data { at, data, fin }) => {
+                        // Nothing beyond the window should have been sent yet, so isn't worth holding on to
+                        let in_order = at == self.next_in;
+                        if at >= self.next_in && at < self.next_in.saturating_add(CHANNEL_WINDOW as u32) {
+                            self.early.insert(at, (data, fin));
+                        }
+
+                        while let Some((data, fin)) = self.early.remove(&self.next_in) {
+                            self.next_in += 1;
+                            // The application may have stopped reading, in which case the data's just dropped
+                            let _ = readable.write_all(&data).await;
+                            if fin {
+                                self.finished_reading = true;
+                                let _ = readable.shutdown().await;
+                            }
+                        }
+
+                        // The sender hears straight away when it's closing, resending (so missed our last ack),
+                        // out of room to send more, or something's gone missing in between
+                        self.unacked_in += 1;
+                        match fin || !in_order || self.unacked_in >= CHANNEL_WINDOW {
+                            true => self.ack().await,
+                            false => {
+                                let due = Instant::now() + Duration::from_millis(CHANNEL_ACK_DELAY_MS);
+                                self.ack_due.get_or_insert(due);
+                            }
+                        }
+                    }
+                    None => break,
+                },
+                _ = sleep_until(self.ack_due.unwrap_or_else(Instant::now).into()), if self.ack_due.is_some() => {
+                    self.ack().await
+                }
+                _ = retransmit.tick() => {
+                    if !self.retransmit().await {
+                        warn!("Channel {} to {} gave up after {CHANNEL_MAX_RETRIES} retries", self.id, self.peer);
+                        break;
+                    }
+                }
+            }
+        }
+
+        debug!("Channel {} to {} closed", self.id, self.peer);
+        self.next_in
+    }
+
+    /// Sends a data segment, holding on to it until it's acknowledged.
+    async fn transmit(&mut self, segment: Segment) {
+        self.send(&segment).await;
+        if let Segment::Data { at, .. } = segment {
+            self.unacked.insert(at, Unacked { segment, sent: Instant::now(), tries: 1 });
+        }
+    }
+
+    /// Acknowledges everything received so far in one go.
+    async fn ack(&mut self) {
+        (self.unacked_in, self.ack_due) = (0, None);
+        let sack = self.early.keys().take(MAX_SACK).copied().collect();
+        self.send(&Segment::Ack { next: self.next_in, sack }).await;
+    }
+
+    /// Resends anything unacknowledged for too long, `false` if something's run out of retries.
+    async fn retransmit(&mut self) -> bool {
+        let rto = Duration::from_millis(CHANNEL_RTO_MS);
+        let due = self.unacked.iter().filter(|(_, u)| u.sent.elapsed() >= rto).map(|(at, _)| *at).collect::<Vec<_>>();
+        for at in due {
+            let Some(unacked) = self.unacked.get_mut(&at) else { continue };
+            if unacked.tries >= CHANNEL_MAX_RETRIES {
+                return false;
+            }
+
+            (unacked.sent, unacked.tries) = (Instant::now(), unacked.tries + 1);
+            let segment = unacked.segment.clone();
+            self.send(&segment).await;
+        }
+
+        true
+    }
+
+    async fn send(&self, segment: &Segment) {
+        if let Err(e) = self.network.send(segment.to_message(self.id, self.peer)).await {
+            debug!("Failed to send on channel {}: {e}", self.id);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+type Clock = Rc<dyn Fn() -> u64>;
+
+thread_local! {
+    static CLOCK: RefCell<Option<Clock>> = const { RefCell::new(None) };
+}
+
+/// Seconds since the unix epoch, by the clock set on this thread with `set_clock`, or the system's.
+/// Messages are timestamped, and checked for expiry, by this.
+pub fn now() -> u64 {
+    match CLOCK.with_borrow(Clone::clone) {
+        Some(clock) => clock(),
+        None => SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    }
+}
+
+/// Has `now` read `clock` on this thread, rather than the system's, until the returned guard's
+/// dropped, so tests can pin the time. Being per thread, it suits single threaded runtimes (such
+/// as `#[tokio::test]`'s default), and leaves tests running alongside alone.
+pub fn set_clock(clock: impl Fn() -> u64 + 'static) -> ClockGuard {
+    ClockGuard(CLOCK.replace(Some(Rc::new(clock))))
+}
+
+/// Puts back the clock `set_clock` replaced when dropped.
+#[must_use = "the clock is put back as soon as the guard's dropped"]
+pub struct ClockGuard(Option<Clock>);
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) { CLOCK.set(self.0.take()); }
+}
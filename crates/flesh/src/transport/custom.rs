@@ -0,0 +1,39 @@
+use {
+    crate::transport::status::{Status, StatusType},
+    std::{collections::BTreeMap, sync::RwLock},
+    thiserror::Error,
+};
+
+/// Names and categories applications have given to codes outside the standard set.
+static REGISTRY: RwLock<BTreeMap<u8, (&'static str, StatusType)>> = RwLock::new(BTreeMap::new());
+
+#[derive(Debug, Error)]
+pub enum RegisterError {
+    #[error("Status {0} is a standard status")]
+    Standard(u8),
+    #[error("Custom status {0} is already registered as {1}")]
+    Taken(u8, &'static str),
+}
+
+impl Status {
+    /// Gives a custom code a name and category, so it reports them from `name`/`as_type` and takes
+    /// part in `is_ok` like a standard status. Unregistered custom codes stay `StatusType::Unknown`.
+    pub fn register_custom(code: u8, name: &'static str, kind: StatusType) -> Result<Self, RegisterError> {
+        if Self::STANDARD.iter().any(|s| s.as_u8() == code) {
+            return Err(RegisterError::Standard(code));
+        }
+
+        let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+        if let Some((existing, _)) = registry.get(&code) {
+            return Err(RegisterError::Taken(code, existing));
+        }
+
+        registry.insert(code, (name, kind));
+        Ok(Self::Custom(code))
+    }
+}
+
+/// The name and category registered for a custom code, if any.
+pub(crate) fn lookup(code: u8) -> Option<(&'static str, StatusType)> {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner()).get(&code).copied()
+}
@@ -1,54 +1,147 @@
+#[cfg(feature = "crypto")]
 use {
-    crate::transport::status::Status,
     chacha20poly1305::{
         ChaCha20Poly1305,
         aead::{Aead, KeyInit},
     },
-    ed25519_dalek::{Signature, SigningKey, VerifyingKey, ed25519::signature::Signer},
-    postcard,
     rand_core::{OsRng, RngCore},
+    x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey, StaticSecret},
+};
+use {
+    crate::transport::{
+        TransportId, clock,
+        fragment::Fragment,
+        keys::{SigningKey, VerifyingKey},
+        scheme::{ED25519, MessageSigner, MessageVerifier},
+        status::Status,
+    },
+    postcard,
     serde::{Deserialize, Serialize},
+    sha2::{Digest, Sha256},
     std::{
-        collections::HashMap,
+        collections::BTreeMap,
         fmt::{Debug, Display},
-        time::SystemTime,
+        time::Duration,
     },
     thiserror::Error,
     uuid::Uuid,
-    x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey, StaticSecret},
 };
 
+/// Headers the protocol sets and reads itself, which applications setting them would break:
+/// encryption, signing, routing and sequencing, receipts, broadcast collection, groups and channels.
+pub const RESERVED_HEADERS: &[&str] = &[
+    "signature",
+    "ephemeral_key",
+    "nonce",
+    "scheme",
+    "for",
+    "to",
+    "from",
+    "via",
+    "seq",
+    "hops",
+    "self",
+    "key",
+    "status",
+    "max",
+    "beacon",
+    "receipt",
+    "receipt_for",
+    "collect",
+    "app",
+    "group",
+    "epoch",
+    "group_key",
+    "channel",
+    "at",
+    "fin",
+    "ack",
+    "sack",
+];
+
+/// Version of the wire format, carried first in every message. Messages of any other version are
+/// refused by `FLESHMessage::deserialize`, as there's no telling what the rest of them means.
+pub const WIRE_VERSION: u16 = 1;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FLESHMessage {
     pub version: u16,
     pub target: Option<Uuid>,
     pub sender: Option<Uuid>,
     pub timestamp: u64,
-    pub headers: HashMap<String, Vec<u8>>,
+    pub headers: BTreeMap<String, Vec<u8>>,
     pub body: Vec<u8>,
     pub signature: Option<Vec<u8>>,
     pub status: Status,
+    /// Where this message sits in a larger one split to fit the transport, see `InternalMessage::split`
+    pub fragment: Option<Fragment>,
+    /// The link this message arrived on, `None` for messages created locally. Never sent.
+    #[serde(skip)]
+    pub source: Option<TransportId>,
 }
 
 impl FLESHMessage {
-    pub fn new(status: Status) -> Self {
+    /// A message timestamped now, by `clock::now`.
+    pub fn new(status: Status) -> Self { Self::new_at(status, clock::now()) }
+
+    /// A message timestamped `unix_secs` seconds after the unix epoch.
+    pub fn new_at(status: Status, unix_secs: u64) -> Self {
         Self {
             status,
-            version: env!("CARGO_PKG_VERSION").split_once('.').unwrap().0.parse().unwrap(),
+            version: WIRE_VERSION,
             target: None,
             sender: None,
-            timestamp: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(),
-            headers: HashMap::new(),
+            timestamp: unix_secs,
+            headers: BTreeMap::new(),
             body: Vec::new(),
             signature: None,
+            fragment: None,
+            source: None,
         }
     }
 
+    /// Whether at least `ttl` has passed since the message was timestamped, by `clock::now`.
+    pub fn is_expired(&self, ttl: Duration) -> bool { clock::now().saturating_sub(self.timestamp) >= ttl.as_secs() }
+
+    /// Starts a message that's checked as it's finished, see `MessageBuilder`.
+    pub fn builder(status: Status) -> MessageBuilder { MessageBuilder { message: Self::new(status), encrypt_for: None } }
+
+    pub fn with_status(mut self, status: Status) -> Self {
+        self.status = status;
+        self
+    }
+
     pub fn with_target(mut self, target: Uuid) -> Self {
         self.target = Some(target);
         self
     }
 
+    /// Asks the recipient to confirm it got the message, see `Network::send_reliable`. Signed messages
+    /// need this before signing, as adding it afterwards would break the signature.
+    pub fn with_receipt(self) -> Self { self.with_header("receipt", Uuid::new_v4()) }
+
+    /// The id the recipient should confirm receipt with, if it's been asked to.
+    pub fn receipt(&self) -> Option<Uuid> { self.headers.get("receipt").and_then(|id| Uuid::from_slice(id).ok()) }
+
+    /// Tags the message for the application on `port` at the other end, see `Network::app`.
+    pub fn with_app(self, port: u16) -> Self { self.with_header("app", port.to_le_bytes().to_vec()) }
+
+    /// The application port the message is for, if it's been tagged with one.
+    pub fn app(&self) -> Option<u16> { Some(u16::from_le_bytes(self.headers.get("app")?.as_slice().try_into().ok()?)) }
+
+    /// Tags the message with an id from outside the mesh, such as a bridged HTTP request's, for
+    /// tracing it end to end. Replies, receipts and relay failures about the message carry it back.
+    pub fn with_correlation_id(self, id: &str) -> Self { self.with_header("correlation", id) }
+
+    /// The id the message was tagged with by `with_correlation_id`, or echoed from the one it answers.
+    pub fn correlation_id(&self) -> Option<&str> { std::str::from_utf8(self.headers.get("correlation")?).ok() }
+
+    /// Makes the message a broadcast.
+    pub fn clear_target(mut self) -> Self {
+        self.target = None;
+        self
+    }
+
     pub fn with_sender(mut self, sender: Uuid) -> Self {
         self.sender = Some(sender);
         self
@@ -59,45 +152,93 @@ impl FLESHMessage {
         self
     }
 
+    /// Like `with_header`, but refusing the names in `RESERVED_HEADERS`. Anything setting headers on
+    /// behalf of someone else, such as a bridge taking them from a request, should go through this.
+    pub fn try_with_header(self, key: impl Display, value: impl Into<Vec<u8>>) -> Result<Self, MessageError> {
+        let key = key.to_string();
+        match RESERVED_HEADERS.contains(&key.as_str()) {
+            true => Err(MessageError::ReservedHeader(key)),
+            false => Ok(self.with_header(key, value)),
+        }
+    }
+
     pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
         self.body = body.into();
         self
     }
 
+    pub fn with_fragment(mut self, fragment: Fragment) -> Self {
+        self.fragment = Some(fragment);
+        self
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>, MessageError> {
         postcard::to_allocvec(self).map_err(MessageError::SerializationError)
     }
 
+    /// Decodes a message from untrusted bytes. Lengths in `data` are checked against what's left of
+    /// it before anything is allocated for them, so a frame can't claim its way into a large allocation.
+    /// Messages in another version of the wire format fail with `MessageError::UnsupportedVersion`.
     pub fn deserialize(data: &[u8]) -> Result<Self, MessageError> {
+        let (version, _) = postcard::take_from_bytes::<u16>(data).map_err(MessageError::DeserializationError)?;
+        if version != WIRE_VERSION {
+            return Err(MessageError::UnsupportedVersion(version));
+        }
+
         postcard::from_bytes(data).map_err(MessageError::DeserializationError)
     }
 
-    pub fn sign(mut self, identity: impl Identity) -> anyhow::Result<Self> {
-        self.sender = Some(identity.id());
+    #[cfg(feature = "crypto")]
+    pub fn sign(self, identity: impl Identity) -> anyhow::Result<Self> { Ok(self.sign_with(identity.id(), identity.key())?) }
+
+    /// Signs the message as `sender` with any scheme, see `MessageSigner`. Schemes other than
+    /// `ED25519` are named in the `scheme` header, which the signature covers.
+    pub fn sign_with(mut self, sender: Uuid, signer: &impl MessageSigner) -> Result<Self, MessageError> {
+        self.sender = Some(sender);
         self.signature = None;
+        match signer.scheme() {
+            ED25519 => self.headers.remove("scheme"),
+            scheme => self.headers.insert("scheme".to_string(), scheme.as_bytes().to_vec()),
+        };
 
         let unsigned_data = self.serialize()?;
-        let signature = identity.key().try_sign(&unsigned_data)?;
-        self.signature = Some(signature.to_bytes().to_vec());
+        self.signature = Some(signer.sign(&unsigned_data)?);
 
         Ok(self)
     }
 
-    pub fn verify(&self, key: &VerifyingKey) -> Result<(), MessageError> {
+    /// The signature scheme the message claims to be signed with.
+    pub fn scheme(&self) -> &str {
+        self.headers.get("scheme").and_then(|s| std::str::from_utf8(s).ok()).unwrap_or(ED25519)
+    }
+
+    /// An id for the message derived from its content, so the same message gets the same id
+    /// wherever it's seen. Covers everything but the signature, so signing doesn't change it.
+    pub fn content_id(&self) -> [u8; 32] {
+        let unsigned = FLESHMessage { signature: None, ..self.clone() };
+        // Serializing only fails for types postcard can't represent, which a message never holds
+        let data = unsigned.serialize().unwrap_or_default();
+        Sha256::digest(data).into()
+    }
+
+    #[cfg(feature = "crypto")]
+    pub fn verify(&self, key: &VerifyingKey) -> Result<(), MessageError> { self.verify_with(key) }
+
+    /// Checks the signature with any scheme, failing if the message was signed with another.
+    pub fn verify_with(&self, verifier: &impl MessageVerifier) -> Result<(), MessageError> {
         let signature_bytes = self.signature.as_ref().ok_or(MessageError::MissingSignature)?;
+        if self.scheme() != verifier.scheme() {
+            return Err(MessageError::WrongScheme(self.scheme().to_string()));
+        }
 
         let mut unsigned_msg = self.clone();
         unsigned_msg.signature = None;
         let unsigned_data = unsigned_msg.serialize()?;
 
-        let signature =
-            Signature::from_bytes(signature_bytes.as_slice().try_into().map_err(|_| MessageError::InvalidSignature)?);
-
-        key.verify_strict(&unsigned_data, &signature).map_err(|_| MessageError::InvalidSignature)?;
-
-        Ok(())
+        verifier.verify(&unsigned_data, signature_bytes)
     }
 
+    #[cfg(feature = "crypto")]
     pub fn encrypt_body(mut self, target_key: &VerifyingKey) -> Result<Self, MessageError> {
         if self.body.is_empty() {
             return Ok(self);
@@ -106,7 +247,7 @@ impl FLESHMessage {
         let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
         let ephemeral_public = X25519PublicKey::from(&ephemeral_secret);
 
-        let target_x25519 = X25519PublicKey::from(*target_key.as_bytes());
+        let target_x25519 = X25519PublicKey::from(target_key.to_montgomery().to_bytes());
         let shared_secret = ephemeral_secret.diffie_hellman(&target_x25519);
 
         let cipher =
@@ -125,6 +266,7 @@ impl FLESHMessage {
         Ok(self)
     }
 
+    #[cfg(feature = "crypto")]
     pub fn decrypt_body(mut self, identity: &impl Identity) -> Result<Self, MessageError> {
         let ephemeral_key = self.headers.get("ephemeral_key").ok_or(MessageError::MissingEncryptionData)?;
         let nonce_bytes = self.headers.get("nonce").ok_or(MessageError::MissingEncryptionData)?;
@@ -133,7 +275,7 @@ impl FLESHMessage {
             ephemeral_key.as_slice().try_into().map_err(|_| MessageError::InvalidEncryptionData)?;
         let ephemeral_public = X25519PublicKey::from(ephemeral_key);
 
-        let my_secret = StaticSecret::from(identity.key().to_bytes());
+        let my_secret = StaticSecret::from(identity.key().to_scalar_bytes());
         let shared_secret = my_secret.diffie_hellman(&ephemeral_public);
 
         let cipher =
@@ -152,10 +294,13 @@ impl FLESHMessage {
     pub fn is_ok(&self) -> bool { self.status.is_ok() }
 
     /// If the target is broadcast, or targets the given identity
-    pub fn for_id(&self, id: impl Identity) -> bool {
-        self.target == Some(id.id()) || self.target.is_none()
-    }
+    pub fn for_id(&self, id: impl Identity) -> bool { self.addressed_to(id.id()) || self.is_broadcast() }
+
+    /// If the message names the given node as its target, as opposed to being broadcast
+    pub fn addressed_to(&self, id: Uuid) -> bool { self.target == Some(id) }
 
+    /// If the message is meant for every node
+    pub fn is_broadcast(&self) -> bool { self.target.is_none() }
 }
 
 #[derive(Debug, Error)]
@@ -164,10 +309,14 @@ pub enum MessageError {
     SerializationError(postcard::Error),
     #[error("Deserialization failed: {0}")]
     DeserializationError(postcard::Error),
+    #[error("Wire format version {0} isn't supported, only {WIRE_VERSION}")]
+    UnsupportedVersion(u16),
     #[error("Missing signature")]
     MissingSignature,
     #[error("Invalid signature")]
     InvalidSignature,
+    #[error("Signed with {0}, which the given key can't check")]
+    WrongScheme(String),
     #[error("Encryption failed")]
     EncryptionError,
     #[error("Decryption failed")]
@@ -176,6 +325,62 @@ pub enum MessageError {
     MissingEncryptionData,
     #[error("Invalid encryption data")]
     InvalidEncryptionData,
+    #[error("Invalid fragment")]
+    InvalidFragment,
+    #[error("Missing target")]
+    MissingTarget,
+    #[error("Header '{0}' is reserved for the protocol")]
+    ReservedHeader(String),
+    #[error("Missing sender")]
+    MissingSender,
+    #[error("Couldn't resolve the key of sender {0}")]
+    UnknownSender(Uuid),
+}
+
+/// Builds a `FLESHMessage` in an order that keeps it valid: encryption is applied once the body is
+/// final, and signing finishes the message, so nothing can change under the signature afterwards.
+pub struct MessageBuilder {
+    message: FLESHMessage,
+    encrypt_for: Option<VerifyingKey>,
+}
+
+impl MessageBuilder {
+    pub fn with_target(mut self, target: Uuid) -> Self {
+        self.message = self.message.with_target(target);
+        self
+    }
+
+    pub fn with_header(mut self, key: impl Display, value: impl Into<Vec<u8>>) -> Self {
+        self.message = self.message.with_header(key, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.message = self.message.with_body(body);
+        self
+    }
+
+    /// Encrypts the body for `key` when the message is built. Only targeted messages can be
+    /// encrypted, as there's no one key a broadcast could be encrypted for.
+    #[cfg(feature = "crypto")]
+    pub fn encrypt_for(mut self, key: VerifyingKey) -> Self {
+        self.encrypt_for = Some(key);
+        self
+    }
+
+    /// Finishes the message without signing it.
+    pub fn build(self) -> Result<FLESHMessage, MessageError> {
+        match (self.encrypt_for, self.message.target) {
+            (Some(_), None) => Err(MessageError::MissingTarget),
+            #[cfg(feature = "crypto")]
+            (Some(key), Some(_)) => self.message.encrypt_body(&key),
+            _ => Ok(self.message),
+        }
+    }
+
+    /// Finishes the message, signed by `identity`.
+    #[cfg(feature = "crypto")]
+    pub fn sign(self, identity: impl Identity) -> anyhow::Result<FLESHMessage> { self.build()?.sign(identity) }
 }
 
 pub trait Identity {
@@ -0,0 +1,131 @@
+use {
+    crate::transport::{
+        encoding::{FLESHMessage, MessageError},
+        status::Status,
+    },
+    std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    },
+    serde::{Deserialize, Serialize},
+    tracing::debug,
+    uuid::Uuid,
+};
+
+pub const REASSEMBLY_TIMEOUT_SECS: u64 = 120;
+/// Most parts a message can be split into. A part claiming more is refused, rather than having room set
+/// aside for them on the word of a single frame.
+pub const MAX_PARTS: u16 = 1024;
+/// Most messages being reassembled at once. Starting another drops the oldest.
+pub const MAX_TRANSFERS: usize = 32;
+
+/// Where a `Status::Fragment` message's body belongs in the message it was split from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fragment {
+    /// Shared by every part of the same message
+    pub id: Uuid,
+    pub index: u16,
+    pub total: u16,
+}
+
+/// A received frame once any splitting has been accounted for.
+#[derive(Debug, Clone)]
+pub enum InternalMessage {
+    /// One piece of a serialized message that was split across several frames
+    Part { id: Uuid, index: u16, total: u16, data: Vec<u8> },
+    /// A whole serialized message, either received in a single frame or reassembled from parts
+    Complete(Vec<u8>),
+}
+
+impl InternalMessage {
+    /// Classifies a raw frame as either a complete message or a part of one.
+    pub fn from_frame(data: Vec<u8>) -> Result<Self, MessageError> {
+        let message = FLESHMessage::deserialize(&data)?;
+        if !matches!(message.status, Status::Fragment) {
+            return Ok(Self::Complete(data));
+        }
+
+        let Fragment { id, index, total } = message.fragment.ok_or(MessageError::InvalidFragment)?;
+        if total == 0 || total > MAX_PARTS || index >= total {
+            return Err(MessageError::InvalidFragment);
+        }
+
+        Ok(Self::Part { id, index, total, data: message.body })
+    }
+
+    /// Splits a message into `Fragment` messages carrying at most `chunk` bytes of its
+    /// serialized form each, along with the id they share. Parts inherit the message's target and sender
+    /// so they can be routed, and relay failures reported. Fails if it'd take more than `MAX_PARTS`.
+    pub fn split(m: &FLESHMessage, chunk: usize) -> Result<(Uuid, Vec<FLESHMessage>), MessageError> {
+        let data = m.serialize()?;
+        let chunks = data.chunks(chunk.max(1)).collect::<Vec<_>>();
+        let total = u16::try_from(chunks.len()).ok().filter(|total| *total <= MAX_PARTS).ok_or(MessageError::InvalidFragment)?;
+        let id = Uuid::new_v4();
+
+        let parts = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let part = FLESHMessage::new(Status::Fragment)
+                    .with_fragment(Fragment { id, index: index as u16, total })
+                    .with_body(data);
+
+                FLESHMessage { target: m.target, sender: m.sender, ..part }
+            })
+            .collect();
+
+        Ok((id, parts))
+    }
+}
+
+/// When a transfer started, how many had started before it, and the parts received so far.
+type Transfer = (Instant, u64, Vec<Option<Vec<u8>>>);
+
+/// Collects `InternalMessage::Part`s until every piece of a message has arrived.
+#[derive(Debug, Default)]
+pub struct Reassembler(
+    HashMap<Uuid, Transfer>,
+    /// Transfers started so far, for telling which is oldest
+    u64,
+);
+impl Reassembler {
+    /// Accepts a part, returning `InternalMessage::Complete` once the message it belongs to is whole.
+    /// Anything that isn't a part is passed straight through.
+    pub fn insert(&mut self, m: InternalMessage) -> Option<InternalMessage> {
+        let InternalMessage::Part { id, index, total, data } = m else {
+            return Some(m);
+        };
+
+        // Drop transfers that stalled, so a lost part doesn't pin memory forever
+        self.0.retain(|_, (started, ..)| started.elapsed() < Duration::from_secs(REASSEMBLY_TIMEOUT_SECS));
+        // Parts built by hand never went through `from_frame`'s checks
+        if total == 0 || total > MAX_PARTS || index >= total {
+            return None;
+        }
+
+        if !self.0.contains_key(&id)
+            && self.0.len() >= MAX_TRANSFERS
+            && let Some(oldest) = self.0.iter().min_by_key(|(_, (_, order, _))| *order).map(|(id, _)| *id)
+        {
+            debug!("Too many messages being reassembled, dropping {oldest}");
+            self.0.remove(&oldest);
+        }
+
+        let order = &mut self.1;
+        let (.., parts) = self.0.entry(id).or_insert_with(|| {
+            *order += 1;
+            (Instant::now(), *order, vec![None; total as usize])
+        });
+        if parts.len() != total as usize {
+            return None;
+        }
+
+        parts[index as usize] = Some(data);
+        if parts.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let (.., parts) = self.0.remove(&id)?;
+        Some(InternalMessage::Complete(parts.into_iter().flatten().flatten().collect()))
+    }
+}
@@ -0,0 +1,86 @@
+use {
+    crate::transport::encoding::{FLESHMessage, MessageError},
+    chacha20poly1305::{
+        ChaCha20Poly1305,
+        aead::{Aead, KeyInit},
+    },
+    rand_core::{OsRng, RngCore},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeSet, HashMap},
+        sync::Arc,
+    },
+    tokio::sync::RwLock,
+    uuid::Uuid,
+};
+
+/// Groups we're in, keyed by their id.
+pub(crate) type Groups = Arc<RwLock<HashMap<Uuid, GroupKey>>>;
+
+/// A group as we last heard of it, see `Network::group`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupInfo {
+    pub id: Uuid,
+    /// The node that created the group, and the only one that can change its members
+    pub admin: Uuid,
+    /// How many times the group's been re-keyed
+    pub epoch: u32,
+    pub members: BTreeSet<Uuid>,
+}
+
+/// A group along with the key its messages are encrypted with, as handed to each member by the admin.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct GroupKey {
+    pub info: GroupInfo,
+    key: [u8; 32],
+}
+
+impl GroupKey {
+    pub(crate) fn new(id: Uuid, admin: Uuid, members: BTreeSet<Uuid>) -> Self {
+        Self { info: GroupInfo { id, admin, epoch: 0, members }, key: random_key() }
+    }
+
+    /// The same group under a new key, which the members it's handed to can read from and
+    /// anyone holding the old one can't.
+    pub(crate) fn rekeyed(&self) -> Self {
+        Self { info: GroupInfo { epoch: self.info.epoch + 1, ..self.info.clone() }, key: random_key() }
+    }
+
+    /// Encrypts a message's body for the group, tagging it with the group and key it's for.
+    pub(crate) fn seal(&self, m: FLESHMessage) -> Result<FLESHMessage, MessageError> {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let body = self.cipher()?.encrypt(&nonce.into(), m.body.as_ref()).map_err(|_| MessageError::EncryptionError)?;
+
+        Ok(m.with_header("group", self.info.id)
+            .with_header("epoch", self.info.epoch.to_le_bytes().to_vec())
+            .with_header("nonce", nonce.to_vec())
+            .with_body(body))
+    }
+
+    /// Decrypts the body of a message sealed for the group. Messages sealed under any other key
+    /// than ours, older or newer, can't be read.
+    pub(crate) fn open(&self, m: &FLESHMessage) -> Result<Vec<u8>, MessageError> {
+        let epoch = m.headers.get("epoch").and_then(|e| Some(u32::from_le_bytes(e.as_slice().try_into().ok()?)));
+        let nonce = m.headers.get("nonce").ok_or(MessageError::MissingEncryptionData)?;
+        let nonce: [u8; 12] = nonce.as_slice().try_into().map_err(|_| MessageError::InvalidEncryptionData)?;
+        if epoch != Some(self.info.epoch) {
+            return Err(MessageError::DecryptionError);
+        }
+
+        self.cipher()?.decrypt(&nonce.into(), m.body.as_ref()).map_err(|_| MessageError::DecryptionError)
+    }
+
+    fn cipher(&self) -> Result<ChaCha20Poly1305, MessageError> {
+        ChaCha20Poly1305::new_from_slice(&self.key).map_err(|_| MessageError::EncryptionError)
+    }
+}
+
+/// The group a message was sealed for, if it was.
+pub(crate) fn group_of(m: &FLESHMessage) -> Option<Uuid> { m.headers.get("group").and_then(|id| Uuid::from_slice(id).ok()) }
+
+fn random_key() -> [u8; 32] {
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    key
+}
@@ -0,0 +1,141 @@
+use {
+    crate::transport::{PacketTransport, TransportId, middleware::Direction},
+    async_trait::async_trait,
+    std::{
+        fs::{File, OpenOptions},
+        io::{self, Read, Write},
+        path::Path,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Bytes before a record's frame: length, direction and timestamp.
+const RECORD_HEADER: usize = 4 + 1 + 8;
+
+/// A frame as it was recorded by a `Journaled` transport.
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub direction: Direction,
+    /// When it was sent or received, in milliseconds since the unix epoch
+    pub at: u64,
+    pub data: Vec<u8>,
+}
+
+impl JournalEntry {
+    fn encode(&self) -> Vec<u8> {
+        let direction = match self.direction {
+            Direction::Inbound => 0u8,
+            Direction::Outbound => 1u8,
+        };
+
+        [&(self.data.len() as u32).to_le_bytes()[..], &[direction], &self.at.to_le_bytes(), &self.data].concat()
+    }
+}
+
+/// Wraps a transport, appending every frame sent or received through it to a file, see
+/// `Network::with_journal`. Records are a little endian `u32` frame length, a direction byte
+/// (0 received, 1 sent), a little endian `u64` millisecond timestamp, then the frame itself.
+#[derive(Clone)]
+pub struct Journaled<T: PacketTransport> {
+    inner: T,
+    file: Arc<Mutex<File>>,
+}
+
+impl<T: PacketTransport> Journaled<T> {
+    /// Journals to `path`, appending to it if it already exists.
+    pub fn new(inner: T, path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { inner, file: Arc::new(Mutex::new(file)) })
+    }
+
+    pub fn inner(&self) -> &T { &self.inner }
+
+    fn record(&self, direction: Direction, data: &[u8]) -> io::Result<()> {
+        let at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let entry = JournalEntry { direction, at, data: data.to_vec() };
+        self.file.lock().map_err(|_| io::Error::other("Journal poisoned"))?.write_all(&entry.encode())
+    }
+}
+
+#[async_trait]
+impl<T: PacketTransport> PacketTransport for Journaled<T> {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        self.record(Direction::Outbound, data)?;
+        self.inner.send(data).await
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let data = self.inner.recv().await?;
+        self.record(Direction::Inbound, &data)?;
+        Ok(data)
+    }
+
+    async fn send_via(&self, link: TransportId, data: &[u8]) -> io::Result<()> {
+        self.record(Direction::Outbound, data)?;
+        self.inner.send_via(link, data).await
+    }
+
+    fn max_frame(&self) -> Option<usize> { self.inner.max_frame() }
+
+    async fn recv_from(&mut self) -> io::Result<(TransportId, Vec<u8>)> {
+        let (link, data) = self.inner.recv_from().await?;
+        self.record(Direction::Inbound, &data)?;
+        Ok((link, data))
+    }
+
+    async fn recv_measured(&mut self) -> io::Result<(TransportId, Vec<u8>, Option<i16>)> {
+        let (link, data, rssi) = self.inner.recv_measured().await?;
+        self.record(Direction::Inbound, &data)?;
+        Ok((link, data, rssi))
+    }
+
+    async fn ready(&self) -> io::Result<()> { self.inner.ready().await }
+
+    async fn flush(&self) -> io::Result<()> { self.inner.flush().await }
+}
+
+/// Reads back every record in a journal. A record cut short at the end, as left by a crash
+/// mid-write, is ignored.
+pub fn read_journal(path: impl AsRef<Path>) -> io::Result<Vec<JournalEntry>> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    let mut entries = vec![];
+    let mut rest = bytes.as_slice();
+    while rest.len() >= RECORD_HEADER {
+        let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        if rest.len() < RECORD_HEADER + len {
+            break;
+        }
+
+        let direction = match rest[4] {
+            0 => Direction::Inbound,
+            1 => Direction::Outbound,
+            other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown journal direction {other}"))),
+        };
+
+        let at = u64::from_le_bytes(rest[5..RECORD_HEADER].try_into().unwrap());
+        entries.push(JournalEntry { direction, at, data: rest[RECORD_HEADER..RECORD_HEADER + len].to_vec() });
+        rest = &rest[RECORD_HEADER + len..];
+    }
+
+    Ok(entries)
+}
+
+/// Sends every frame the journaled node received back out over `transport`, with the gaps
+/// between them as recorded. Sent over a `MemoryTransport` sharing a medium with a `Network`
+/// restored from the journaled node's state, that network hears what the original did.
+/// Returns how many frames were replayed.
+pub async fn replay_journal(path: impl AsRef<Path>, transport: &impl PacketTransport) -> io::Result<usize> {
+    let received = read_journal(path)?.into_iter().filter(|e| e.direction == Direction::Inbound).collect::<Vec<_>>();
+
+    let mut last = received.first().map_or(0, |e| e.at);
+    for entry in &received {
+        tokio::time::sleep(Duration::from_millis(entry.at.saturating_sub(last))).await;
+        last = entry.at;
+        transport.send(&entry.data).await?;
+    }
+
+    Ok(received.len())
+}
@@ -0,0 +1,71 @@
+#[cfg(feature = "crypto")]
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+#[cfg(not(feature = "crypto"))]
+pub use plain::{SigningKey, VerifyingKey};
+
+/// Signs `claim` for the routing messages that carry their own signature (aliases, handshakes and
+/// leaving). Without `crypto` there's nothing to sign with, so the signature's empty.
+#[cfg(feature = "crypto")]
+pub(crate) fn sign_claim(key: &SigningKey, claim: &[u8]) -> Vec<u8> {
+    ed25519_dalek::Signer::sign(key, claim).to_bytes().to_vec()
+}
+
+#[cfg(not(feature = "crypto"))]
+pub(crate) fn sign_claim(_: &SigningKey, _: &[u8]) -> Vec<u8> { vec![] }
+
+/// Whether `signature` is `key`'s over `claim`. Without `crypto` nothing can be checked, so every
+/// claim is taken at its word.
+#[cfg(feature = "crypto")]
+pub(crate) fn verify_claim(key: &VerifyingKey, claim: &[u8], signature: &[u8]) -> bool {
+    ed25519_dalek::Signature::from_slice(signature).and_then(|s| key.verify_strict(claim, &s)).is_ok()
+}
+
+#[cfg(not(feature = "crypto"))]
+pub(crate) fn verify_claim(_: &VerifyingKey, _: &[u8], _: &[u8]) -> bool { true }
+
+/// Stand-ins for ed25519 keys without `crypto`, so nodes are still routed to (unauthenticated) without
+/// pulling in any cryptography.
+#[cfg(not(feature = "crypto"))]
+mod plain {
+    use {
+        rand_core::{CryptoRng, RngCore},
+        std::convert::Infallible,
+    };
+
+    /// A node's secret, kept so saved state still holds a usable key if it's later loaded with `crypto`.
+    /// It's never sent anywhere.
+    #[derive(Clone)]
+    pub struct SigningKey([u8; 32]);
+
+    impl SigningKey {
+        pub fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+            let mut secret = [0; 32];
+            rng.fill_bytes(&mut secret);
+            Self(secret)
+        }
+
+        pub fn from_bytes(secret: &[u8; 32]) -> Self { Self(*secret) }
+
+        pub fn to_bytes(&self) -> [u8; 32] { self.0 }
+
+        /// There are no public keys without `crypto`, so every node shares the same placeholder.
+        pub fn verifying_key(&self) -> VerifyingKey { VerifyingKey }
+    }
+
+    impl std::fmt::Debug for SigningKey {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str("SigningKey(..)") }
+    }
+
+    /// What a node's key is exchanged as without `crypto`, which is nothing at all: resolving a node
+    /// only finds a route to it.
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct VerifyingKey;
+
+    impl VerifyingKey {
+        pub fn from_bytes(_: &[u8; 32]) -> Result<Self, Infallible> { Ok(Self) }
+
+        pub fn as_bytes(&self) -> &[u8; 32] { &[0; 32] }
+
+        pub fn to_bytes(&self) -> [u8; 32] { [0; 32] }
+    }
+}
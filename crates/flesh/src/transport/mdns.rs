@@ -0,0 +1,206 @@
+use {
+    crate::events::EventTarget,
+    std::{
+        io,
+        net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+        sync::Arc,
+        time::Duration,
+    },
+    tokio::{net::UdpSocket, spawn},
+    tracing::{debug, trace},
+    uuid::Uuid,
+};
+
+pub const SERVICE: &str = "_flesh._udp.local";
+pub const MDNS_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+pub const QUERY_INTERVAL_SECS: u64 = 60;
+
+const RECORD_TTL_SECS: u32 = 120;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+const CACHE_FLUSH: u16 = 0x8000;
+
+/// Advertises a FLESH node over mDNS/DNS-SD as `_flesh._udp.local`, and finds others doing the same,
+/// so nodes on a LAN can reach each other over IP without being configured with addresses.
+///
+/// Found peers are emitted as the address to connect to, for IP transports to add to their peer set.
+#[derive(Clone)]
+pub struct MdnsDiscovery {
+    socket: Arc<UdpSocket>,
+    group: SocketAddr,
+    instance: String,
+    port: u16,
+    discovered: EventTarget<SocketAddr>,
+}
+
+impl MdnsDiscovery {
+    /// Advertises our IP transport listening on `port`, and starts looking for peers.
+    pub async fn new(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, MDNS_GROUP.port())).await?;
+        socket.join_multicast_v4(*MDNS_GROUP.ip(), Ipv4Addr::UNSPECIFIED)?;
+        socket.set_multicast_loop_v4(true)?;
+        Ok(Self::from_socket(socket, MDNS_GROUP.into(), port))
+    }
+
+    /// Runs over an already bound socket, sending to `group` rather than the mDNS multicast group.
+    pub fn from_socket(socket: UdpSocket, group: SocketAddr, port: u16) -> Self {
+        let s = Self {
+            socket: Arc::new(socket),
+            group,
+            instance: format!("{}.{SERVICE}", Uuid::new_v4().simple()),
+            port,
+            discovered: Default::default(),
+        };
+
+        spawn(s.clone().listen());
+        spawn(s.clone().periodic_queries());
+        s
+    }
+
+    /// Addresses of peers as they're found (or re-announce themselves).
+    pub fn discovered(&self) -> &EventTarget<SocketAddr> { &self.discovered }
+
+    /// Asks the network who's offering the service.
+    pub async fn query(&self) -> io::Result<()> {
+        let mut packet = header(0, 1, 0);
+        write_name(&mut packet, SERVICE);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        self.socket.send_to(&packet, self.group).await.map(|_| ())
+    }
+
+    /// Tells the network we're offering the service.
+    pub async fn announce(&self) -> io::Result<()> {
+        let mut packet = header(0x8400, 0, 2);
+        write_record(&mut packet, SERVICE, TYPE_PTR, CLASS_IN, &{
+            let mut data = Vec::new();
+            write_name(&mut data, &self.instance);
+            data
+        });
+
+        write_record(&mut packet, &self.instance, TYPE_SRV, CLASS_IN | CACHE_FLUSH, &{
+            let mut data = [0u16.to_be_bytes(), 0u16.to_be_bytes(), self.port.to_be_bytes()].concat();
+            write_name(&mut data, &format!("{}.local", self.instance.split('.').next().unwrap_or_default()));
+            data
+        });
+
+        self.socket.send_to(&packet, self.group).await.map(|_| ())
+    }
+
+    async fn periodic_queries(self) {
+        loop {
+            let _ = self.announce().await;
+            let _ = self.query().await;
+            tokio::time::sleep(Duration::from_secs(QUERY_INTERVAL_SECS)).await;
+        }
+    }
+
+    async fn listen(self) {
+        let mut buf = [0u8; 9000];
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf).await {
+            let Some(packet) = Packet::parse(&buf[..len]) else {
+                trace!("Ignoring malformed mDNS packet from {from}");
+                continue;
+            };
+
+            if packet.asks_for_service {
+                let _ = self.announce().await;
+            }
+
+            // The SRV gives the port, and the peer is at whatever address it sent from
+            for (instance, port) in packet.services {
+                if instance != self.instance {
+                    debug!("Discovered {instance} at {}:{port}", from.ip());
+                    self.discovered.emit(SocketAddr::new(from.ip(), port));
+                }
+            }
+        }
+    }
+}
+
+/// The parts of an mDNS packet we care about.
+#[derive(Debug, Default)]
+struct Packet {
+    asks_for_service: bool,
+    /// Instance names and ports from `_flesh._udp` SRV records
+    services: Vec<(String, u16)>,
+}
+
+impl Packet {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let count = |at: usize| Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?));
+        let (questions, records) = (count(4)?, count(6)? as usize + count(8)? as usize + count(10)? as usize);
+        let (mut packet, mut at) = (Self::default(), 12);
+
+        for _ in 0..questions {
+            let (name, next) = read_name(data, at)?;
+            let kind = count(next)?;
+            packet.asks_for_service |= kind == TYPE_PTR && name.eq_ignore_ascii_case(SERVICE);
+            at = next + 4;
+        }
+
+        for _ in 0..records {
+            let (name, next) = read_name(data, at)?;
+            let (kind, length) = (count(next)?, count(next + 8)? as usize);
+            let rdata = next + 10;
+            if kind == TYPE_SRV && name.to_ascii_lowercase().ends_with(SERVICE) {
+                packet.services.push((name, count(rdata + 4)?));
+            }
+
+            at = rdata + length;
+        }
+
+        Some(packet)
+    }
+}
+
+fn header(flags: u16, questions: u16, answers: u16) -> Vec<u8> {
+    [0u16, flags, questions, answers, 0, 0].iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+
+    out.push(0);
+}
+
+fn write_record(out: &mut Vec<u8>, name: &str, kind: u16, class: u16, data: &[u8]) {
+    write_name(out, name);
+    out.extend_from_slice(&kind.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&RECORD_TTL_SECS.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Reads a (possibly compressed) name, returning it and the offset just past it.
+fn read_name(data: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let (mut labels, mut end, mut jumps) = (Vec::new(), None, 0);
+    loop {
+        let len = *data.get(at)? as usize;
+        match len {
+            0 => break,
+            // Compression pointer, bounded so a malicious loop can't spin forever
+            _ if len & 0xC0 == 0xC0 => {
+                jumps += 1;
+                if jumps > 16 {
+                    return None;
+                }
+
+                end.get_or_insert(at + 2);
+                at = ((len & 0x3F) << 8) | *data.get(at + 1)? as usize;
+            }
+            _ => {
+                labels.push(String::from_utf8_lossy(data.get(at + 1..at + 1 + len)?).into_owned());
+                at += 1 + len;
+            }
+        }
+    }
+
+    Some((labels.join("."), end.unwrap_or(at + 1)))
+}
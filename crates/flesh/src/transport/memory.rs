@@ -0,0 +1,116 @@
+use {
+    crate::transport::PacketTransport,
+    async_trait::async_trait,
+    std::{
+        collections::{HashMap, HashSet},
+        io,
+        sync::{
+            Arc, RwLock,
+            atomic::{AtomicUsize, Ordering},
+        },
+    },
+    tokio::sync::{
+        Mutex,
+        mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+    },
+};
+
+/// Every transport attached to a medium, alongside the channel feeding its `recv`.
+type Peers = Vec<(usize, UnboundedSender<Vec<u8>>)>;
+
+/// An in-process broadcast medium. Every frame sent by one connected transport is
+/// delivered to all of the others, as if they were sharing a radio channel, unless
+/// `restrict` limits who a transport can reach.
+#[derive(Clone, Default)]
+pub struct MemoryMedium {
+    peers: Arc<RwLock<Peers>>,
+    reach: Arc<RwLock<HashMap<usize, HashSet<usize>>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl MemoryMedium {
+    pub fn new() -> Self { Self::default() }
+
+    /// Attaches a new transport to the medium.
+    pub fn connect(&self) -> MemoryTransport {
+        let id = self.next.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = unbounded_channel();
+
+        if let Ok(mut peers) = self.peers.write() {
+            peers.push((id, tx));
+        }
+
+        MemoryTransport { id, medium: self.clone(), reader: Arc::new(Mutex::new(rx)), max_frame: None }
+    }
+
+    /// Limits the transport with id `from` to only reaching the transports in `to`, as if the
+    /// rest were out of range. Ids are handed out in order of `connect`, starting at 0.
+    pub fn restrict(&self, from: usize, to: impl IntoIterator<Item = usize>) {
+        if let Ok(mut reach) = self.reach.write() {
+            reach.insert(from, to.into_iter().collect());
+        }
+    }
+
+    /// Whether a frame sent by `from` is heard by `to`.
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        from != to && self.reach.read().map(|reach| reach.get(&from).is_none_or(|r| r.contains(&to))).unwrap_or(true)
+    }
+}
+
+/// A `PacketTransport` over a `MemoryMedium`, for running networks entirely in-process.
+#[derive(Clone)]
+pub struct MemoryTransport {
+    id: usize,
+    medium: MemoryMedium,
+    reader: Arc<Mutex<UnboundedReceiver<Vec<u8>>>>,
+    max_frame: Option<usize>,
+}
+
+impl MemoryTransport {
+    /// Two transports sharing a fresh medium.
+    pub fn pair() -> (Self, Self) {
+        let medium = MemoryMedium::new();
+        (medium.connect(), medium.connect())
+    }
+
+    pub fn medium(&self) -> &MemoryMedium { &self.medium }
+
+    /// Our id on the medium, see `MemoryMedium::restrict`.
+    pub fn id(&self) -> usize { self.id }
+
+    /// Refuses frames over `max` bytes, like a radio with a small payload limit.
+    pub fn with_max_frame(mut self, max: usize) -> Self {
+        self.max_frame = Some(max);
+        self
+    }
+}
+
+#[async_trait]
+impl PacketTransport for MemoryTransport {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        if let Some(max) = self.max_frame.filter(|max| data.len() > *max) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Packet size of {} bytes exceeds max payload of {} bytes", data.len(), max),
+            ));
+        }
+
+        let peers = self.medium.peers.read().map_err(|_| io::Error::other("Memory medium was poisoned"))?;
+        peers.iter().filter(|(id, _)| self.medium.reaches(self.id, *id)).for_each(|(_, tx)| {
+            let _ = tx.send(data.to_vec());
+        });
+
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> {
+        self.reader
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(io::Error::new(io::ErrorKind::BrokenPipe, "Memory medium was disconnected"))
+    }
+
+    fn max_frame(&self) -> Option<usize> { self.max_frame }
+}
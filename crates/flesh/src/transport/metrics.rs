@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+/// Buckets in a `LatencyHistogram`, each twice as wide as the last from 1ms up.
+const BUCKETS: usize = 18;
+
+/// How long something took across many tries, in buckets doubling in width from 1ms, with the
+/// last catching anything over about a minute. See `Network::resolution_latency_histogram`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    counts: [usize; BUCKETS],
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let ms = latency.as_millis().max(1) as u64;
+        let bucket = (u64::BITS - (ms - 1).leading_zeros()) as usize;
+        self.counts[bucket.min(BUCKETS - 1)] += 1;
+    }
+
+    /// How many latencies have been recorded.
+    pub fn count(&self) -> usize { self.counts.iter().sum() }
+
+    /// Each bucket's upper bound alongside how many latencies fell in it, `Duration::MAX` for the last.
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, usize)> + '_ {
+        self.counts.iter().enumerate().map(|(i, count)| (Self::bound(i), *count))
+    }
+
+    /// The upper bound of the bucket holding the `p`th (0.0 -> 1.0) percentile, `None` if nothing's been recorded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        let total = self.count();
+        let rank = ((total as f64 * p.clamp(0.0, 1.0)).ceil() as usize).max(1);
+
+        let mut seen = 0;
+        self.counts.iter().enumerate().find_map(|(i, count)| {
+            seen += count;
+            (total > 0 && seen >= rank).then(|| Self::bound(i))
+        })
+    }
+
+    pub fn p50(&self) -> Option<Duration> { self.percentile(0.5) }
+
+    pub fn p95(&self) -> Option<Duration> { self.percentile(0.95) }
+
+    fn bound(bucket: usize) -> Duration {
+        match bucket {
+            b if b == BUCKETS - 1 => Duration::MAX,
+            b => Duration::from_millis(1 << b),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use {
+    crate::transport::encoding::FLESHMessage,
+    std::sync::{Arc, RwLock},
+};
+
+/// Which way a message is passing through the middleware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received, and about to be handed to the application
+    Inbound,
+    /// Handed to `Network::send`, and about to be put on the air
+    Outbound,
+}
+
+/// What a middleware wants done with a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Carry on as is
+    Pass,
+    /// Carry on with the changes made to it
+    Modify,
+    /// Stop here, skipping any later middleware
+    Drop,
+}
+
+type Middleware = Box<dyn Fn(&mut FLESHMessage, Direction) -> Decision + Send + Sync>;
+
+/// Middleware run in the order it was added, see `Network::add_middleware`.
+#[derive(Clone, Default)]
+pub struct MiddlewareChain(Arc<RwLock<Vec<Middleware>>>);
+
+impl MiddlewareChain {
+    pub fn push(&self, middleware: impl Fn(&mut FLESHMessage, Direction) -> Decision + Send + Sync + 'static) {
+        if let Ok(mut chain) = self.0.write() {
+            chain.push(Box::new(middleware));
+        }
+    }
+
+    /// Runs a message through every middleware, `None` if one of them dropped it.
+    pub fn apply(&self, mut m: FLESHMessage, direction: Direction) -> Option<FLESHMessage> {
+        let chain = self.0.read().ok()?;
+        chain.iter().all(|middleware| middleware(&mut m, direction) != Decision::Drop).then_some(m)
+    }
+}
+
+type Tap = Box<dyn Fn(&[u8]) + Send + Sync>;
+
+/// Watchers of the raw frames passing through the transport, see `Network::on_tx` and `Network::on_rx`.
+#[derive(Clone, Default)]
+pub struct Taps(Arc<RwLock<Vec<(Direction, Tap)>>>);
+
+impl Taps {
+    pub fn push(&self, direction: Direction, tap: impl Fn(&[u8]) + Send + Sync + 'static) {
+        if let Ok(mut taps) = self.0.write() {
+            taps.push((direction, Box::new(tap)));
+        }
+    }
+
+    /// Shows a frame to every tap watching its direction.
+    pub fn see(&self, direction: Direction, frame: &[u8]) {
+        if let Ok(taps) = self.0.read() {
+            taps.iter().filter(|(d, _)| *d == direction).for_each(|(_, tap)| tap(frame));
+        }
+    }
+}
@@ -1,8 +1,36 @@
 use {async_trait::async_trait, std::io};
 
+pub mod app;
+pub mod blocking;
+pub mod builder;
+pub mod channel;
+pub mod clock;
+pub mod custom;
 pub mod encoding;
+pub mod fragment;
+#[cfg(feature = "crypto")]
+pub mod group;
+pub mod journal;
+pub mod keys;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+pub mod memory;
+pub mod metrics;
+pub mod middleware;
+pub mod multi;
 pub mod network;
+pub mod pacing;
+pub mod reconnect;
+pub mod scheme;
+pub mod short_id;
+pub mod state;
 pub mod status;
+pub mod testing;
+
+/// Identifies one of the links a transport sends and receives over.
+/// Single-link transports only ever use the default id.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TransportId(pub usize);
 
 #[async_trait]
 pub trait PacketTransport: Send + Sync {
@@ -11,4 +39,25 @@ pub trait PacketTransport: Send + Sync {
 
     /// Receives a single data packet.
     async fn recv(&mut self) -> io::Result<Vec<u8>>;
-}
\ No newline at end of file
+
+    /// Sends a single data packet over a specific link.
+    async fn send_via(&self, _link: TransportId, data: &[u8]) -> io::Result<()> { self.send(data).await }
+
+    /// Largest packet the transport can carry in one go, if it's limited.
+    fn max_frame(&self) -> Option<usize> { None }
+
+    /// Receives a single data packet, along with the link it arrived on.
+    async fn recv_from(&mut self) -> io::Result<(TransportId, Vec<u8>)> { Ok((TransportId::default(), self.recv().await?)) }
+
+    /// Like `recv_from`, along with the signal strength (in dBm) the packet arrived at, for transports that measure it.
+    async fn recv_measured(&mut self) -> io::Result<(TransportId, Vec<u8>, Option<i16>)> {
+        let (link, data) = self.recv_from().await?;
+        Ok((link, data, None))
+    }
+
+    /// Resolves once the transport is set up and able to transmit, or with the reason it never will be.
+    async fn ready(&self) -> io::Result<()> { Ok(()) }
+
+    /// Resolves once everything sent so far has actually gone out, for transports that queue frames.
+    async fn flush(&self) -> io::Result<()> { Ok(()) }
+}
@@ -0,0 +1,120 @@
+use {
+    crate::transport::{PacketTransport, TransportId},
+    async_trait::async_trait,
+    futures::future::join_all,
+    std::{io, sync::Arc, time::Duration},
+    tokio::{
+        spawn,
+        sync::{
+            Mutex,
+            mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel},
+        },
+    },
+    tracing::error,
+};
+
+/// Received packets, tagged with the link they came in on and the signal strength it measured.
+type Inbound = (TransportId, Vec<u8>, Option<i16>);
+
+/// Combines several transports (e.g. a LoRa radio and a UDP link on a gateway) into one.
+/// Packets are received from every link, broadcasts go out on all of them, and `send_via`
+/// targets a single link, which `Network` uses to reach a peer the way it was last heard.
+#[derive(Clone)]
+pub struct MultiTransport {
+    links: Vec<Arc<dyn PacketTransport>>,
+    sender: UnboundedSender<Inbound>,
+    receiver: Arc<Mutex<UnboundedReceiver<Inbound>>>,
+}
+
+impl MultiTransport {
+    pub fn new() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        Self { links: Vec::new(), sender, receiver: Arc::new(Mutex::new(receiver)) }
+    }
+
+    /// Adds a link, which is identified by the order it was added in.
+    pub fn with(mut self, transport: impl PacketTransport + Clone + 'static) -> Self {
+        let id = TransportId(self.links.len());
+        let sender = self.sender.clone();
+        let mut reader = transport.clone();
+
+        spawn(async move {
+            loop {
+                match reader.recv_measured().await {
+                    Ok((_, data, rssi)) => {
+                        if sender.send((id, data, rssi)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Link {} receive error: {}. Retrying in 1s.", id.0, e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        });
+
+        self.links.push(Arc::new(transport));
+        self
+    }
+
+    /// Ids of every link, in the order they were added.
+    pub fn links(&self) -> impl Iterator<Item = TransportId> { (0..self.links.len()).map(TransportId) }
+}
+
+impl Default for MultiTransport {
+    fn default() -> Self { Self::new() }
+}
+
+#[async_trait]
+impl PacketTransport for MultiTransport {
+    /// Sends on every link, only failing if none of them could send.
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        let mut result = Err(io::Error::new(io::ErrorKind::NotConnected, "No links to send on"));
+        for link in &self.links {
+            match link.send(data).await {
+                Ok(()) => result = Ok(()),
+                Err(e) if result.is_err() => result = Err(e),
+                Err(_) => {}
+            }
+        }
+
+        result
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { self.recv_from().await.map(|(_, data)| data) }
+
+    async fn send_via(&self, link: TransportId, data: &[u8]) -> io::Result<()> {
+        match self.links.get(link.0) {
+            Some(transport) => transport.send(data).await,
+            None => self.send(data).await,
+        }
+    }
+
+    /// The smallest limit of any link, so a frame fits wherever it's sent.
+    fn max_frame(&self) -> Option<usize> { self.links.iter().filter_map(|l| l.max_frame()).min() }
+
+    async fn recv_from(&mut self) -> io::Result<(TransportId, Vec<u8>)> {
+        let (link, data, _) = self.recv_measured().await?;
+        Ok((link, data))
+    }
+
+    async fn recv_measured(&mut self) -> io::Result<(TransportId, Vec<u8>, Option<i16>)> {
+        self.receiver
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or(io::Error::new(io::ErrorKind::BrokenPipe, "Every link was disconnected"))
+    }
+
+    /// Waits for every link to settle, succeeding as long as one of them came up.
+    async fn ready(&self) -> io::Result<()> {
+        let results = join_all(self.links.iter().map(|l| l.ready())).await;
+        let ok = results.iter().any(Result::is_ok);
+        results.into_iter().find(|r| ok == r.is_ok()).unwrap_or(Ok(()))
+    }
+
+    /// Waits for every link to send what it's been given, failing with the first link that couldn't.
+    async fn flush(&self) -> io::Result<()> { join_all(self.links.iter().map(|l| l.flush())).await.into_iter().collect() }
+}
@@ -2,197 +2,2016 @@ use {
     crate::{
         events::EventTarget,
         transport::{
-            PacketTransport,
-            encoding::{FLESHMessage, Identity},
+            PacketTransport, TransportId,
+            app::App,
+            builder::{NetworkBuilder, NetworkConfig},
+            channel::{self, Channel, ChannelTable, Segment},
+            clock,
+            encoding::{FLESHMessage, Identity, MessageError, WIRE_VERSION},
+            fragment::{InternalMessage, Reassembler},
+            journal::Journaled,
+            keys::{SigningKey, VerifyingKey, sign_claim, verify_claim},
+            metrics::LatencyHistogram,
+            middleware::{Decision, Direction, MiddlewareChain, Taps},
+            pacing::{Backoff, Priority, ResolveQueue, TokenBucket},
+            short_id::ShortId,
             status::Status,
         },
     },
     anyhow::anyhow,
-    ed25519_dalek::{SigningKey, VerifyingKey},
     futures::{Stream, StreamExt},
-    rand_core::OsRng,
+    rand_core::{OsRng, RngCore},
+    serde::{Deserialize, Serialize, de::DeserializeOwned},
     std::{
-        collections::HashMap,
-        ops::{Deref, Not},
-        sync::Arc,
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+        hash::{DefaultHasher, Hash, Hasher},
+        io,
+        ops::{Deref, Range},
+        path::Path,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
         time::{Duration, Instant},
     },
-    tokio::{spawn, sync::RwLock},
-    tracing::{error, info, trace, warn},
+    tokio::{
+        select, spawn,
+        sync::{Mutex, Notify, RwLock, Semaphore, mpsc, oneshot},
+        time::timeout,
+    },
+    thiserror::Error,
+    tokio_util::sync::CancellationToken,
+    tracing::{debug, error, info, trace, warn},
     uuid::Uuid,
 };
+#[cfg(feature = "crypto")]
+use crate::transport::group::{GroupInfo, GroupKey, Groups, group_of};
+
+pub const RESOLUTION_TTL_SECS: u64 = 5000;
+pub const ANNOUNCE_DURATION_SECS: u64 = 30;
+pub const MIN_ANNOUNCE_INTERVAL_SECS: u64 = 10;
+pub const MAX_ANNOUNCE_INTERVAL_SECS: u64 = 300;
+pub const ANNOUNCE_JITTER_MS: u64 = 2000;
+pub const RESOLVE_TIMEOUT_SECS: u64 = 10;
+pub const SPLIT_DELAY_MS: u64 = 1000;
+pub const DEFAULT_MAX_HOPS: u8 = 8;
+pub const RELAY_MEMORY_SECS: u64 = 60;
+pub const RESOLVE_RATE_PER_SEC: u32 = 4;
+pub const RESOLVE_BURST: u32 = 8;
+pub const RELIABLE_RETRY_MS: u64 = 1000;
+pub const RELIABLE_RETRY_MAX_MS: u64 = 16_000;
+pub const CLOCK_SKEW_SECS: u64 = 300;
+
+/// Awaiters of in-flight key resolutions, keyed by the node being resolved.
+type PendingResolutions = Arc<Mutex<HashMap<Uuid, Vec<oneshot::Sender<VerifyingKey>>>>>;
+
+/// Reliable sends waiting to hear back, keyed by their receipt id, see `send_reliable`.
+type Receipts = Arc<Mutex<HashMap<Uuid, oneshot::Sender<FLESHMessage>>>>;
+
+/// The same sends, as reported by `inflight`, with how to call each off.
+type Inflight = Arc<Mutex<HashMap<Uuid, (InflightSend, CancellationToken)>>>;
+
+/// Broadcasts still collecting acknowledgements, keyed by the id they carry, see `broadcast_collect`.
+type Collections = Arc<Mutex<HashMap<Uuid, mpsc::UnboundedSender<Uuid>>>>;
+
+#[derive(Clone)]
+pub struct Network<T: PacketTransport> {
+    nodes: Arc<RwLock<NodeRelationshipMap>>,
+    pending: PendingResolutions,
+    collections: Collections,
+    receipts: Receipts,
+    inflight: Inflight,
+    /// Encrypted groups we're in, see `create_group`
+    #[cfg(feature = "crypto")]
+    groups: Groups,
+    /// Receipt ids of messages already delivered, so retransmissions aren't delivered twice
+    delivered: Arc<Mutex<HashMap<Uuid, Instant>>>,
+    resolve_queue: Arc<Mutex<ResolveQueue>>,
+    resolve_queued: Arc<Notify>,
+    /// How long resolutions we've asked the network for took, see `resolution_latency_histogram`
+    resolution_latency: Arc<Mutex<LatencyHistogram>>,
+    /// Woken whenever we learn a node's key, see `wait_for_peers`
+    peers_changed: Arc<Notify>,
+    sequences: Arc<Mutex<Sequences>>,
+    relayed: Arc<Mutex<RecentRelays>>,
+    relay_slots: Arc<Semaphore>,
+    /// Messages held for nodes that have gone quiet, see `NetworkBuilder::mailbox`
+    mailbox: Arc<Mutex<Mailbox>>,
+    /// Messages dropped for claiming an implausible time, see `NetworkBuilder::clock_skew`
+    mistimed: Arc<AtomicUsize>,
+    /// The link each neighbour was heard on, see `heard_on`
+    links: Arc<RwLock<HashMap<Uuid, Link>>>,
+    /// Signal strength each neighbour was last heard at, where the transport measures it
+    signal: Arc<RwLock<HashMap<Uuid, i16>>>,
+    /// Parts of split messages, whether heard directly or relayed to us, until they're whole
+    reassembler: Arc<Mutex<Reassembler>>,
+    aliases: Arc<RwLock<Aliases>>,
+    alias: Arc<RwLock<Option<String>>>,
+    /// What we advertise we can do, see `set_capabilities`
+    capabilities: Arc<RwLock<BTreeSet<String>>>,
+    /// What the nodes around us have advertised they can do
+    peer_capabilities: Arc<RwLock<HashMap<Uuid, BTreeSet<String>>>>,
+    /// What the nodes we've exchanged keys with told us they handle, see `negotiated`
+    negotiated: Arc<RwLock<HashMap<Uuid, PeerParams>>>,
+    target: EventTarget<FLESHMessage>,
+    router_target: EventTarget<RoutingMessage>,
+    /// Every message heard, before anything's filtered out, see `monitor`
+    monitor: EventTarget<FLESHMessage>,
+    announcements: SharedAnnouncements,
+    events: EventTarget<NetworkEvent>,
+    middleware: MiddlewareChain,
+    taps: Taps,
+    channels: Arc<ChannelTable>,
+    config: Arc<NetworkConfig>,
+    shutdown: CancellationToken,
+    pub(crate) key: SigningKey,
+    pub id: Uuid,
+    transport: T,
+}
+
+impl<T: PacketTransport + Clone + 'static> Network<T> {
+    /// Creates a new Network instance that operates over any compatible packet transport.
+    pub fn new(transport: T) -> Self { Self::with_config(transport, NetworkConfig::default()) }
+
+    /// Starts configuring a Network, see `NetworkBuilder`.
+    pub fn builder(transport: T) -> NetworkBuilder<T> { NetworkBuilder::new(transport) }
+
+    /// Resumes a network from a snapshot taken with `export_state`, keeping its identity and the
+    /// keys it knew. Whether those nodes are reachable, and how, is relearned as they're heard from.
+    pub fn from_state(transport: T, state: NetworkState) -> Self {
+        Self::restore(transport, NetworkConfig::default(), state, SharedResolution::default(), SharedAnnouncements::default())
+    }
+
+    /// Like `new`, recording every frame sent and received to `path` for debugging, see `Journaled`.
+    /// A recording can be played back into another network with `journal::replay_journal`.
+    pub fn with_journal(transport: T, path: impl AsRef<Path>) -> io::Result<Network<Journaled<T>>> {
+        Ok(Network::new(Journaled::new(transport, path)?))
+    }
+
+    pub(crate) fn with_config(transport: T, config: NetworkConfig) -> Self {
+        Self::restore(transport, config, NetworkState::new(Uuid::new_v4()), SharedResolution::default(), SharedAnnouncements::default())
+    }
+
+    pub(crate) fn restore(
+        transport: T,
+        config: NetworkConfig,
+        state: NetworkState,
+        shared: SharedResolution,
+        announcements: SharedAnnouncements,
+    ) -> Self {
+        let saved = state.nodes.into_iter().filter_map(|(id, key)| match VerifyingKey::from_bytes(&key) {
+            Ok(key) => Some((id, key)),
+            Err(e) => {
+                warn!("Dropping invalid key for {id} from saved state: {e}");
+                None
+            }
+        });
+
+        match shared.nodes.try_write() {
+            Ok(mut nodes) => saved.for_each(|(id, key)| nodes.announced(id, key)),
+            // Only a map shared with a running network can be busy, in which case it'll be free shortly
+            Err(_) => {
+                let (nodes, saved) = (shared.nodes.clone(), saved.collect::<Vec<_>>());
+                spawn(async move {
+                    let mut nodes = nodes.write().await;
+                    saved.into_iter().for_each(|(id, key)| nodes.announced(id, key));
+                });
+            }
+        }
+
+        let s = Self {
+            id: state.id,
+            key: SigningKey::from_bytes(&state.key),
+            nodes: shared.nodes,
+            pending: shared.pending,
+            collections: Default::default(),
+            receipts: Default::default(),
+            inflight: Default::default(),
+            #[cfg(feature = "crypto")]
+            groups: Default::default(),
+            delivered: Default::default(),
+            resolve_queue: Default::default(),
+            resolve_queued: Default::default(),
+            resolution_latency: Default::default(),
+            peers_changed: shared.peers_changed,
+            sequences: Default::default(),
+            relayed: Default::default(),
+            relay_slots: Arc::new(Semaphore::new(config.max_relays.unwrap_or(Semaphore::MAX_PERMITS))),
+            mailbox: Arc::new(Mutex::new(Mailbox::new(config.mailbox))),
+            mistimed: Default::default(),
+            links: Default::default(),
+            signal: Default::default(),
+            reassembler: Default::default(),
+            aliases: Default::default(),
+            alias: Default::default(),
+            capabilities: Default::default(),
+            peer_capabilities: Default::default(),
+            negotiated: Default::default(),
+            target: Default::default(),
+            router_target: Default::default(),
+            monitor: Default::default(),
+            announcements,
+            events: Default::default(),
+            middleware: Default::default(),
+            taps: Default::default(),
+            channels: Arc::new(ChannelTable::new()),
+            config: Arc::new(config),
+            shutdown: CancellationToken::new(),
+            transport,
+        };
+
+        info!("Resolver #{} started.", s.id);
+
+        // Spawn the main loop that receives all incoming packets from the transport
+        spawn(s.clone().packet_processing_loop());
+
+        // Spawn the handler for internal routing messages (requests/responses for keys)
+        spawn(s.clone().handle_requests(s.router_target.as_stream()));
+
+        // Passive networks only listen, so have no use for the tasks that transmit unprompted
+        if !s.config.passive {
+            // Spawn the task that periodically broadcasts a discovery message
+            spawn(s.clone().periodic_announcements());
+
+            if let Some(every) = s.config.beacon_interval {
+                spawn(s.clone().periodic_beacons(every));
+            }
+
+            // Spawn the task that puts queued key requests on the air at a steady pace
+            spawn(s.clone().pace_resolutions());
+        }
+
+        s
+    }
+
+    /// Adds a hook that sees every data message we send or deliver, and may change or drop it.
+    /// Hooks run in the order they were added. Outbound messages are seen before they're sequenced,
+    /// so changing a signed message invalidates its signature.
+    pub fn add_middleware(&self, middleware: impl Fn(&mut FLESHMessage, Direction) -> Decision + Send + Sync + 'static) {
+        self.middleware.push(middleware);
+    }
+
+    /// A handle to the keys and routes we know, for `NetworkBuilder::share_resolution`, so a node
+    /// on several networks at once (say LoRa and IP) only has to resolve each peer on one of them.
+    pub fn share_resolution(&self) -> SharedResolution {
+        SharedResolution { nodes: self.nodes.clone(), pending: self.pending.clone(), peers_changed: self.peers_changed.clone() }
+    }
+
+    /// A handle to when we last announced ourselves, for `NetworkBuilder::share_announcements`, so
+    /// several networks speaking for the same node over one transport don't repeat each other.
+    pub fn share_announcements(&self) -> SharedAnnouncements { self.announcements.clone() }
+
+    /// Calls `tap` with every frame exactly as it's handed to the transport to send, routing traffic
+    /// included, for audit logs that need to show what went out. Runs on the sending task, so
+    /// anything slow should be passed off elsewhere.
+    pub fn on_tx(&self, tap: impl Fn(&[u8]) + Send + Sync + 'static) { self.taps.push(Direction::Outbound, tap) }
+
+    /// Calls `tap` with every frame exactly as the transport received it, before it's decoded.
+    pub fn on_rx(&self, tap: impl Fn(&[u8]) + Send + Sync + 'static) { self.taps.push(Direction::Inbound, tap) }
+
+    /// Every message we hear, as soon as it's decoded: routing traffic, messages for other nodes,
+    /// our own echoed back and ones we'd drop as mistimed included. Meant for sniffers and analysers,
+    /// as nothing on it has been checked. Split messages show up once they've been put back together.
+    pub fn monitor(&self) -> impl Stream<Item = Arc<FLESHMessage>> + use<T> { self.monitor.as_stream() }
+
+    /// Messages as from `as_stream`, for consumers that run as long as the node does. The stream keeps
+    /// the network alive while it's held, carries on across the transport failing and coming back
+    /// (see `reconnect::Reconnecting`), and resubscribes should it ever end, so it only ends once the
+    /// network's shut down.
+    pub fn resilient_stream(&self) -> impl Stream<Item = Arc<FLESHMessage>> + Unpin + use<T> {
+        Box::pin(futures::stream::unfold((self.clone(), self.as_stream()), |(s, mut stream)| async move {
+            loop {
+                if let Some(m) = stream.next().await {
+                    return Some((m, (s, stream)));
+                }
+
+                if s.shutdown.is_cancelled() {
+                    return None;
+                }
+
+                stream = s.as_stream();
+            }
+        }))
+    }
+
+    /// Diagnostic events about the network's operation, separate from the message stream.
+    pub fn events(&self) -> &EventTarget<NetworkEvent> { &self.events }
+
+    /// Snapshots our identity and the keys of every node we know, for `from_state`.
+    pub async fn export_state(&self) -> NetworkState {
+        let nodes = self.nodes.read().await.keys().map(|(id, key)| (id, key.to_bytes())).collect();
+        NetworkState { id: self.id, key: self.key.to_bytes(), nodes }
+    }
+
+    /// The transport we're running over, for reaching its own API (radio settings, metrics and so on).
+    /// The network keeps using it from background tasks throughout, so anything done through
+    /// it happens alongside our own sends and receives.
+    pub fn transport(&self) -> &T { &self.transport }
+
+    /// Mutable access to this handle's transport. Background tasks hold their own clones, so only
+    /// changes to state the clones share (as with most transports, which are handles to a shared
+    /// connection) are seen by the network as a whole.
+    pub fn transport_mut(&mut self) -> &mut T { &mut self.transport }
+
+    /// A short form of `id` for display, kept distinct from every other node we know of.
+    pub async fn short_id(&self, id: Uuid) -> ShortId {
+        let nodes = self.nodes.read().await;
+        ShortId::new(id, nodes.keys().map(|(id, _)| id).chain([self.id]))
+    }
+
+    /// Our id on the network, random unless one was given to `NetworkBuilder::id`.
+    pub fn local_id(&self) -> Uuid { self.id }
+
+    fn identity(&self) -> (Uuid, SigningKey) { (self.id, self.key.clone()) }
+
+    /// Messages dropped since starting for being timestamped too far from our own clock,
+    /// see `NetworkBuilder::clock_skew`.
+    pub fn mistimed_messages(&self) -> usize { self.mistimed.load(Ordering::Relaxed) }
+
+    /// How long resolutions that went out to the network took to come back, for tuning how long
+    /// to wait on them. Resolutions answered from what we already knew aren't counted.
+    pub async fn resolution_latency_histogram(&self) -> LatencyHistogram { self.resolution_latency.lock().await.clone() }
+
+    /// Messages we're holding for nodes that have gone quiet, see `NetworkBuilder::mailbox`.
+    pub async fn held_messages(&self) -> usize { self.mailbox.lock().await.len() }
+
+    /// Asks the relays in range for anything they've held for us while we were away, see
+    /// `NetworkBuilder::mailbox`. Announcing has the same effect, so this is for nodes waking
+    /// from sleep that don't want to wait for their next announcement.
+    pub async fn pull_mail(&self) -> Result<(), SendError> {
+        self.put(None, &RoutingMessage::PullMail(self.id).to_message()?.serialize()?).await?;
+        Ok(())
+    }
+
+    /// Whether a message's timestamp is close enough to our own clock to be believed.
+    fn plausibly_timed(&self, m: &FLESHMessage) -> bool {
+        let Some(skew) = self.config.clock_skew else { return true };
+        clock::now().abs_diff(m.timestamp) <= skew.as_secs()
+    }
+
+    /// The main inbound message loop. It continually waits for packets from the
+    /// transport, deserializes them, and forwards them to the correct handler.
+    async fn packet_processing_loop(self) {
+        let mut transport = self.transport.clone();
+        let mut failing = false;
+        loop {
+            let received = select! {
+                received = transport.recv_measured() => received,
+                _ = self.shutdown.cancelled() => return,
+            };
+
+            match received {
+                Ok((link, data, rssi)) => {
+                    if failing {
+                        self.recovered();
+                        failing = false;
+                    }
+
+                    self.taps.see(Direction::Inbound, &data);
+                    if self.refuse_oversized(&data) {
+                        continue;
+                    }
+
+                    // Parts are held back until whole, so split and unsplit messages share one path from here
+                    let Ok(frame) = InternalMessage::from_frame(data) else { continue };
+                    let Some(InternalMessage::Complete(data)) = self.reassembler.lock().await.insert(frame) else {
+                        continue;
+                    };
+
+                    if let Ok(mut message) = FLESHMessage::deserialize(&data) {
+                        message.source = Some(link);
+                        self.monitor.emit(message.clone());
+
+                        // Our own messages coming back around (echoed or re-broadcast) were already handled locally
+                        if message.sender == Some(self.id) {
+                            continue;
+                        }
+
+                        // A timestamp is only the sender's say-so, and believing any would undo expiry and ordering
+                        if !self.plausibly_timed(&message) {
+                            self.mistimed.fetch_add(1, Ordering::Relaxed);
+                            debug!("Dropping {:?} message timestamped {}", message.status, message.timestamp);
+                            continue;
+                        }
+
+                        let mut routing = RoutingMessage::from_message(&message);
+                        if let Ok(Some(RoutingMessage::Relay(_, _, inner))) = &mut routing {
+                            inner.source = Some(link);
+                        }
+
+                        if let Some(origin) = message.sender.or(routing.as_ref().ok().and_then(Option::as_ref).and_then(RoutingMessage::origin)) {
+                            self.heard_on(origin, link, &message).await;
+                            if let Some(rssi) = rssi {
+                                self.signal.write().await.insert(origin, rssi);
+                            }
+                        }
+
+                        // Messages targeted at someone else aren't ours to surface. Anything we're asked to
+                        // pass on arrives wrapped in a `Relay` addressed to us, so still gets through
+                        match routing {
+                            _ if !message.for_id(self.identity()) => {
+                                trace!("Ignoring message for {:?}", message.target)
+                            }
+                            Ok(Some(rm)) => self.router_target.emit(rm),
+                            Ok(None) => self.deliver(message).await,
+                            Err(e) => warn!("Dropping malformed {:?} message: {e}", message.status),
+                        }
+                    }
+                }
+                Err(e) => {
+                    failing = true;
+                    error!("Transport receive error: {}. Retrying in 1s.", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await; // Avoid tight error loop
+                }
+            }
+        }
+    }
+
+    /// Remembers which link we heard `origin` on, so replies go back the same way. Who a frame is from is
+    /// only its say-so unless it's signed with their key, so only a signed message can move a node to
+    /// another link. Unsigned ones that disagree leave the link contested, and replies go out on every link
+    /// rather than wherever a spoofer asked.
+    async fn heard_on(&self, origin: Uuid, link: TransportId, m: &FLESHMessage) {
+        #[cfg(feature = "crypto")]
+        let signed = m.sender == Some(origin)
+            && m.signature.is_some()
+            && self.nodes.read().await.key(&origin).is_some_and(|key| m.verify(&key).is_ok());
+        // Without `crypto` nothing's signed, so no link is ever more than claimed
+        #[cfg(not(feature = "crypto"))]
+        let signed = {
+            let _ = m;
+            false
+        };
+
+        let mut links = self.links.write().await;
+        let heard = match (links.get(&origin), signed) {
+            (_, true) => Link::Verified(link),
+            (None, false) => Link::Claimed(link),
+            (Some(Link::Claimed(known)), false) if *known != link => Link::Contested,
+            (Some(known), false) => *known,
+        };
+
+        links.insert(origin, heard);
+    }
+
+    /// Picks back up once the transport's receiving again after failing, announcing us so nodes
+    /// that gave up on us while we were gone can reach us again (and send on any mail they held).
+    fn recovered(&self) {
+        info!("Transport is receiving again");
+        self.events.emit(NetworkEvent::Reconnected);
+        if !self.config.passive {
+            let s = self.clone();
+            spawn(async move {
+                if let Err(e) = s.announce_now().await {
+                    debug!("Couldn't announce after reconnecting: {e}");
+                }
+            });
+        }
+    }
+
+    /// Refuses a frame addressed to us that's over `NetworkConfig::max_inbound_frame`, answering its
+    /// sender with a `TooLarge` naming the limit so they can split it. Returns whether it was refused.
+    fn refuse_oversized(&self, data: &[u8]) -> bool {
+        let Some(max) = self.config.max_inbound_frame.filter(|max| data.len() > *max) else {
+            return false;
+        };
+
+        // Broadcasts are left alone, as answering them would have everyone in range reply at once
+        let Ok(m) = FLESHMessage::deserialize(data) else { return false };
+        if !m.addressed_to(self.id) {
+            return false;
+        }
+
+        // Whoever asked us to relay it is only passing it on, it's the original sender who needs telling
+        let m = match RoutingMessage::from_message(&m) {
+            Ok(Some(RoutingMessage::Relay(_, _, inner))) => inner,
+            _ => m,
+        };
+
+        debug!("Refusing a {} byte frame from {:?}, over our limit of {max}", data.len(), m.sender);
+        let mut refusal = FLESHMessage::new(Status::TooLarge).with_header("max", (max as u32).to_le_bytes().to_vec());
+        if let Some(id) = m.receipt() {
+            refusal = refusal.with_header("receipt_for", id);
+        }
+
+        let s = self.clone();
+        spawn(async move {
+            if let Err(e) = s.reply(&m, refusal).await {
+                debug!("Couldn't tell {:?} their message was too large: {e}", m.sender);
+            }
+        });
+
+        true
+    }
+
+    /// Hands a message relayed to us to the application. A split message can arrive as relayed parts,
+    /// which are reassembled alongside those heard directly before it's delivered.
+    async fn deliver_relayed(&self, m: FLESHMessage) {
+        if !matches!(m.status, Status::Fragment) {
+            return self.deliver(m).await;
+        }
+
+        let source = m.source;
+        let part = match m.serialize().and_then(InternalMessage::from_frame) {
+            Ok(part) => part,
+            Err(e) => return warn!("Dropping malformed relayed part: {e}"),
+        };
+
+        let Some(InternalMessage::Complete(data)) = self.reassembler.lock().await.insert(part) else { return };
+        let mut whole = match FLESHMessage::deserialize(&data) {
+            Ok(whole) => whole,
+            Err(e) => return warn!("Dropping relayed message that didn't reassemble: {e}"),
+        };
+
+        whole.source = source;
+        match RoutingMessage::from_message(&whole) {
+            Ok(Some(rm)) => self.router_target.emit(rm),
+            Ok(None) => self.deliver(whole).await,
+            Err(e) => warn!("Dropping malformed {:?} message: {e}", whole.status),
+        }
+    }
+
+    /// Hands a received data message to the application.
+    async fn deliver(&self, m: FLESHMessage) {
+        self.track_sequence(&m).await;
+        if matches!(m.status, Status::Channel) {
+            return self.channel_segment(m).await;
+        }
+
+        if let Some(id) = m.headers.get("receipt_for").and_then(|id| Uuid::from_slice(id).ok()) {
+            if let Some(waiter) = self.receipts.lock().await.remove(&id) {
+                let _ = waiter.send(m);
+            }
+            return;
+        }
+
+        let status = self.check(&m).await;
+        if let Some(id) = m.receipt().filter(|_| m.addressed_to(self.id)) {
+            let receipt = FLESHMessage::new(status).with_header("receipt_for", id);
+            let (s, to) = (self.clone(), m.clone());
+            spawn(async move {
+                if let Err(e) = s.reply(&to, receipt).await {
+                    debug!("Couldn't send receipt {id}: {e}");
+                }
+            });
+
+            let mut delivered = self.delivered.lock().await;
+            delivered.retain(|_, at| at.elapsed() < Duration::from_secs(RELAY_MEMORY_SECS));
+            if delivered.insert(id, Instant::now()).is_some() {
+                trace!("Not delivering {id} again, it's a retransmission");
+                return;
+            }
+        }
+
+        if !status.is_ok() {
+            warn!("Dropping message from {:?} that failed checks: {}", m.sender, status.name());
+            return;
+        }
+
+        if let Some(id) = m.headers.get("collect").and_then(|id| Uuid::from_slice(id).ok()) {
+            match (m.status, m.sender, m.target) {
+                // Someone's counting who hears their broadcast, so let them know we did
+                (_, Some(_), None) => {
+                    let ack = FLESHMessage::new(Status::Acknowledge).with_header("collect", id);
+                    let (s, to) = (self.clone(), m.clone());
+                    spawn(async move {
+                        if let Err(e) = s.reply(&to, ack).await {
+                            debug!("Couldn't acknowledge broadcast {id}: {e}");
+                        }
+                    });
+                }
+                (Status::Acknowledge, Some(from), Some(_)) => {
+                    if let Some(collection) = self.collections.lock().await.get(&id) {
+                        let _ = collection.send(from);
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        // A group's admin handing us its key is for us, rather than the application
+        #[cfg(feature = "crypto")]
+        if m.addressed_to(self.id) && m.headers.contains_key("group_key") {
+            spawn(self.clone().learn_group(m));
+            return;
+        }
+
+        let Some(m) = self.middleware.apply(m, Direction::Inbound) else {
+            trace!("Middleware dropped an inbound message");
+            return;
+        };
+
+        self.target.emit(m);
+    }
+
+    /// Whether a message is intact, when `NetworkBuilder::check_inbound` is on: `Unauthorized` if
+    /// its signature doesn't hold up, `UnprocessableEntity` if it's encrypted but not for us.
+    async fn check(&self, m: &FLESHMessage) -> Status {
+        if !self.config.check_inbound {
+            return Status::Acknowledge;
+        }
+
+        // Resolving here would hold up the packet loop, so only nodes we already know are checked
+        let key = match m.sender {
+            Some(sender) => self.nodes.read().await.key(&sender),
+            None => None,
+        };
+
+        match (key, &m.signature) {
+            #[cfg(feature = "crypto")]
+            (Some(key), Some(_)) if m.verify(&key).is_err() => Status::Unauthorized,
+            #[cfg(feature = "crypto")]
+            _ if m.headers.contains_key("ephemeral_key") && m.clone().decrypt_body(&self.identity()).is_err() => {
+                Status::UnprocessableEntity
+            }
+            _ => Status::Acknowledge,
+        }
+    }
+
+    /// Passes a segment to its channel, starting one if this is the first segment of a new channel.
+    async fn channel_segment(&self, m: FLESHMessage) {
+        let (Some((id, segment)), Some(peer)) = (Segment::from_message(&m), m.sender) else {
+            warn!("Dropping malformed channel segment");
+            return;
+        };
+
+        if self.channels.route(id, peer, segment.clone()).await {
+            return;
+        }
+
+        // Our last ack went missing, so the peer's still resending what we already have
+        if let Some((owner, next)) = self.channels.closed(id).await {
+            if owner == peer && matches!(segment, Segment::Data { .. }) {
+                let ack = Segment::Ack { next, sack: vec![] }.to_message(id, peer);
+                if let Err(e) = self.send(ack).await {
+                    debug!("Failed to acknowledge closed channel {id}: {e}");
+                }
+            }
+
+            return;
+        }
+
+        if let Segment::Data { at: 0, .. } = segment {
+            debug!("{peer} opened channel {id}");
+            let channel = channel::start(self.clone(), self.channels.clone(), id, peer).await;
+            self.channels.route(id, peer, segment).await;
+            self.channels.offer(channel);
+        }
+    }
+
+    /// Records the `seq` header of a message addressed to us, reporting any skipped numbers.
+    async fn track_sequence(&self, m: &FLESHMessage) {
+        let (Some(from), true) = (m.sender, m.addressed_to(self.id)) else {
+            return;
+        };
+
+        let Some(seq) = m.headers.get("seq").and_then(|v| v.as_slice().try_into().ok()).map(u32::from_le_bytes) else {
+            return;
+        };
+
+        // A late or repeated message says nothing about loss, and mustn't wind the count back
+        let last = {
+            let received = &mut self.sequences.lock().await.received;
+            if received.get(&from).is_some_and(|last| seq <= *last) {
+                return;
+            }
+
+            received.insert(from, seq)
+        };
+
+        if let Some(last) = last.filter(|last| seq > last.saturating_add(1)) {
+            warn!("Missed {} message(s) from {from}", seq - last - 1);
+            self.events.emit(NetworkEvent::Gap { from, missing: last + 1..seq });
+        }
+    }
+
+    /// Handles routing logic by listening for `RoutingMessage` events and
+    /// sending replies or new requests via the transport.
+    async fn handle_requests(self, e: impl Stream<Item = Arc<RoutingMessage>>) {
+        e.for_each(|v| {
+            let s = self.clone();
+
+            async move {
+                let me = s.identity();
+                let nodes = &s.nodes;
+                let reply = match RoutingMessage::clone(&*v) {
+                    // Announcements aren't passed on, so anyone we hear announce is in range
+                    RoutingMessage::Announce(uuid) => {
+                        let mut nodes = nodes.write().await;
+                        match nodes.contains(&uuid) {
+                            true => {
+                                nodes.pong(uuid);
+                                spawn(s.clone().deliver_mail(uuid));
+                            }
+                            false => s.queue_resolution(uuid, Priority::Background).await,
+                        }
+                        None
+                    }
+                    // Beacons only vouch for nodes we already know, so unlike announcements never start a resolution
+                    RoutingMessage::Beacon(uuid) => {
+                        let mut nodes = nodes.write().await;
+                        if nodes.contains(&uuid) {
+                            nodes.pong(uuid);
+                        }
+                        None
+                    }
+                    // Like announcements, requests for mail aren't passed on, so come from someone in range
+                    RoutingMessage::PullMail(uuid) if uuid != me.id() => {
+                        nodes.write().await.pong(uuid);
+                        spawn(s.clone().deliver_mail(uuid));
+                        None
+                    }
+                    // A nil target is a ping to anyone listening, see `scan`
+                    RoutingMessage::Ping(to, from) => {
+                        (to == me.id() || to.is_nil()).then_some(RoutingMessage::Pong(from, me.id()))
+                    }
+                    RoutingMessage::Pong(to, from) if to == me.id() => {
+                        nodes.write().await.pong(from);
+                        s.events.emit(NetworkEvent::Pong { from });
+                        None
+                    }
+                    // Whoever asked is about to have our key, so can check what we tell them we handle
+                    RoutingMessage::RequestKey(uuid) if uuid == me.id() => {
+                        s.answer(RoutingMessage::ProvideKey(me.id(), me.key().verifying_key().as_bytes().to_vec(), None)).await;
+                        if let Err(e) = s.greet(Uuid::nil()).await {
+                            debug!("Couldn't send our handshake: {e}");
+                        }
+                        None
+                    }
+                    RoutingMessage::RequestKey(_) if s.config.passive => None,
+                    // Answering for others (finding them ourselves first if need be) spreads the request
+                    // outwards, and leaves every node on the way back with a route through the one before
+                    RoutingMessage::RequestKey(uuid) => {
+                        spawn(s.clone().answer_for(uuid));
+                        None
+                    }
+                    RoutingMessage::ProvideKey(uuid, _, via) if uuid == me.id() || via == Some(me.id()) => None,
+                    RoutingMessage::ProvideKey(uuid, key, via) => {
+                        if let Some(key) = key.as_slice().try_into().ok().and_then(|k| VerifyingKey::from_bytes(k).ok()) {
+                            {
+                                let mut nodes = nodes.write().await;
+                                if !nodes.accepts(&uuid, &key) {
+                                    warn!("Refusing a key for {uuid} that doesn't match the one preloaded for it");
+                                    return;
+                                }
+                                nodes.announced(uuid, key);
+                                match via {
+                                    None => nodes.pong(uuid),
+                                    Some(via) => nodes.relayed(uuid, via),
+                                }
+                            }
+                            s.peers_changed.notify_waiters();
+
+                            // A node we'd no key for when mail arrived for them can be reached now
+                            if via.is_none() {
+                                spawn(s.clone().deliver_mail(uuid));
+                            }
+
+                            // Hand the key to everyone waiting on this resolution
+                            let waiters = s.pending.lock().await.remove(&uuid).unwrap_or_default();
+                            let asked = !waiters.is_empty();
+                            for waiter in waiters {
+                                let _ = waiter.send(key);
+                            }
+
+                            // Having asked for them, tell them what we handle, if we've yet to hear what they do
+                            if asked && via.is_none() && !s.negotiated.read().await.contains_key(&uuid)
+                                && let Err(e) = s.greet(uuid).await
+                            {
+                                debug!("Couldn't send our handshake to {uuid}: {e}");
+                            }
+                        }
+                        None
+                    }
+                    RoutingMessage::Alias(uuid, alias, signature) if uuid != me.id() => {
+                        spawn(s.clone().learn_alias(uuid, alias, signature));
+                        None
+                    }
+                    RoutingMessage::Capabilities(uuid, capabilities) if uuid != me.id() => {
+                        s.peer_capabilities.write().await.insert(uuid, capabilities.into_iter().collect());
+                        None
+                    }
+                    RoutingMessage::Handshake(from, to, params, signature) if from != me.id() => {
+                        spawn(s.clone().learn_params(from, to == me.id(), params, signature));
+                        None
+                    }
+                    RoutingMessage::Leave(uuid, signature) if uuid != me.id() => {
+                        s.learn_leave(uuid, signature).await;
+                        None
+                    }
+                    RoutingMessage::RequestRelayCapability(uuid) if nodes.read().await.can_relay(&uuid) => {
+                        Some(RoutingMessage::ProvideRelayCapability(me.id(), uuid, true))
+                    }
+                    RoutingMessage::ProvideRelayCapability(from, to, status) if status => {
+                        nodes.write().await.relayed(to, from);
+                        None
+                    }
+                    RoutingMessage::Relay(_, _, msg) if msg.sender == Some(me.id()) => {
+                        trace!("Dropping our own message, relayed back to us");
+                        None
+                    }
+                    RoutingMessage::Relay(uuid, _, msg) if uuid == me.id() => {
+                        s.deliver_relayed(msg).await;
+                        None
+                    }
+                    RoutingMessage::Relay(..) if s.config.passive => None,
+                    RoutingMessage::Relay(uuid, _, msg) if !s.config.forward => msg.sender.map(|origin| {
+                        debug!("Declining to relay to {uuid}, forwarding is disabled");
+                        RoutingMessage::RelayFailure(origin, SendError::NotForwarding.to_string(), msg.correlation_id().map(Into::into))
+                    }),
+                    RoutingMessage::Relay(uuid, hops, msg) if !s.relayed.lock().await.first_pass(&msg, hops) => {
+                        trace!("Not relaying to {uuid} again, the message has looped back to us");
+                        None
+                    }
+                    // Relays are passed on in the background, up to as many at once as we've the capacity for
+                    RoutingMessage::Relay(uuid, hops, msg) => match s.relay_slots.clone().try_acquire_owned() {
+                        Ok(slot) => {
+                            let s = s.clone();
+                            spawn(async move {
+                                let failure = s.relay(uuid, hops, msg).await;
+                                drop(slot);
+                                if let Some(failure) = failure {
+                                    s.answer(failure).await;
+                                }
+                            });
+                            None
+                        }
+                        Err(_) => msg.sender.map(|origin| {
+                            debug!("Declining to relay to {uuid}, already relaying as much as we can");
+                            RoutingMessage::RelayFailure(origin, SendError::Busy.to_string(), msg.correlation_id().map(Into::into))
+                        }),
+                    },
+                    RoutingMessage::RelayFailure(uuid, reason, correlation) if uuid == me.id() => {
+                        error!("Relay failed: {reason}");
+                        s.events.emit(NetworkEvent::RelayFailed { reason, correlation });
+                        None
+                    }
+                    _ => None,
+                };
+
+                if let Some(reply) = reply {
+                    s.answer(reply).await;
+                }
+            }
+        })
+        .await;
+    }
+
+    /// Sends a response to a routing message, or a new request.
+    async fn answer(&self, reply: RoutingMessage) {
+        if self.config.passive {
+            return;
+        }
+
+        // Failures are for a node that may be several hops away, so they're routed rather than broadcast
+        let origin = match &reply {
+            RoutingMessage::RelayFailure(origin, ..) => Some(*origin),
+            _ => None,
+        };
+
+        if let Ok(msg) = reply.to_message() {
+            match origin {
+                Some(origin) if self.route(origin, self.config.max_hops, msg.clone().with_target(origin)).await.is_ok() => {}
+                _ => {
+                    let _ = self.put(None, &msg.serialize().unwrap()).await;
+                }
+            }
+        }
+    }
+
+    /// Passes on a message we were asked to relay, returning the failure to tell its sender
+    /// about if we couldn't, as they won't hear otherwise.
+    async fn relay(&self, id: Uuid, hops: u8, m: FLESHMessage) -> Option<RoutingMessage> {
+        let (origin, correlation) = (m.sender, m.correlation_id().map(String::from));
+        let result = match self.config.mailbox.is_some() && self.quiet(&id).await {
+            true => Err(SendError::NoRoute(id)),
+            false => self.forward(id, hops, m.clone()).await,
+        };
+
+        // Nodes that are asleep or out of range for now get their messages when they're back, if we're holding mail
+        if matches!(result, Err(SendError::NoRoute(_) | SendError::UnknownNode(_))) && self.mailbox.lock().await.hold(id, hops, m) {
+            debug!("Holding a message for {id} until they're back");
+            return None;
+        }
+
+        result.err().zip(origin).map(|(e, origin)| {
+            warn!("Couldn't relay to {id}: {e}");
+            RoutingMessage::RelayFailure(origin, e.to_string(), correlation)
+        })
+    }
+
+    /// Whether a node in range has gone longer than it should between announcements without being
+    /// heard, as one that's asleep or has wandered off would.
+    async fn quiet(&self, id: &Uuid) -> bool {
+        let most = self.config.announce_interval.end + self.config.announce_jitter;
+        self.nodes.read().await.quiet(id, most)
+    }
+
+    /// Sends on anything we've been holding for a node that's just been heard from.
+    async fn deliver_mail(self, id: Uuid) {
+        let held = self.mailbox.lock().await.take(id);
+        for (hops, m) in held {
+            if let Err(e) = self.forward(id, hops, m).await {
+                warn!("Couldn't deliver a held message to {id}: {e}");
+            }
+        }
+    }
+
+    /// Periodically broadcasts a request for its own ID to the network,
+    /// serving as a discovery and presence mechanism. Announcements slow down
+    /// while the set of nodes we can see is stable, and speed up when it changes.
+    async fn periodic_announcements(self) {
+        if let Err(e) = self.transport.ready().await {
+            error!("Transport never became ready, not announcing: {e}");
+            return;
+        }
+
+        let mut schedule = AnnounceSchedule::new(self.config.announce_interval.clone());
+        loop {
+            select! {
+                _ = tokio::time::sleep(schedule.interval + self.announce_jitter()) => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+
+            // Another network on the same transport may have just announced us, in which case we'd only repeat it.
+            // Half an interval leaves room for the two schedules drifting, without both deciding the other has it.
+            if !self.announcements.claim(self.id, schedule.interval / 2).await {
+                trace!("Skipping an announcement, another network sharing ours just made it");
+            } else if let Err(e) = self.announce().await {
+                warn!("Failed to announce: {e}");
+            }
+
+            let interval = schedule.next(self.nodes.read().await.live());
+            trace!("Next announcement in {interval:?}");
+        }
+    }
+
+    /// Sends a beacon every `every`, see `NetworkBuilder::beacon_interval`.
+    async fn periodic_beacons(self, every: Duration) {
+        if self.transport.ready().await.is_err() {
+            return;
+        }
+
+        loop {
+            select! {
+                _ = tokio::time::sleep(every + self.announce_jitter()) => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+
+            if let Err(e) = self.beacon().await {
+                warn!("Failed to send a beacon: {e}");
+            }
+        }
+    }
+
+    async fn beacon(&self) -> Result<(), SendError> {
+        self.put(None, &RoutingMessage::Beacon(self.id).to_message()?.serialize()?).await?;
+        Ok(())
+    }
+
+    /// Announces us straight away, along with our alias and capabilities if we've any, rather than
+    /// waiting for the next scheduled announcement, say right after starting or once a transport's
+    /// reconnected. Waits up to the configured jitter first, as nodes prompted together would otherwise
+    /// talk over each other. Fails on passive networks, which never transmit.
+    pub async fn announce_now(&self) -> Result<(), SendError> {
+        tokio::time::sleep(self.announce_jitter()).await;
+        self.announcements.claim(self.id, Duration::ZERO).await;
+        self.announce().await
+    }
+
+    async fn announce(&self) -> Result<(), SendError> {
+        let announcement = RoutingMessage::Announce(self.id).to_message()?;
+        self.put(None, &announcement.serialize()?).await?;
+        self.announce_alias().await?;
+        self.announce_capabilities().await
+    }
+
+    /// A random extra wait before announcing, so nodes that started together (say, after a power
+    /// cut) don't keep announcing over the top of each other.
+    fn announce_jitter(&self) -> Duration {
+        match self.config.announce_jitter.as_millis() as u64 {
+            0 => Duration::ZERO,
+            max => Duration::from_millis(OsRng.next_u64() % max),
+        }
+    }
+
+    /// Resolves the verifying key of a node, asking the network if we don't already know it.
+    /// Concurrent resolutions of the same id are coalesced into a single `RequestKey`, with
+    /// every caller receiving the same result.
+    pub async fn resolve(&self, id: Uuid) -> anyhow::Result<VerifyingKey> { self.resolve_with(id, Priority::Urgent).await }
+
+    /// Waits until we know the keys of at least `count` nodes, so can reach them, for up to `wait`.
+    /// Returns whether they turned up in time.
+    pub async fn wait_for_peers(&self, count: usize, wait: Duration) -> bool {
+        timeout(wait, async {
+            loop {
+                // Registered before checking, so a key learned in between still wakes us
+                let mut changed = std::pin::pin!(self.peers_changed.notified());
+                changed.as_mut().enable();
+                if self.nodes.read().await.live().len() >= count {
+                    return;
+                }
+                changed.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+
+    /// Trusts the keys of a known roster up front, so they can be reached without resolving them first
+    /// and can't be impersonated by whoever answers for them first. Each node is taken to be in range
+    /// until we learn otherwise, and keys other nodes offer for them that don't match are refused.
+    pub async fn preload_keys(&self, keys: &[(Uuid, VerifyingKey)]) {
+        let mut nodes = self.nodes.write().await;
+        for (id, key) in keys {
+            nodes.pin(*id, *key);
+        }
+        drop(nodes);
+        self.peers_changed.notify_waiters();
+    }
+
+    /// Drops a node's key, and any routes through it, as if it had left. We'll have to resolve it
+    /// afresh to reach it again, picking up a new key if it's changed identity. Keys from
+    /// `preload_keys` are kept, as the node's still expected to use them, unless `unpin_key` is called first.
+    pub async fn forget(&self, id: &Uuid) {
+        self.nodes.write().await.left(*id);
+        self.links.write().await.remove(id);
+        self.signal.write().await.remove(id);
+        self.peer_capabilities.write().await.remove(id);
+        self.negotiated.write().await.remove(id);
+        self.events.emit(NetworkEvent::Left { id: *id });
+    }
+
+    /// Stops trusting the key `preload_keys` gave for `id` and drops it, for a node that's changed identity.
+    /// The next key it's resolved with is taken instead.
+    pub async fn unpin_key(&self, id: &Uuid) {
+        self.nodes.write().await.unpin(*id);
+        self.forget(id).await;
+    }
+
+    /// Where we're at with reaching `id`, for explaining why a node can't be reached.
+    pub async fn resolution_state(&self, id: &Uuid) -> ResolutionState {
+        let seen = self.nodes.read().await.seen(id);
+        match seen {
+            Some(Some(seen)) if NodeRelationshipMap::fresh(&Some(seen)) => ResolutionState::Resolved { age: seen.elapsed() },
+            _ if self.pending.lock().await.contains_key(id) => ResolutionState::Pending,
+            Some(_) => ResolutionState::Stale,
+            None => ResolutionState::Unknown,
+        }
+    }
+
+    async fn resolve_with(&self, id: Uuid, priority: Priority) -> anyhow::Result<VerifyingKey> {
+        if let Some(key) = self.nodes.read().await.key(&id) {
+            return Ok(key);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let first = {
+            let mut pending = self.pending.lock().await;
+            let waiters = pending.entry(id).or_default();
+            waiters.push(tx);
+            waiters.len() == 1
+        };
+
+        // Only the first caller queues a request, the rest piggyback on it
+        let started = Instant::now();
+        let mut waiting = Waiting { network: self.clone(), id, priority, requested: first, answered: false };
+        if first {
+            self.queue_resolution(id, priority).await;
+        }
+
+        match timeout(Duration::from_secs(RESOLVE_TIMEOUT_SECS), rx).await {
+            Ok(Ok(key)) => {
+                waiting.answered = true;
+                if first {
+                    self.resolution_latency.lock().await.record(started.elapsed());
+                }
+                Ok(key)
+            }
+            _ => Err(anyhow!("Failed to resolve node {id}")),
+        }
+    }
+
+    /// Gives up the place of a caller that's stopped waiting on `id`, leaving anyone else waiting in
+    /// theirs. If it was their request the rest were piggybacking on, it's sent again for them.
+    async fn stop_waiting(&self, id: Uuid, priority: Priority, requested: bool) {
+        let mut pending = self.pending.lock().await;
+        let Some(waiters) = pending.get_mut(&id) else { return };
+        waiters.retain(|waiter| !waiter.is_closed());
+        if waiters.is_empty() {
+            pending.remove(&id);
+            return;
+        }
+
+        drop(pending);
+        if requested {
+            self.queue_resolution(id, priority).await;
+        }
+    }
+
+    async fn queue_resolution(&self, id: Uuid, priority: Priority) {
+        self.resolve_queue.lock().await.push(id, priority);
+        self.resolve_queued.notify_one();
+    }
+
+    /// Sends queued key requests, no faster than the configured rate.
+    async fn pace_resolutions(self) {
+        let mut bucket = TokenBucket::new(self.config.resolve_rate, self.config.resolve_burst);
+        loop {
+            if self.resolve_queue.lock().await.is_empty() {
+                select! {
+                    _ = self.resolve_queued.notified() => continue,
+                    _ = self.shutdown.cancelled() => return,
+                }
+            }
+
+            // Wait for a token before choosing, so anything urgent queued meanwhile goes first
+            while let Err(wait) = bucket.take() {
+                select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = self.shutdown.cancelled() => return,
+                }
+            }
+
+            let Some(id) = self.resolve_queue.lock().await.pop() else {
+                continue;
+            };
+
+            // Someone may have answered an earlier request for them while they were queued
+            if self.nodes.read().await.knows(&id) {
+                continue;
+            }
+
+            let request = RoutingMessage::RequestKey(id).to_message().unwrap().serialize().unwrap();
+            if let Err(e) = self.put(None, &request).await {
+                warn!("Failed to request key for {id}: {e}");
+            }
+        }
+    }
+
+    /// Answers a `RequestKey` for another node with its key, offering to relay to them.
+    /// Stays quiet if we can't find them either.
+    async fn answer_for(self, id: Uuid) {
+        let Ok(key) = self.resolve_with(id, Priority::Background).await else {
+            trace!("Can't answer for {id}, not resolvable from here either");
+            return;
+        };
+
+        let answer = RoutingMessage::ProvideKey(id, key.as_bytes().to_vec(), Some(self.id));
+        if let Ok(frame) = answer.to_message().and_then(|m| m.serialize()) {
+            let _ = self.put(None, &frame).await;
+        }
+    }
+
+    /// Tells our peers we're leaving so they can drop us (and any routes through us) straight away,
+    /// rather than waiting for us to go stale, then stops announcing and handling incoming messages.
+    /// Anything still queued by the transport is sent first.
+    pub async fn shutdown(&self) -> Result<(), SendError> {
+        let signature = sign_claim(&self.key, &leave_claim(self.id));
+        let leave = RoutingMessage::Leave(self.id, signature).to_message()?.serialize()?;
+        let sent = self.put(None, &leave).await;
+        let _ = self.flush().await;
+        self.shutdown.cancel();
+        Ok(sent?)
+    }
+
+    /// Waits for everything sent so far to go out, for transports that queue frames rather than sending
+    /// them straight away, see `PacketTransport::flush`. `shutdown` does this itself, after saying goodbye.
+    pub async fn flush(&self) -> io::Result<()> { self.transport.flush().await }
+
+    /// Opens an ordered, reliable byte stream to `target`, resolving them first if need be.
+    /// The other node picks it up with `accept_channel`.
+    pub async fn open_channel(&self, target: Uuid) -> Result<Channel, SendError> {
+        self.resolve(target).await.map_err(|_| SendError::UnknownNode(target))?;
+        Ok(channel::start(self.clone(), self.channels.clone(), Uuid::new_v4(), target).await)
+    }
+
+    /// Waits for another node to open a channel to us.
+    pub async fn accept_channel(&self) -> Option<Channel> { self.channels.accept().await }
+
+    /// Claims a human readable name other nodes can address us by, see `resolve_alias`.
+    /// The claim is signed, and repeated alongside our announcements.
+    pub async fn set_alias(&self, alias: impl Into<String>) -> Result<(), SendError> {
+        *self.alias.write().await = Some(alias.into());
+        self.announce_alias().await
+    }
+
+    async fn announce_alias(&self) -> Result<(), SendError> {
+        let Some(alias) = self.alias.read().await.clone() else {
+            return Ok(());
+        };
+
+        let signature = sign_claim(&self.key, &alias_claim(self.id, &alias));
+        let claim = RoutingMessage::Alias(self.id, alias, signature).to_message()?;
+        Ok(self.put(None, &claim.serialize()?).await?)
+    }
+
+    /// Advertises what we can do (say "relay", or "compression") to the nodes in range, so they can
+    /// pick us out with `capable` and `send_to_capable`. Repeated alongside our announcements.
+    pub async fn set_capabilities(&self, capabilities: impl IntoIterator<Item = impl Into<String>>) -> Result<(), SendError> {
+        *self.capabilities.write().await = capabilities.into_iter().map(Into::into).collect();
+        self.announce_capabilities().await
+    }
+
+    async fn announce_capabilities(&self) -> Result<(), SendError> {
+        let capabilities = self.capabilities.read().await.iter().cloned().collect::<Vec<_>>();
+        if capabilities.is_empty() {
+            return Ok(());
+        }
+
+        let advert = RoutingMessage::Capabilities(self.id, capabilities).to_message()?;
+        Ok(self.put(None, &advert.serialize()?).await?)
+    }
+
+    /// Tells `to` (or, given a nil id, anyone in range who knows us) our protocol version, the largest
+    /// frame we'll accept and what we can do, signed so they can hold us to it. Sent when we first
+    /// exchange keys with a node.
+    async fn greet(&self, to: Uuid) -> Result<(), SendError> {
+        let params = PeerParams {
+            version: WIRE_VERSION,
+            max_frame: self.config.max_inbound_frame.map(|max| max as u32),
+            features: self.capabilities.read().await.iter().cloned().collect(),
+        };
+
+        let signature = sign_claim(&self.key, &postcard::to_allocvec(&params).map_err(MessageError::SerializationError)?);
+        let greeting = RoutingMessage::Handshake(self.id, to, params, signature).to_message()?;
+        Ok(self.put(None, &greeting.serialize()?).await?)
+    }
+
+    /// Forgets `id` on hearing it leave, as long as it was signed with the key we hold for it. Anyone could
+    /// claim another node's leaving, and we'd drop its key and routes on their say-so.
+    async fn learn_leave(&self, id: Uuid, signature: Vec<u8>) {
+        let Some(key) = self.nodes.read().await.key(&id) else { return };
+        if !verify_claim(&key, &leave_claim(id), &signature) {
+            warn!("Dropping a forged leave for {id}");
+            return;
+        }
+
+        self.forget(&id).await;
+    }
+
+    /// Records what a node told us it handles, once its signature checks out. Only handshakes meant
+    /// for us are worth resolving their sender over, those we overhear are checked against keys we know.
+    async fn learn_params(self, id: Uuid, for_us: bool, params: PeerParams, signature: Vec<u8>) {
+        let known = self.nodes.read().await.key(&id);
+        let key = match known {
+            Some(key) => key,
+            None if for_us => match self.resolve(id).await {
+                Ok(key) => key,
+                Err(_) => {
+                    debug!("Ignoring a handshake from {id}, who we can't resolve");
+                    return;
+                }
+            },
+            None => return,
+        };
+
+        let record = postcard::to_allocvec(&params).unwrap_or_default();
+        if !verify_claim(&key, &record, &signature) {
+            warn!("Dropping a forged handshake for {id}");
+            return;
+        }
+
+        if params.version != WIRE_VERSION {
+            warn!("{id} speaks wire format version {}, we speak {WIRE_VERSION}", params.version);
+        }
+
+        self.peer_capabilities.write().await.insert(id, params.features.iter().cloned().collect());
+        self.negotiated.write().await.insert(id, params);
+    }
+
+    /// What `id` told us it handles when we first exchanged keys, `None` until it has. Sends to it
+    /// are kept within its limits, being split to fit (or failing with `SendError::TooLarge` when
+    /// fragmenting is disabled) rather than being refused on arrival.
+    pub async fn negotiated(&self, id: &Uuid) -> Option<PeerParams> { self.negotiated.read().await.get(id).cloned() }
+
+    /// Nodes we can reach that have advertised `capability`, see `set_capabilities`.
+    pub async fn capable(&self, capability: &str) -> Vec<Uuid> {
+        let advertised = self.peer_capabilities.read().await;
+        let nodes = self.nodes.read().await;
+        advertised.iter().filter(|(id, c)| c.contains(capability) && nodes.knows(id)).map(|(id, _)| *id).collect()
+    }
+
+    /// Sends a message to every node we can reach that has advertised `capability`, encrypted for and
+    /// addressed to each in turn, and signed by us. Returns who it was sent to, leaving out anyone
+    /// it couldn't be.
+    #[cfg(feature = "crypto")]
+    pub async fn send_to_capable(
+        &self,
+        capability: &str,
+        status: Status,
+        body: impl Into<Vec<u8>>,
+    ) -> Result<Vec<Uuid>, SendError> {
+        let body = body.into();
+        let mut sent = vec![];
+        for id in self.capable(capability).await {
+            let result = async {
+                let key = self.resolve(id).await.map_err(|_| SendError::UnknownNode(id))?;
+                let m = FLESHMessage::builder(status)
+                    .with_target(id)
+                    .with_body(body.clone())
+                    .encrypt_for(key)
+                    .build()?
+                    .sign_with(self.id, &self.key)?;
+                self.send(m).await
+            };
+
+            match result.await {
+                Ok(()) => sent.push(id),
+                Err(e) => debug!("Couldn't send to {id}, advertising {capability}: {e}"),
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Checks a node's claim to an alias against its key before recording it. Claims we can't check
+    /// (as the node can't be resolved) are kept, but give way to any verified claim to the same name.
+    async fn learn_alias(self, id: Uuid, alias: String, signature: Vec<u8>) {
+        let verified = match self.resolve(id).await {
+            Ok(key) if verify_claim(&key, &alias_claim(id, &alias), &signature) => true,
+            Ok(_) => {
+                warn!("Dropping forged claim to alias '{alias}' for {id}");
+                return;
+            }
+            Err(_) => false,
+        };
+
+        let mut aliases = self.aliases.write().await;
+        match aliases.get(&alias) {
+            Some((holder, true)) if !verified && *holder != id => {
+                trace!("Ignoring unverified claim to '{alias}', held by {holder}")
+            }
+            _ => {
+                aliases.insert(alias, (id, verified));
+            }
+        }
+    }
+
+    /// The node that claimed `alias`. Aliases are advisory, so anything sensitive should
+    /// still check who it's talking to.
+    pub async fn resolve_alias(&self, alias: &str) -> Option<Uuid> { self.aliases.read().await.get(alias).map(|(id, _)| *id) }
+
+    /// Sends a message to whichever node claimed `alias`.
+    pub async fn send_to_alias(&self, alias: &str, m: FLESHMessage) -> Result<(), SendError> {
+        let id = self.resolve_alias(alias).await.ok_or(SendError::UnknownAlias(alias.to_string()))?;
+        self.send(m.with_target(id)).await
+    }
+
+    /// Pings every node in earshot and reports those that answer within `window`, for a
+    /// one-off picture of who's out there.
+    pub async fn scan(&self, window: Duration) -> Result<Vec<NodeSnapshot>, SendError> {
+        let mut pongs = self.events.as_stream();
+        let started = Instant::now();
+        self.put(None, &RoutingMessage::Ping(Uuid::nil(), self.id).to_message()?.serialize()?).await?;
+
+        let mut found = BTreeMap::new();
+        let deadline = tokio::time::sleep(window);
+        tokio::pin!(deadline);
+        loop {
+            select! {
+                _ = &mut deadline => break,
+                event = pongs.next() => match event.as_deref() {
+                    Some(NetworkEvent::Pong { from }) => {
+                        found.entry(*from).or_insert_with(|| started.elapsed());
+                    }
+                    Some(_) => {}
+                    None => break,
+                },
+            }
+        }
+
+        let (nodes, signal) = (self.nodes.read().await, self.signal.read().await);
+        Ok(found
+            .into_iter()
+            .map(|(id, rtt)| NodeSnapshot {
+                id,
+                rtt,
+                rssi: signal.get(&id).copied(),
+                relation: nodes.get(&id).map(|(relation, _)| relation),
+            })
+            .collect())
+    }
+
+    /// Checks a message's signature against its sender's key, resolving the key if we don't have it yet.
+    #[cfg(feature = "crypto")]
+    pub async fn verify_message(&self, m: &FLESHMessage) -> Result<(), MessageError> {
+        let sender = m.sender.ok_or(MessageError::MissingSender)?;
+        let key = self.resolve(sender).await.map_err(|_| MessageError::UnknownSender(sender))?;
+        m.verify(&key)
+    }
+
+    /// Handles routing with or without a specified target via m.target
+    pub async fn send(&self, m: FLESHMessage) -> Result<(), SendError> { self.send_with_hops(m, self.config.max_hops).await }
+
+    /// Sends `response` back to whoever sent `to`, resolving them first if we haven't yet. If `to`
+    /// arrived encrypted, the response is encrypted for its sender too. `to`'s correlation id is echoed
+    /// on the response, unless it has its own or is already signed.
+    pub async fn reply(&self, to: &FLESHMessage, response: FLESHMessage) -> Result<(), SendError> {
+        let sender = to.sender.ok_or(MessageError::MissingSender)?;
+        let response = match to.correlation_id() {
+            Some(id) if response.correlation_id().is_none() && response.signature.is_none() => response.with_correlation_id(id),
+            _ => response,
+        };
+        // Resolving them also finds us a route, so happens whether or not there's anything to encrypt
+        let key = self.resolve(sender).await.map_err(|_| SendError::UnknownNode(sender))?;
+        #[cfg(feature = "crypto")]
+        let response = match to.headers.contains_key("ephemeral_key") {
+            true => response.encrypt_body(&key)?,
+            false => response,
+        };
+        #[cfg(not(feature = "crypto"))]
+        let _ = key;
+
+        self.send(response.with_target(sender)).await
+    }
+
+    /// Sends `value` to `to`, encrypted for them and signed by us, for them to read with `messages`.
+    #[cfg(feature = "crypto")]
+    pub async fn send_secure<V: Serialize>(&self, to: Uuid, value: &V) -> Result<(), SendError> {
+        let key = self.resolve(to).await.map_err(|_| SendError::UnknownNode(to))?;
+        let body = postcard::to_allocvec(value).map_err(MessageError::SerializationError)?;
+        let m = FLESHMessage::builder(Status::Acknowledge)
+            .with_target(to)
+            .with_body(body)
+            .encrypt_for(key)
+            .build()?
+            .sign_with(self.id, &self.key)?;
+        self.send(m).await
+    }
+
+    /// Every message we receive as a `V` along with who sent it, once it's been verified as theirs,
+    /// decrypted and decoded. Messages that fall short, including unsigned ones, are left out and
+    /// reported as `NetworkEvent::Unreadable`. Messages sent to a group are read with `group_messages`.
+    pub fn messages<V: DeserializeOwned>(&self) -> impl Stream<Item = (Uuid, V)> + use<T, V> {
+        let s = self.clone();
+        self.as_stream().filter_map(move |m| {
+            let s = s.clone();
+            async move {
+                if m.headers.contains_key("group") {
+                    return None;
+                }
+
+                s.open(&m)
+                    .await
+                    .inspect_err(|e| s.events.emit(NetworkEvent::Unreadable { from: m.sender, reason: e.to_string() }))
+                    .ok()
+            }
+        })
+    }
+
+    /// Starts an encrypted group of us and `members`, with us as its admin, handing each member the
+    /// group's key. Returns the group's id, for `send_group`. Fails if any member couldn't be reached,
+    /// once the key's been offered to all of them.
+    #[cfg(feature = "crypto")]
+    pub async fn create_group(&self, members: impl IntoIterator<Item = Uuid>) -> Result<Uuid, SendError> {
+        let id = Uuid::new_v4();
+        let group = GroupKey::new(id, self.id, members.into_iter().chain([self.id]).collect());
+        self.groups.write().await.insert(id, group.clone());
+        self.hand_out(&group).await?;
+        Ok(id)
+    }
+
+    /// Adds `member` to a group we're the admin of, handing them its key and the rest the new list of members.
+    #[cfg(feature = "crypto")]
+    pub async fn add_member(&self, group: Uuid, member: Uuid) -> Result<(), SendError> {
+        let group = self
+            .change_group(group, |g| {
+                let mut g = g.clone();
+                g.info.members.insert(member);
+                g
+            })
+            .await?;
+        self.hand_out(&group).await
+    }
+
+    /// Removes `member` from a group we're the admin of, re-keying it for the members left, so
+    /// nothing sent to the group from now on can be read by them.
+    #[cfg(feature = "crypto")]
+    pub async fn remove_member(&self, group: Uuid, member: Uuid) -> Result<(), SendError> {
+        let group = self
+            .change_group(group, |g| {
+                let mut g = g.rekeyed();
+                g.info.members.remove(&member);
+                g
+            })
+            .await?;
+        self.hand_out(&group).await
+    }
+
+    /// Replaces a group we're the admin of with `change`'s take on it.
+    #[cfg(feature = "crypto")]
+    async fn change_group(&self, id: Uuid, change: impl FnOnce(&GroupKey) -> GroupKey) -> Result<GroupKey, SendError> {
+        let mut groups = self.groups.write().await;
+        let group = groups.get_mut(&id).ok_or(SendError::UnknownGroup(id))?;
+        if group.info.admin != self.id {
+            return Err(SendError::NotGroupAdmin(id));
+        }
+
+        *group = change(group);
+        Ok(group.clone())
+    }
+
+    /// Sends each of a group's members (but us) its key and members, encrypted for them and signed by us.
+    #[cfg(feature = "crypto")]
+    async fn hand_out(&self, group: &GroupKey) -> Result<(), SendError> {
+        let record = postcard::to_allocvec(group).map_err(MessageError::SerializationError)?;
+        let mut result = Ok(());
+        for &member in group.info.members.iter().filter(|m| **m != self.id) {
+            let sent = async {
+                let key = self.resolve(member).await.map_err(|_| SendError::UnknownNode(member))?;
+                let m = FLESHMessage::builder(Status::Acknowledge)
+                    .with_target(member)
+                    .with_header("group_key", group.info.id)
+                    .with_body(record.clone())
+                    .encrypt_for(key)
+                    .build()?
+                    .sign_with(self.id, &self.key)?;
+                self.send(m).await
+            }
+            .await;
+
+            if let Err(e) = sent {
+                debug!("Couldn't hand group {}'s key to {member}: {e}", group.info.id);
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// Takes on a group's key from its admin, or drops the group if they've removed us from it.
+    #[cfg(feature = "crypto")]
+    async fn learn_group(self, m: FLESHMessage) {
+        let learned = async {
+            self.verify_message(&m).await?;
+            let body = m.clone().decrypt_body(&self.identity())?.body;
+            let group: GroupKey = postcard::from_bytes(&body).map_err(MessageError::DeserializationError)?;
+            Ok::<_, MessageError>((m.sender.ok_or(MessageError::MissingSender)?, group))
+        };
+
+        let (from, group) = match learned.await {
+            Ok(learned) => learned,
+            Err(e) => {
+                warn!("Dropping a group key we couldn't read: {e}");
+                return;
+            }
+        };
+
+        let (id, mut groups) = (group.info.id, self.groups.write().await);
+        match groups.get(&id) {
+            _ if group.info.admin != from => warn!("Dropping a key for group {id} from {from}, who isn't its admin"),
+            Some(known) if known.info.admin != from || known.info.epoch > group.info.epoch => {
+                warn!("Dropping an outdated or forged key for group {id}")
+            }
+            _ if !group.info.members.contains(&self.id) => {
+                groups.remove(&id);
+            }
+            _ => {
+                groups.insert(id, group);
+            }
+        }
+    }
+
+    /// A group we're in, as we last heard of it.
+    #[cfg(feature = "crypto")]
+    pub async fn group(&self, id: Uuid) -> Option<GroupInfo> { self.groups.read().await.get(&id).map(|g| g.info.clone()) }
+
+    /// Sends `value` to each of a group's members (but us), encrypted with the group's key and signed
+    /// by us, for them to read with `group_messages`.
+    #[cfg(feature = "crypto")]
+    pub async fn send_group<V: Serialize>(&self, id: Uuid, value: &V) -> Result<(), SendError> {
+        let group = self.groups.read().await.get(&id).cloned().ok_or(SendError::UnknownGroup(id))?;
+        let body = postcard::to_allocvec(value).map_err(MessageError::SerializationError)?;
+        let sealed = group.seal(FLESHMessage::new(Status::Acknowledge).with_body(body))?;
+        for &member in group.info.members.iter().filter(|m| **m != self.id) {
+            self.send(sealed.clone().with_target(member).sign_with(self.id, &self.key)?).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Every message sent to group `id` as a `V` along with who sent it, once it's been verified as
+    /// theirs, decrypted and decoded. Like `messages`, those that fall short are reported as
+    /// `NetworkEvent::Unreadable`, including anything sent under a key we no longer (or don't yet) hold.
+    #[cfg(feature = "crypto")]
+    pub fn group_messages<V: DeserializeOwned>(&self, id: Uuid) -> impl Stream<Item = (Uuid, V)> + use<T, V> {
+        let s = self.clone();
+        self.as_stream().filter_map(move |m| {
+            let s = s.clone();
+            async move {
+                if group_of(&m) != Some(id) {
+                    return None;
+                }
+
+                s.open_group(&m)
+                    .await
+                    .inspect_err(|e| s.events.emit(NetworkEvent::Unreadable { from: m.sender, reason: e.to_string() }))
+                    .ok()
+            }
+        })
+    }
+
+    /// Verifies, decrypts and decodes a message sent to a group we're in, as `group_messages` does,
+    /// returning who sent it. Fails for messages sent under any key but the group's current one.
+    #[cfg(feature = "crypto")]
+    pub async fn open_group<V: DeserializeOwned>(&self, m: &FLESHMessage) -> Result<(Uuid, V), MessageError> {
+        let (sender, id) = (m.sender.ok_or(MessageError::MissingSender)?, group_of(m).ok_or(MessageError::MissingEncryptionData)?);
+        self.verify_message(m).await?;
+        let body = match self.groups.read().await.get(&id) {
+            Some(group) => group.open(m)?,
+            None => return Err(MessageError::DecryptionError),
+        };
+
+        Ok((sender, postcard::from_bytes(&body).map_err(MessageError::DeserializationError)?))
+    }
+
+    /// A handle for the application on `port`, which tags what it sends with the port and hears
+    /// only messages tagged the same, so several applications can share one network.
+    pub fn app(&self, port: u16) -> App<T> { App::new(self.clone(), port) }
+
+    /// Every message we receive tagged for the application on `port`, see `Network::app`.
+    pub fn subscribe_app(&self, port: u16) -> impl Stream<Item = Arc<FLESHMessage>> + use<T> {
+        self.as_stream().filter(move |m| std::future::ready(m.app() == Some(port)))
+    }
+
+    /// Verifies, decrypts and decodes a message for `messages`. Without `crypto` there's nothing to check it with.
+    async fn open<V: DeserializeOwned>(&self, m: &FLESHMessage) -> Result<(Uuid, V), MessageError> {
+        let sender = m.sender.ok_or(MessageError::MissingSender)?;
+        #[cfg(feature = "crypto")]
+        self.verify_message(m).await?;
+
+        #[cfg(feature = "crypto")]
+        let body = match m.headers.contains_key("ephemeral_key") {
+            true => m.clone().decrypt_body(&self.identity())?.body,
+            false => m.body.clone(),
+        };
+        #[cfg(not(feature = "crypto"))]
+        let body = m.body.clone();
+
+        Ok((sender, postcard::from_bytes(&body).map_err(MessageError::DeserializationError)?))
+    }
+
+    /// Like `send`, but allowing the message through at most `hops` relays rather than the configured default.
+    pub async fn send_with_hops(&self, m: FLESHMessage, hops: u8) -> Result<(), SendError> {
+        let m = self.prepare(m).await?;
+        self.send_prepared(m, hops).await
+    }
+
+    /// Sends a message with as much confirmation as `mode` asks for. With `AckMode::None` it's sent
+    /// as with `send`, and the recipient isn't asked to acknowledge it even if it carries a receipt id
+    /// (unless that's signed in). With `AckMode::Ack` the recipient is asked to acknowledge it and we
+    /// wait up to `wait` to hear back, without resending. `AckMode::AckAndRetry` is `send_reliable`.
+    pub async fn send_with_ack(&self, m: FLESHMessage, mode: AckMode, wait: Duration) -> Result<(), SendError> {
+        match mode {
+            AckMode::None => {
+                let m = match m.signature {
+                    Some(_) => m,
+                    None => FLESHMessage { headers: m.headers.into_iter().filter(|(h, _)| h != "receipt").collect(), ..m },
+                };
+                self.send(m).await
+            }
+            AckMode::Ack => self.send_acknowledged(m, wait, false).await,
+            AckMode::AckAndRetry => self.send_acknowledged(m, wait, true).await,
+        }
+    }
+
+    /// Sends a targeted message, resending it until the recipient confirms they got it or `wait` runs
+    /// out. The first resend is after `RELIABLE_RETRY_MS`, with the wait doubling each time after up to
+    /// `RELIABLE_RETRY_MAX_MS`, and up to half of each wait again added at random (see `Backoff`).
+    /// Recipients checking what they receive (see `NetworkBuilder::check_inbound`) answer messages
+    /// that fail with why, surfaced here as errors.
+    /// Messages refused for being too large for a node on the way are split to fit and sent again,
+    /// failing with `SendError::TooLarge` if they can't be.
+    pub async fn send_reliable(&self, m: FLESHMessage, wait: Duration) -> Result<(), SendError> {
+        self.send_acknowledged(m, wait, true).await
+    }
+
+    /// Sends a targeted message and waits for its receipt, resending it along the way if `retry` is set.
+    async fn send_acknowledged(&self, m: FLESHMessage, wait: Duration, retry: bool) -> Result<(), SendError> {
+        let target = m.target.ok_or(MessageError::MissingTarget)?;
+        let m = match (m.receipt(), &m.signature) {
+            (Some(_), _) => m,
+            (None, None) => m.with_receipt(),
+            (None, Some(_)) => return Err(SendError::NeedsReceipt),
+        };
+
+        let id = m.receipt().ok_or(SendError::NeedsReceipt)?;
+        let hops = self.config.max_hops;
+        // Shutting down calls off every send still in flight
+        let cancel = self.shutdown.child_token();
+        self.inflight.lock().await.insert(id, (InflightSend { id, target, attempts: 0 }, cancel.clone()));
+        let result = async {
+            // Prepared once, so every retransmission is the same message
+            let m = self.prepare(m).await?;
+            let deadline = tokio::time::sleep(wait);
+            tokio::pin!(deadline);
+            // Size of the parts the message is split into, once someone's refused it whole
+            let mut chunk = None;
+            loop {
+                let (tx, mut rx) = oneshot::channel();
+                self.receipts.lock().await.insert(id, tx);
+                let mut backoff =
+                    Backoff::new(Duration::from_millis(RELIABLE_RETRY_MS), Duration::from_millis(RELIABLE_RETRY_MAX_MS));
+
+                let reply = loop {
+                    if let Some((send, _)) = self.inflight.lock().await.get_mut(&id) {
+                        send.attempts += 1;
+                    }
+
+                    let sent = match chunk {
+                        Some(chunk) => self.send_parts(m.clone(), chunk, hops, cancel.clone()).await,
+                        None => self.send_prepared(m.clone(), hops).await,
+                    };
+                    match sent {
+                        // Too large for what the target told us it accepts, so resending won't help
+                        Err(e @ SendError::TooLarge { .. }) => return Err(e),
+                        Err(e) => debug!("Reliable send {id} to {target} failed, will retry: {e}"),
+                        Ok(_) => {}
+                    }
+
+                    select! {
+                        reply = &mut rx => break reply.map_err(|_| SendError::Timeout)?,
+                        _ = tokio::time::sleep(backoff.wait()), if retry => {}
+                        _ = &mut deadline => return Err(SendError::Timeout),
+                        _ = cancel.cancelled() => return Err(SendError::Withdrawn(id)),
+                    }
+                };
+
+                match reply.status {
+                    status if status.is_ok() => return Ok(()),
+                    Status::TooLarge => {
+                        let max = reply.headers.get("max").and_then(|max| Some(u32::from_le_bytes(max.as_slice().try_into().ok()?)));
+                        let max = max.ok_or(SendError::Rejected { by: target, status: Status::TooLarge })? as usize;
+                        let size = m.serialize()?.len();
+                        match self.chunk_size(&m, max, hops) {
+                            // Splitting to fit should have been enough, so there's no use trying again
+                            Ok(fits) if chunk.is_none_or(|chunk| fits < chunk) => chunk = Some(fits),
+                            _ => return Err(SendError::TooLarge { size, max }),
+                        }
+                        debug!("Reliable send {id} to {target} was too large for a node on the way, splitting it");
+                    }
+                    Status::Unauthorized => return Err(SendError::Unverified(target)),
+                    Status::UnprocessableEntity => return Err(SendError::Undecryptable(target)),
+                    status => return Err(SendError::Rejected { by: target, status }),
+                }
+            }
+        }
+        .await;
+
+        self.receipts.lock().await.remove(&id);
+        self.inflight.lock().await.remove(&id);
+        result
+    }
+
+    /// Reliable sends still waiting on their receipt, see `send_reliable` and `send_with_ack`.
+    pub async fn inflight(&self) -> Vec<InflightSend> {
+        self.inflight.lock().await.values().map(|(send, _)| send.clone()).collect()
+    }
 
-pub const RESOLUTION_TTL_SECS: u64 = 5000;
-pub const ANNOUNCE_DURATION_SECS: u64 = 30;
+    /// Stops resending the in-flight send with receipt id `id`, which then fails with
+    /// `SendError::Withdrawn`. Returns whether there was such a send.
+    pub async fn cancel(&self, id: Uuid) -> bool {
+        self.inflight.lock().await.get(&id).map(|(_, cancel)| cancel.cancel()).is_some()
+    }
 
-#[derive(Clone)]
-pub struct Network<T: PacketTransport> {
-    nodes: Arc<RwLock<NodeRelationshipMap>>,
-    target: EventTarget<FLESHMessage>,
-    router_target: EventTarget<RoutingMessage>,
-    pub(crate) key: SigningKey,
-    pub id: Uuid,
-    transport: T,
-}
+    /// Puts a message that's been through `prepare` on the air, splitting it first if it's too large.
+    async fn send_prepared(&self, m: FLESHMessage, hops: u8) -> Result<(), SendError> {
+        let size = m.serialize()?.len();
+        match self.frame_limit(m.target).await {
+            Some(max) if self.config.fragment && size > max => {
+                let chunk = self.chunk_size(&m, max, hops)?;
+                self.send_parts(m, chunk, hops, CancellationToken::new()).await
+            }
+            Some(max) if size > max => Err(SendError::TooLarge { size, max }),
+            _ => self.dispatch(m, hops).await,
+        }
+    }
 
-impl<T: PacketTransport + Clone + 'static> Network<T> {
-    /// Creates a new Network instance that operates over any compatible packet transport.
-    pub fn new(transport: T) -> Self {
-        let mut rng = OsRng;
-        let key = SigningKey::generate(&mut rng);
-        let id = Uuid::new_v4();
+    /// The largest frame we can send towards `target`, the smaller of our transport's limit and
+    /// the one they told us they'll accept.
+    async fn frame_limit(&self, target: Option<Uuid>) -> Option<usize> {
+        let theirs = match target {
+            Some(id) => self.negotiated.read().await.get(&id).and_then(|p| p.max_frame).map(|max| max as usize),
+            None => None,
+        };
 
-        let s = Self {
-            id,
-            key,
-            nodes: Default::default(),
-            target: Default::default(),
-            router_target: Default::default(),
-            transport,
+        match (self.transport.max_frame(), theirs) {
+            (Some(ours), Some(theirs)) => Some(ours.min(theirs)),
+            (ours, theirs) => ours.or(theirs),
+        }
+    }
+
+    /// Runs an outgoing message through middleware, sequences it, and stamps targeted ones with
+    /// our id, as relays report failures back to the sender so they need to know who that is.
+    async fn prepare(&self, m: FLESHMessage) -> Result<FLESHMessage, SendError> {
+        let m = self.middleware.apply(m, Direction::Outbound).ok_or(SendError::Dropped)?;
+        let m = self.sequence(m).await;
+        Ok(match (m.target, m.sender, &m.signature) {
+            (Some(_), None, None) => m.with_sender(self.id),
+            _ => m,
+        })
+    }
+
+    /// Puts a prepared message on the air, broadcast or routed to its target.
+    async fn dispatch(&self, m: FLESHMessage, hops: u8) -> Result<(), SendError> {
+        match m.target {
+            None => self.put(None, &self.frame(&m)?).await?,
+            Some(id) => self.route(id, hops, m).await?,
+        }
+
+        Ok(())
+    }
+
+    /// The most of a message each part can carry while still fitting in a `max` byte frame,
+    /// allowing for the part's own headers and a relay wrapping it.
+    fn chunk_size(&self, m: &FLESHMessage, max: usize, hops: u8) -> Result<usize, SendError> {
+        let size = m.serialize()?.len();
+        let (_, parts) = InternalMessage::split(m, size)?;
+        let part = parts.into_iter().next().ok_or(MessageError::InvalidFragment)?;
+
+        let wrapped = match m.target {
+            Some(id) => RoutingMessage::Relay(id, hops, part).to_message()?.with_target(id).serialize()?.len(),
+            None => part.serialize()?.len(),
         };
 
-        info!("Resolver #{} started.", s.id);
-        let identity = (s.id, s.key.clone());
+        // Slack for length prefixes growing or shrinking with the chunk
+        let overhead = wrapped - size + 8;
+        match max.checked_sub(overhead) {
+            Some(chunk) if chunk > 0 => Ok(chunk),
+            _ => Err(SendError::TooLarge { size, max }),
+        }
+    }
 
-        // Spawn the main loop that receives all incoming packets from the transport
-        spawn(Self::packet_processing_loop(
-            s.target.clone(),
-            s.router_target.clone(),
-            identity.clone(),
-            s.transport.clone(),
-        ));
+    /// Sends a prepared message as parts of at most `chunk` bytes, reporting progress as it goes.
+    async fn send_parts(&self, m: FLESHMessage, chunk: usize, hops: u8, cancel: CancellationToken) -> Result<(), SendError> {
+        let (id, parts) = InternalMessage::split(&m, chunk)?;
+        let total = parts.len();
+        for (i, part) in parts.into_iter().enumerate() {
+            if i > 0 {
+                // Leave the (half-duplex) medium some room between parts
+                select! {
+                    _ = tokio::time::sleep(Duration::from_millis(SPLIT_DELAY_MS)) => {}
+                    _ = cancel.cancelled() => {}
+                }
+            }
 
-        // Spawn the handler for internal routing messages (requests/responses for keys)
-        spawn(Self::handle_requests(identity, s.router_target.as_stream(), s.nodes.clone(), s.transport.clone(), {
-            let t = s.target.clone();
-            move |m: FLESHMessage| {
-                t.emit(m);
+            if cancel.is_cancelled() {
+                return Err(SendError::Cancelled { sent: i, total });
             }
-        }));
 
-        // Spawn the task that periodically broadcasts a discovery message
-        spawn(Self::periodic_announcements(s.id, s.transport.clone()));
+            self.dispatch(part, hops).await?;
+            self.events.emit(NetworkEvent::SendProgress { id, sent: i + 1, total });
+        }
 
-        s
+        Ok(())
     }
 
-    /// The main inbound message loop. It continually waits for packets from the
-    /// transport, deserializes them, and forwards them to the correct handler.
-    async fn packet_processing_loop(
-        target: EventTarget<FLESHMessage>,
-        router_target: EventTarget<RoutingMessage>,
-        id: impl Identity + Clone,
-        mut transport: T,
-    ) {
-        loop {
-            match transport.recv().await {
-                Ok(data) => {
-                    if let Ok(message) = FLESHMessage::deserialize(&data) {
-                        match RoutingMessage::from_message(&message) {
-                            Ok(Some(rm)) if message.for_id(id.clone()) => router_target.emit(rm),
-                            _ => target.emit(message),
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("Transport receive error: {}. Retrying in 1s.", e);
-                    tokio::time::sleep(Duration::from_secs(1)).await; // Avoid tight error loop
-                }
+    /// Sends a message towards `id`, wrapping it for the next relay if we can't reach them directly.
+    /// `hops` is how many relays the message may still pass through.
+    async fn route(&self, id: Uuid, hops: u8, m: FLESHMessage) -> Result<(), SendError> {
+        let target = {
+            let nodes = self.nodes.read().await;
+            match nodes.get(&id) {
+                Some(target) => target,
+                None if nodes.contains(&id) => return Err(SendError::NoRoute(id)),
+                None => return Err(SendError::UnknownNode(id)),
+            }
+        };
+
+        match target.0 {
+            NodeRelation::Local => self.transmit(id, &self.frame(&m)?).await?,
+            NodeRelation::Relay { .. } if hops == 0 => return Err(SendError::HopLimit(id)),
+            NodeRelation::Relay { via } => {
+                let m = RoutingMessage::Relay(id, hops, m).to_message()?.with_target(via);
+                self.transmit(via, &self.frame(&m)?).await?
             }
         }
+
+        Ok(())
     }
 
-    /// Handles routing logic by listening for `RoutingMessage` events and
-    /// sending replies or new requests via the transport.
-    async fn handle_requests(
-        me: impl Identity + Clone,
-        e: impl Stream<Item = Arc<RoutingMessage>>,
-        nodes: Arc<RwLock<NodeRelationshipMap>>,
-        transport: T,
-        emit: impl Fn(FLESHMessage) + Clone,
-    ) {
-        e.for_each(|v| {
-            let transport = transport.clone();
-            let nodes = nodes.clone();
-            let me = me.clone();
-            let emit = emit.clone();
+    /// Passes on a message we were asked to relay, spending one of its hops.
+    async fn forward(&self, id: Uuid, hops: u8, m: FLESHMessage) -> Result<(), SendError> {
+        let hops = hops.checked_sub(1).ok_or(SendError::HopLimit(id))?;
+        self.route(id, hops, m).await
+    }
 
-            async move {
-                let reply = match RoutingMessage::clone(&*v) {
-                    RoutingMessage::Announce(uuid) => {
-                        nodes.read().await.knows(&uuid).not().then_some(RoutingMessage::RequestKey(uuid))
-                    }
-                    RoutingMessage::Ping(to, from) => to.eq(&me.id()).then_some(RoutingMessage::Pong(from, to)),
-                    RoutingMessage::Pong(to, from) if to == me.id() => {
-                        nodes.write().await.pong(from);
-                        None
-                    }
-                    RoutingMessage::RequestKey(uuid) => {
-                        if uuid == me.id() {
-                            Some(RoutingMessage::ProvideKey(me.id(), me.key().as_bytes().to_vec()))
-                        } else { nodes.read().await.key(&uuid).map(|key| RoutingMessage::ProvideKey(uuid, key.as_bytes().to_vec())) }
-                    }
-                    RoutingMessage::ProvideKey(uuid, key) => {
-                        if let Ok(key) = VerifyingKey::from_bytes(key.as_slice().try_into().unwrap()) {
-                            nodes.write().await.announced(uuid, key);
-                        }
-                        None
-                    }
-                    RoutingMessage::RequestRelayCapability(uuid) if nodes.read().await.can_relay(&uuid) => {
-                        Some(RoutingMessage::ProvideRelayCapability(me.id(), uuid, true))
-                    }
-                    RoutingMessage::ProvideRelayCapability(from, to, status) if status => {
-                        nodes.write().await.relayed(from, to);
-                        None
-                    }
-                    RoutingMessage::Relay(uuid, msg) if uuid == me.id() => {
-                        emit(msg.clone());
-                        None
-                    }
-                    RoutingMessage::RelayFailure(uuid, msg) if uuid == me.id() => {
-                        error!("Relay failed: {msg}");
-                        None
-                    }
-                    _ => None,
-                };
+    /// Serializes a message, checking it fits within the transport's frame limit.
+    fn frame(&self, m: &FLESHMessage) -> Result<Vec<u8>, SendError> {
+        let data = m.serialize()?;
+        match self.transport.max_frame() {
+            Some(max) if data.len() > max => Err(SendError::TooLarge { size: data.len(), max }),
+            _ => Ok(data),
+        }
+    }
 
-                // If a response or new request needs to be sent, serialize and send it.
-                if let Some(msg) = reply {
-                    transport.send(&FLESHMessage::new(msg.status()).serialize().unwrap()).await.unwrap();
-                }
+    /// Sends a frame towards a neighbour, over the link we last heard them on if we know it.
+    async fn transmit(&self, to: Uuid, data: &[u8]) -> io::Result<()> {
+        let link = self.links.read().await.get(&to).and_then(|link| link.id());
+        self.put(link, data).await
+    }
+
+    /// Puts a frame on the air, over `link` if given. Everything we send passes through here,
+    /// so it's where passive networks are kept quiet.
+    async fn put(&self, link: Option<TransportId>, data: &[u8]) -> io::Result<()> {
+        if self.config.passive {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Passive networks don't transmit"));
+        }
+
+        self.taps.see(Direction::Outbound, data);
+
+        match link {
+            Some(link) => self.transport.send_via(link, data).await,
+            None => self.transport.send(data).await,
+        }
+    }
+
+    /// Tags an unsigned, targeted message with the next `seq` for its target when sequencing is enabled.
+    /// Signed messages are left alone, as adding a header would invalidate their signature.
+    async fn sequence(&self, m: FLESHMessage) -> FLESHMessage {
+        let (true, Some(target), None) = (self.config.sequence, m.target, &m.signature) else {
+            return m;
+        };
+
+        let seq = {
+            let mut sequences = self.sequences.lock().await;
+            let seq = sequences.sent.entry(target).or_default();
+            *seq = seq.wrapping_add(1);
+            *seq
+        };
+
+        let m = if m.sender.is_none() { m.with_sender(self.id) } else { m };
+        m.with_header("seq", seq.to_le_bytes().to_vec())
+    }
+
+    /// Broadcasts a message that everyone who hears it acknowledges, returning the nodes that did
+    /// within `window`. Only reaches nodes in range, as broadcasts aren't relayed.
+    pub async fn broadcast_collect(
+        &self,
+        status: Status,
+        body: impl Into<Vec<u8>>,
+        window: Duration,
+    ) -> Result<Vec<Uuid>, SendError> {
+        let id = Uuid::new_v4();
+        let (tx, mut acks) = mpsc::unbounded_channel();
+        self.collections.lock().await.insert(id, tx);
+
+        let m = FLESHMessage::new(status).with_sender(self.id).with_header("collect", id).with_body(body);
+        if let Err(e) = self.send(m).await {
+            self.collections.lock().await.remove(&id);
+            return Err(e);
+        }
+
+        let mut responders = BTreeSet::new();
+        let _ = timeout(window, async {
+            while let Some(from) = acks.recv().await {
+                responders.insert(from);
             }
         })
         .await;
+
+        self.collections.lock().await.remove(&id);
+        Ok(responders.into_iter().collect())
     }
 
-    /// Periodically broadcasts a request for its own ID to the network,
-    /// serving as a discovery and presence mechanism.
-    async fn periodic_announcements(my_id: Uuid, transport: T) {
-        loop {
-            tokio::time::sleep(Duration::from_secs(ANNOUNCE_DURATION_SECS)).await;
-            let announce_msg = RoutingMessage::Announce(my_id);
-            let _ = transport.send(&announce_msg.to_message().unwrap().serialize().unwrap()).await;
+    /// Serializes a value and broadcasts it to every node, optionally echoing it onto our own
+    /// receive stream so local consumers see what we sent without special-casing it.
+    /// The message carries our id as its sender, so copies that find their way back to us are dropped.
+    pub async fn broadcast<V: Serialize>(&self, status: Status, value: &V, local_echo: bool) -> anyhow::Result<()> {
+        let m = FLESHMessage::new(status).with_sender(self.id).with_body(postcard::to_allocvec(value)?);
+        self.send(m.clone()).await?;
+
+        if local_echo {
+            self.target.emit(m);
         }
+
+        Ok(())
     }
 
-    /// Handles routing with or without a specified target via m.target
-    pub async fn send(&self, m: FLESHMessage) -> anyhow::Result<()> {
-        match m.target {
-            None => self.transport.send(&m.serialize()?).await?,
-            Some(id) => {
-                let target = self.nodes.read().await.get(&id).ok_or(anyhow!("Unknown node {id}"))?;
-
-                match target.0 {
-                    NodeRelation::Local => self.transport.send(&m.serialize()?).await?,
-                    NodeRelation::Relay { via } => {
-                        let m = RoutingMessage::Relay(id, m).to_message()?.with_target(via);
-                        self.transport.send(&m.serialize()?).await?
-                    }
-                }
-            }
+    /// Sends a message split into parts of at most `chunk` serialized bytes, for payloads too large
+    /// to fit in a single frame. The message should already be signed/encrypted as needed, as the
+    /// receiver reassembles it byte for byte before handling it like any other message.
+    /// Each part sent is reported as `NetworkEvent::SendProgress`.
+    pub async fn send_with_splitting(&self, m: FLESHMessage, chunk: usize) -> anyhow::Result<()> {
+        self.send_with_splitting_cancellable(m, chunk, CancellationToken::new()).await
+    }
+
+    /// Like `send_with_splitting`, but stops emitting parts as soon as `cancel` is triggered,
+    /// freeing up the medium rather than finishing a transfer nobody wants anymore.
+    pub async fn send_with_splitting_cancellable(
+        &self,
+        m: FLESHMessage,
+        chunk: usize,
+        cancel: CancellationToken,
+    ) -> anyhow::Result<()> {
+        let m = self.prepare(m).await?;
+        if m.serialize()?.len() <= chunk {
+            return Ok(self.dispatch(m, self.config.max_hops).await?);
         }
 
-        Ok(())
+        Ok(self.send_parts(m, chunk, self.config.max_hops, cancel).await?)
     }
 }
 
@@ -203,23 +2022,353 @@ impl<T: PacketTransport> Deref for Network<T> {
     fn deref(&self) -> &Self::Target { &self.target }
 }
 
+/// Everything needed to resume a `Network` elsewhere, see `Network::export_state`.
+/// Holds our signing key, so should be stored as carefully as any other secret.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NetworkState {
+    pub id: Uuid,
+    pub key: [u8; 32],
+    pub nodes: HashMap<Uuid, [u8; 32]>,
+}
+
+impl NetworkState {
+    /// A fresh identity with the given id and a newly generated key, knowing no one.
+    pub fn new(id: Uuid) -> Self { Self { id, key: SigningKey::generate(&mut OsRng).to_bytes(), nodes: Default::default() } }
+}
+
+/// How much confirmation a message is sent with, see `Network::send_with_ack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AckMode {
+    /// Sent once, and the recipient doesn't answer
+    #[default]
+    None,
+    /// The recipient acknowledges it, and we wait to hear that they did
+    Ack,
+    /// As `Ack`, resending it until they do, see `Network::send_reliable`
+    AckAndRetry,
+}
+
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("Unknown node {0}")]
+    UnknownNode(Uuid),
+    #[error("No route to node {0}")]
+    NoRoute(Uuid),
+    #[error("Transport error: {0}")]
+    Transport(#[from] std::io::Error),
+    #[error("Message of {size} bytes exceeds the transport's limit of {max} bytes")]
+    TooLarge { size: usize, max: usize },
+    #[error("Timed out")]
+    Timeout,
+    #[error("Transfer cancelled after {sent} of {total} parts")]
+    Cancelled { sent: usize, total: usize },
+    #[error("Dropped by middleware")]
+    Dropped,
+    #[error("No node has claimed the alias '{0}'")]
+    UnknownAlias(String),
+    #[error("Node {0} couldn't verify the message's signature")]
+    Unverified(Uuid),
+    #[error("Node {0} couldn't decrypt the message")]
+    Undecryptable(Uuid),
+    #[error("Node {by} rejected the message with {status:?}")]
+    Rejected { by: Uuid, status: Status },
+    #[error("Signed messages need a receipt id before signing to be sent reliably, see `FLESHMessage::with_receipt`")]
+    NeedsReceipt,
+    #[error("Relay is too busy to pass the message on")]
+    Busy,
+    #[error("Relay doesn't forward other nodes' messages")]
+    NotForwarding,
+    #[error("Ran out of hops before reaching node {0}")]
+    HopLimit(Uuid),
+    #[error("Send {0} was cancelled before its receipt arrived")]
+    Withdrawn(Uuid),
+    #[error("Not in group {0}")]
+    UnknownGroup(Uuid),
+    #[error("Only group {0}'s admin can change who's in it")]
+    NotGroupAdmin(Uuid),
+    #[error(transparent)]
+    Message(#[from] MessageError),
+}
+
+#[derive(Debug, Clone)]
+pub enum NetworkEvent {
+    /// A peer's sequence numbers skipped, so the messages numbered `missing` never arrived
+    Gap { from: Uuid, missing: Range<u32> },
+    /// Another part of a split message has been sent, see `Network::send_with_splitting`
+    SendProgress { id: Uuid, sent: usize, total: usize },
+    /// A relay couldn't pass on one of our messages, tagged with the message's correlation id if it had one
+    RelayFailed { reason: String, correlation: Option<String> },
+    /// A node answered one of our pings
+    Pong { from: Uuid },
+    /// A node announced it was leaving the network, or was dropped with `Network::forget`
+    Left { id: Uuid },
+    /// A message was left out of `Network::messages`, as it couldn't be verified, decrypted or decoded
+    Unreadable { from: Option<Uuid>, reason: String },
+    /// The transport is receiving again after failing, as one does once it's reconnected
+    Reconnected,
+}
+
+/// The keys and routes a network knows, and its resolutions in flight, see `Network::share_resolution`.
+/// Every network holding one sees what any of them learns, and resolutions started on one are
+/// answered by a key heard on any other.
+#[derive(Clone, Default)]
+pub struct SharedResolution {
+    nodes: Arc<RwLock<NodeRelationshipMap>>,
+    pending: PendingResolutions,
+    peers_changed: Arc<Notify>,
+}
+
+/// When each node was last announced by any of the networks holding it, see
+/// `NetworkBuilder::share_announcements`.
+#[derive(Clone, Default)]
+pub struct SharedAnnouncements(Arc<Mutex<HashMap<Uuid, Instant>>>);
+
+impl SharedAnnouncements {
+    /// Records that `id` is being announced, unless it already was within `within`, returning which.
+    async fn claim(&self, id: Uuid, within: Duration) -> bool {
+        let mut last = self.0.lock().await;
+        if last.get(&id).is_some_and(|at| at.elapsed() < within) {
+            return false;
+        }
+
+        last.insert(id, Instant::now());
+        true
+    }
+}
+
+/// How far along we are with reaching a node, see `Network::resolution_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolutionState {
+    /// We've its key and a route to it, last confirmed `age` ago
+    Resolved { age: Duration },
+    /// We've asked the network for its key and are waiting to hear back
+    Pending,
+    /// We've its key, but haven't heard from it (or anyone relaying to it) recently enough to trust the route
+    Stale,
+    /// We've never heard of it
+    Unknown,
+}
+
+/// What a node handles, as it told us when we first exchanged keys, see `Network::negotiated`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerParams {
+    /// Wire format version it speaks, see `encoding::WIRE_VERSION`
+    pub version: u16,
+    /// Largest frame it'll accept, see `NetworkBuilder::max_inbound_frame`
+    pub max_frame: Option<u32>,
+    /// What it can do, as set with `Network::set_capabilities`
+    pub features: Vec<String>,
+}
+
+/// A reliable send still waiting on its receipt, see `Network::inflight`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InflightSend {
+    /// The message's receipt id, for `Network::cancel`
+    pub id: Uuid,
+    pub target: Uuid,
+    /// Times it's been sent so far
+    pub attempts: u32,
+}
+
+/// A node that answered a `Network::scan`.
+#[derive(Debug, Clone)]
+pub struct NodeSnapshot {
+    pub id: Uuid,
+    /// Time from sending the ping to hearing back
+    pub rtt: Duration,
+    /// Signal strength of the reply, where the transport reports it
+    pub rssi: Option<i16>,
+    /// How we reach the node, if we know its key
+    pub relation: Option<NodeRelation>,
+}
+
+/// A caller's place among those waiting on a resolution, given up once they stop waiting, whether
+/// that's by timing out or by being dropped part way.
+struct Waiting<T: PacketTransport + Clone + 'static> {
+    network: Network<T>,
+    id: Uuid,
+    priority: Priority,
+    /// Whether theirs was the request the others are waiting on
+    requested: bool,
+    answered: bool,
+}
+
+impl<T: PacketTransport + Clone + 'static> Drop for Waiting<T> {
+    fn drop(&mut self) {
+        if self.answered {
+            return;
+        }
+
+        // Dropping can't wait on the lock, so it's left to a task, which runs once the caller's end is closed
+        let (network, id, priority, requested) = (self.network.clone(), self.id, self.priority, self.requested);
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move { network.stop_waiting(id, priority, requested).await });
+        }
+    }
+}
+
+/// Spacing between announcements, doubling while the nodes we see stay the same and
+/// dropping back to the minimum as soon as they change.
+#[derive(Debug)]
+struct AnnounceSchedule {
+    interval: Duration,
+    bounds: Range<Duration>,
+    seen: BTreeSet<Uuid>,
+}
+
+impl AnnounceSchedule {
+    fn new(bounds: Range<Duration>) -> Self {
+        let interval = Duration::from_secs(ANNOUNCE_DURATION_SECS).clamp(bounds.start, bounds.end.max(bounds.start));
+        Self { interval, bounds, seen: BTreeSet::new() }
+    }
+
+    /// Updates the interval given the nodes now visible, returning it.
+    fn next(&mut self, nodes: BTreeSet<Uuid>) -> Duration {
+        self.interval = match nodes == self.seen {
+            true => (self.interval * 2).min(self.bounds.end).max(self.bounds.start),
+            false => self.bounds.start,
+        };
+
+        self.seen = nodes;
+        self.interval
+    }
+}
+
+/// Claimed aliases, with the node holding each and whether their claim was verified.
+type Aliases = HashMap<String, (Uuid, bool)>;
+
+/// What we know of the link a neighbour can be reached on, see `Network::heard_on`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Link {
+    /// Named by unsigned messages, which anyone could have sent
+    Claimed(TransportId),
+    /// Heard in a message signed with the neighbour's key
+    Verified(TransportId),
+    /// Unsigned messages disagreed, so replies go out on every link until a signed one settles it
+    Contested,
+}
+
+impl Link {
+    fn id(self) -> Option<TransportId> {
+        match self {
+            Self::Claimed(id) | Self::Verified(id) => Some(id),
+            Self::Contested => None,
+        }
+    }
+}
+
+/// What a node signs to claim an alias.
+fn alias_claim(id: Uuid, alias: &str) -> Vec<u8> { [id.as_bytes(), alias.as_bytes()].concat() }
+
+/// What a node signs to say it's leaving, see `Network::shutdown`.
+fn leave_claim(id: Uuid) -> Vec<u8> { [b"leave".as_slice(), id.as_bytes()].concat() }
+
+/// Fingerprints of messages we've relayed recently, with the hops each had left, for spotting loops.
+#[derive(Debug, Default)]
+struct RecentRelays(HashMap<u64, (u8, Instant)>);
+
+impl RecentRelays {
+    /// Records a message we're about to relay, `false` if it's been round a loop and back to us.
+    /// Arriving again with as many hops left is a retransmission rather than a loop, so is let through.
+    fn first_pass(&mut self, m: &FLESHMessage, hops: u8) -> bool {
+        self.0.retain(|_, (_, at)| at.elapsed() < Duration::from_secs(RELAY_MEMORY_SECS));
+
+        let mut hasher = DefaultHasher::new();
+        m.serialize().unwrap_or_default().hash(&mut hasher);
+        let fingerprint = hasher.finish();
+
+        if self.0.get(&fingerprint).is_some_and(|(seen, _)| *seen > hops) {
+            return false;
+        }
+
+        self.0.insert(fingerprint, (hops, Instant::now()));
+        true
+    }
+}
+
+/// Messages held for nodes that have gone quiet, oldest first, see `NetworkBuilder::mailbox`.
+#[derive(Debug)]
+struct Mailbox {
+    limits: Option<(usize, Duration)>,
+    held: VecDeque<(Uuid, u8, FLESHMessage, Instant)>,
+}
+
+impl Mailbox {
+    fn new(limits: Option<(usize, Duration)>) -> Self { Self { limits, held: VecDeque::new() } }
+
+    fn len(&self) -> usize { self.held.len() }
+
+    /// Drops messages that have been held too long.
+    fn expire(&mut self) {
+        if let Some((_, ttl)) = self.limits {
+            self.held.retain(|(.., at)| at.elapsed() < ttl);
+        }
+    }
+
+    /// Holds a message for `id`, making room by dropping the oldest if full. `false` if there's
+    /// no mailbox to hold it in.
+    fn hold(&mut self, id: Uuid, hops: u8, m: FLESHMessage) -> bool {
+        let Some((capacity, _)) = self.limits.filter(|(capacity, _)| *capacity > 0) else {
+            return false;
+        };
+
+        self.expire();
+        if self.held.len() >= capacity {
+            self.held.pop_front();
+        }
+
+        self.held.push_back((id, hops, m, Instant::now()));
+        true
+    }
+
+    /// Everything still held for `id`, with the hops each had left.
+    fn take(&mut self, id: Uuid) -> Vec<(u8, FLESHMessage)> {
+        self.expire();
+        let (theirs, rest) = self.held.drain(..).partition::<Vec<_>, _>(|(to, ..)| *to == id);
+        self.held = rest.into();
+        theirs.into_iter().map(|(_, hops, m, _)| (hops, m)).collect()
+    }
+}
+
+/// Last `seq` sent to, and received from, each peer.
+#[derive(Debug, Default)]
+struct Sequences {
+    sent: HashMap<Uuid, u32>,
+    received: HashMap<Uuid, u32>,
+}
+
 #[derive(Debug, Clone)]
 pub enum RoutingMessage {
     Announce(Uuid),
+    /// Node saying it's still in range, for those that already know it
+    Beacon(Uuid),
     Ping(Uuid, Uuid),
     Pong(Uuid, Uuid),
     RequestKey(Uuid),
-    ProvideKey(Uuid, Vec<u8>),
+    /// Node, its key, and the relay offering to reach it (`None` when the node answers for itself)
+    ProvideKey(Uuid, Vec<u8>, Option<Uuid>),
     RequestRelayCapability(Uuid),
     ProvideRelayCapability(Uuid, Uuid, bool),
-    Relay(Uuid, FLESHMessage),
-    RelayFailure(Uuid, String),
+    /// Target, relays it may still pass through, and the message
+    Relay(Uuid, u8, FLESHMessage),
+    /// Original sender, why their message couldn't be relayed, and its correlation id
+    RelayFailure(Uuid, String, Option<String>),
+    /// Node, the alias it's claiming, and its signature over both
+    Alias(Uuid, String, Vec<u8>),
+    /// Node, and its signature saying it's leaving
+    Leave(Uuid, Vec<u8>),
+    /// Node asking for messages held while it was away
+    PullMail(Uuid),
+    /// Node, and what it can do
+    Capabilities(Uuid, Vec<String>),
+    /// Node, who it's for (nil for anyone), what it handles, and its signature over them, sent on first exchanging keys
+    Handshake(Uuid, Uuid, PeerParams, Vec<u8>),
 }
 
 impl RoutingMessage {
     pub fn status(&self) -> Status {
         match self {
-            RoutingMessage::Announce(..) => Status::Acknowledge,
+            RoutingMessage::Announce(..) | RoutingMessage::Beacon(..) => Status::Announce,
             RoutingMessage::RequestKey(..) => Status::RequestKey,
             RoutingMessage::ProvideKey(..) => Status::ProvideKey,
             RoutingMessage::RequestRelayCapability(..) => Status::RequestRelay,
@@ -228,26 +2377,77 @@ impl RoutingMessage {
             RoutingMessage::RelayFailure(..) => Status::RelayFailure,
             RoutingMessage::Ping(..) => Status::Ping,
             RoutingMessage::Pong(..) => Status::Pong,
+            RoutingMessage::Alias(..) => Status::Alias,
+            RoutingMessage::Leave(..) => Status::Leave,
+            RoutingMessage::PullMail(..) => Status::PullMail,
+            RoutingMessage::Capabilities(..) | RoutingMessage::Handshake(..) => Status::Capabilities,
         }
     }
 }
 
 impl RoutingMessage {
-    pub fn to_message(self) -> anyhow::Result<FLESHMessage> {
+    /// The node that put this message on the air, where the message says so.
+    pub fn origin(&self) -> Option<Uuid> {
+        match self {
+            RoutingMessage::Announce(uuid)
+            | RoutingMessage::Beacon(uuid)
+            | RoutingMessage::Alias(uuid, ..)
+            | RoutingMessage::Leave(uuid, _)
+            | RoutingMessage::PullMail(uuid)
+            | RoutingMessage::Capabilities(uuid, _)
+            | RoutingMessage::Handshake(uuid, ..) => Some(*uuid),
+            RoutingMessage::Ping(_, from) | RoutingMessage::Pong(_, from) => Some(*from),
+            RoutingMessage::ProvideKey(uuid, _, via) => Some(via.unwrap_or(*uuid)),
+            RoutingMessage::ProvideRelayCapability(from, ..) => Some(*from),
+            _ => None,
+        }
+    }
+
+    pub fn to_message(self) -> Result<FLESHMessage, MessageError> {
         let message = FLESHMessage::new(self.status());
 
         Ok(match self {
             RoutingMessage::Announce(uuid) => message.with_header("self", uuid),
+            // Shares a status with announcements, told apart by its header, so older nodes ignore it
+            RoutingMessage::Beacon(uuid) => message.with_header("beacon", uuid),
             RoutingMessage::RequestKey(uuid) => message.with_header("for", uuid),
-            RoutingMessage::ProvideKey(uuid, key) => message.with_header("for", uuid).with_header("key", key),
+            RoutingMessage::ProvideKey(uuid, key, via) => {
+                let message = message.with_header("for", uuid).with_header("key", key);
+                match via {
+                    Some(via) => message.with_header("via", via),
+                    None => message,
+                }
+            }
             RoutingMessage::RequestRelayCapability(uuid) => message.with_header("for", uuid),
             RoutingMessage::ProvideRelayCapability(from, to, status) => {
                 message.with_header("from", from).with_header("to", to).with_header("status", status.to_string())
             }
-            RoutingMessage::Relay(uuid, msg) => message.with_header("for", uuid).with_body(msg.serialize()?),
-            RoutingMessage::RelayFailure(uuid, reason) => message.with_header("for", uuid).with_body(reason),
+            RoutingMessage::Relay(uuid, hops, msg) => {
+                message.with_header("for", uuid).with_header("hops", [hops]).with_body(msg.serialize()?)
+            }
+            RoutingMessage::RelayFailure(uuid, reason, correlation) => {
+                let message = message.with_header("for", uuid).with_body(reason);
+                match correlation {
+                    Some(id) => message.with_correlation_id(&id),
+                    None => message,
+                }
+            }
             RoutingMessage::Ping(to, from) => message.with_header("to", to).with_header("from", from),
             RoutingMessage::Pong(to, from) => message.with_header("to", to).with_header("from", from),
+            RoutingMessage::Alias(uuid, alias, signature) => {
+                message.with_header("self", uuid).with_header("signature", signature).with_body(alias)
+            }
+            RoutingMessage::Leave(uuid, signature) => message.with_header("self", uuid).with_header("signature", signature),
+            RoutingMessage::PullMail(uuid) => message.with_header("self", uuid),
+            RoutingMessage::Capabilities(uuid, capabilities) => {
+                message.with_header("self", uuid).with_body(postcard::to_allocvec(&capabilities).map_err(MessageError::SerializationError)?)
+            }
+            // Shares a status with plain capability adverts, told apart by the signature
+            RoutingMessage::Handshake(from, to, params, signature) => message
+                .with_header("self", from)
+                .with_header("to", to)
+                .with_header("signature", signature)
+                .with_body(postcard::to_allocvec(&params).map_err(MessageError::SerializationError)?),
         })
     }
 
@@ -259,23 +2459,51 @@ impl RoutingMessage {
         fn string(m: &FLESHMessage) -> anyhow::Result<String> { Ok(String::from_utf8(m.body.to_vec())?) }
 
         Ok(Some(match m.status {
-            Status::Announce => Self::Announce(uuid(m, "for")?),
+            Status::Announce => match m.headers.contains_key("beacon") {
+                true => Self::Beacon(uuid(m, "beacon")?),
+                false => Self::Announce(uuid(m, "self")?),
+            },
             Status::RequestKey => Self::RequestKey(uuid(m, "for")?),
-            Status::ProvideKey => Self::ProvideKey(uuid(m, "for")?, m.body.clone()),
-            Status::RequestRelay => Self::RequestKey(uuid(m, "for")?),
+            Status::ProvideKey => {
+                Self::ProvideKey(
+                    uuid(m, "for")?,
+                    m.headers.get("key").ok_or(anyhow!("Missing 'key' header"))?.clone(),
+                    m.headers.contains_key("via").then(|| uuid(m, "via")).transpose()?,
+                )
+            }
+            Status::RequestRelay => Self::RequestRelayCapability(uuid(m, "for")?),
             Status::ProvideRelay => Self::ProvideRelayCapability(
                 uuid(m, "from")?,
                 uuid(m, "to")?,
                 String::from_utf8(m.headers.get("status").ok_or(anyhow!("Missing 'status' header"))?.to_vec())?
                     .parse::<bool>()?,
             ),
-            Status::Relay => Self::Relay(uuid(m, "for")?, FLESHMessage::deserialize(&m.body)?),
-            Status::RelayFailure => Self::RelayFailure(uuid(m, "for")?, string(m)?),
+            Status::Relay => Self::Relay(
+                uuid(m, "for")?,
+                u8::from_le_bytes(m.headers.get("hops").ok_or(anyhow!("Missing 'hops' header"))?.as_slice().try_into()?),
+                FLESHMessage::deserialize(&m.body)?,
+            ),
+            Status::RelayFailure => Self::RelayFailure(uuid(m, "for")?, string(m)?, m.correlation_id().map(Into::into)),
             Status::Ping => Self::Ping(uuid(m, "to")?, uuid(m, "from")?),
             Status::Pong => {
                 // TODO: Validate this is coming from who we think it is?
-                Self::Ping(uuid(m, "to")?, uuid(m, "from")?)
+                Self::Pong(uuid(m, "to")?, uuid(m, "from")?)
             }
+            Status::Alias => Self::Alias(
+                uuid(m, "self")?,
+                string(m)?,
+                m.headers.get("signature").ok_or(anyhow!("Missing 'signature' header"))?.clone(),
+            ),
+            Status::Leave => {
+                Self::Leave(uuid(m, "self")?, m.headers.get("signature").ok_or(anyhow!("Missing 'signature' header"))?.clone())
+            }
+            Status::PullMail => Self::PullMail(uuid(m, "self")?),
+            Status::Capabilities => match m.headers.get("signature") {
+                Some(signature) => {
+                    Self::Handshake(uuid(m, "self")?, uuid(m, "to")?, postcard::from_bytes(&m.body)?, signature.clone())
+                }
+                None => Self::Capabilities(uuid(m, "self")?, postcard::from_bytes(&m.body)?),
+            },
             _ => return Ok(None),
         }))
     }
@@ -292,67 +2520,125 @@ pub enum NodeRelation {
     Relay { via: Uuid },
 }
 
+/// Last time a node was confirmed reachable, `None` if it never has been.
+type LastSeen = Option<Instant>;
+
 #[derive(Clone, Debug, Default)]
-pub struct NodeRelationshipMap(HashMap<Uuid, (Instant, NodeRelation, VerifyingKey)>);
+pub struct NodeRelationshipMap(
+    HashMap<Uuid, (LastSeen, NodeRelation, VerifyingKey)>,
+    /// Nodes whose keys we were given rather than learned, see `Network::preload_keys`
+    HashSet<Uuid>,
+);
 impl NodeRelationshipMap {
+    fn fresh(seen: &LastSeen) -> bool {
+        seen.is_some_and(|seen| seen.elapsed() < Duration::from_secs(RESOLUTION_TTL_SECS))
+    }
+
     pub fn pong(&mut self, id: Uuid) {
         if let Some(existing) = self.0.get(&id) {
-            self.0.insert(id, (Instant::now(), NodeRelation::Local, existing.2));
+            self.0.insert(id, (Some(Instant::now()), NodeRelation::Local, existing.2));
         }
     }
 
     pub fn announced(&mut self, id: Uuid, key: VerifyingKey) {
         if let Some(existing) = self.0.get(&id) {
+            if !self.accepts(&id, &key) {
+                warn!("Keeping the key pinned for {id} over another announced for it");
+                return;
+            }
+
             if existing.2 != key {
                 warn!("Mismatching keys announced for {id}");
             }
 
-            self.0.insert(id, (Instant::now(), existing.1.clone(), key));
+            self.0.insert(id, (existing.0, existing.1.clone(), key));
         } else {
-            self.0.insert(
-                id,
-                (
-                    // We shouldnt assume we can reach this node unless we know otherwise, so we always disallow it by TTL
-                    Instant::now().checked_sub(Duration::from_secs(RESOLUTION_TTL_SECS)).unwrap(),
-                    NodeRelation::Local,
-                    key,
-                ),
-            );
+            // We shouldnt assume we can reach this node unless we know otherwise, so it starts out unseen
+            self.0.insert(id, (None, NodeRelation::Local, key));
         }
     }
 
+    /// Trusts `key` for the node from now on, taking it to be in range.
+    pub fn pin(&mut self, id: Uuid, key: VerifyingKey) {
+        self.0.insert(id, (Some(Instant::now()), NodeRelation::Local, key));
+        self.1.insert(id);
+    }
+
+    /// Stops trusting the key pinned for the node, so others can be taken for it. The key itself is kept
+    /// until the node leaves or is replaced.
+    pub fn unpin(&mut self, id: Uuid) { self.1.remove(&id); }
+
+    /// Whether `key` can be taken for the node, which it can't if another was pinned for it.
+    pub fn accepts(&self, id: &Uuid, key: &VerifyingKey) -> bool {
+        !self.1.contains(id) || self.0.get(id).is_some_and(|v| v.2 == *key)
+    }
+
+    /// Records that `via` can relay to the node, unless we've already got a working route to them.
     pub fn relayed(&mut self, id: Uuid, via: Uuid) {
         if let Some(existing) = self.0.get(&id) {
-            let relation = match existing.1 {
-                NodeRelation::Local => {
-                    trace!("Not downgrading local relationship to relay (for {id})");
-                    NodeRelation::Local
-                }
-                _ => NodeRelation::Relay { via },
-            };
+            if Self::fresh(&existing.0) {
+                trace!("Keeping existing {:?} route to {id} over relay {via}", existing.1);
+                return;
+            }
 
-            self.0.insert(id, (Instant::now(), relation, existing.2));
+            self.0.insert(id, (Some(Instant::now()), NodeRelation::Relay { via }, existing.2));
         } else {
             warn!("Relay found, but unknown node '{id}' to relay to.");
         }
     }
 
-    pub fn knows(&self, id: &Uuid) -> bool {
-        self.0.get(id).map(|v| v.0.elapsed() < Duration::from_secs(RESOLUTION_TTL_SECS)).unwrap_or_default()
+    /// Forgets a node that's left, along with any routes that went through it. A pinned key is kept,
+    /// as leaving doesn't make another key any more trustworthy, see `unpin`.
+    pub fn left(&mut self, id: Uuid) {
+        match self.1.contains(&id) {
+            true => {
+                if let Some(entry) = self.0.get_mut(&id) {
+                    (entry.0, entry.1) = (None, NodeRelation::Local);
+                }
+            }
+            false => {
+                self.0.remove(&id);
+            }
+        }
+
+        for (seen, relation, _) in self.0.values_mut() {
+            if *relation == (NodeRelation::Relay { via: id }) {
+                *seen = None;
+            }
+        }
     }
 
+    /// Nodes confirmed reachable recently.
+    pub fn live(&self) -> BTreeSet<Uuid> { self.0.iter().filter(|(_, v)| Self::fresh(&v.0)).map(|(id, _)| *id).collect() }
+
+    /// Every node we hold a key for, reachable or not.
+    pub fn keys(&self) -> impl Iterator<Item = (Uuid, VerifyingKey)> + '_ { self.0.iter().map(|(id, v)| (*id, v.2)) }
+
+    /// When the node was last confirmed reachable, `None` if we've never heard of it.
+    pub fn seen(&self, id: &Uuid) -> Option<LastSeen> { self.0.get(id).map(|v| v.0) }
+
+    /// Whether we've heard of the node at all, reachable or not.
+    pub fn contains(&self, id: &Uuid) -> bool { self.0.contains_key(id) }
+
+    pub fn knows(&self, id: &Uuid) -> bool { self.0.get(id).map(|v| Self::fresh(&v.0)).unwrap_or_default() }
+
+    /// The node's key, if we've learned it recently or it's pinned.
     pub fn key(&self, id: &Uuid) -> Option<VerifyingKey> {
-        self.0.get(id).and_then(|v| (v.0.elapsed() < Duration::from_secs(RESOLUTION_TTL_SECS)).then_some(v.2))
+        self.0.get(id).and_then(|v| (Self::fresh(&v.0) || self.1.contains(id)).then_some(v.2))
+    }
+
+    /// Whether a node we reach directly hasn't been heard from in `within`.
+    pub fn quiet(&self, id: &Uuid, within: Duration) -> bool {
+        self.0.get(id).is_some_and(|(seen, relation, _)| {
+            *relation == NodeRelation::Local && seen.is_none_or(|seen| seen.elapsed() >= within)
+        })
     }
 
     pub fn can_relay(&self, id: &Uuid) -> bool {
-        self.0
-            .get(id)
-            .map(|v| (v.0.elapsed() < Duration::from_secs(RESOLUTION_TTL_SECS)) && v.1 == NodeRelation::Local)
-            .unwrap_or(false)
+        self.0.get(id).map(|v| Self::fresh(&v.0) && v.1 == NodeRelation::Local).unwrap_or(false)
     }
 
     pub fn get(&self, id: &Uuid) -> Option<(NodeRelation, VerifyingKey)> {
-        self.0.get(id).and_then(|v| (v.0.elapsed() < Duration::from_secs(RESOLUTION_TTL_SECS)).then_some((v.1.clone(), v.2)))
+        self.0.get(id).and_then(|v| Self::fresh(&v.0).then_some((v.1.clone(), v.2)))
     }
 }
@@ -0,0 +1,97 @@
+use {
+    rand_core::{OsRng, RngCore},
+    std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    },
+    uuid::Uuid,
+};
+
+/// How soon a queued key request should go out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Discovery of nodes nobody's asked about yet, such as ones we've just heard announce
+    Background,
+    /// A node someone is waiting to reach
+    Urgent,
+}
+
+/// Refills at `rate` tokens a second, holding at most `burst` of them.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u32, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self { rate: rate.max(1) as f64, burst, tokens: burst, last: Instant::now() }
+    }
+
+    /// Takes a token, or says how long until one is available.
+    pub fn take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(self.burst);
+        self.last = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+    }
+}
+
+/// Waits between retransmissions, doubling each time up to a cap, with up to half as long again
+/// added at random so nodes that collided once don't retry in step and collide again.
+#[derive(Debug)]
+pub struct Backoff {
+    next: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub fn new(first: Duration, max: Duration) -> Self { Self { next: first, max } }
+
+    /// How long to wait before the next attempt.
+    pub fn wait(&mut self) -> Duration {
+        let base = self.next;
+        self.next = (base * 2).min(self.max);
+
+        let jitter = (base / 2).as_millis() as u64;
+        base + Duration::from_millis(OsRng.next_u64() % (jitter + 1))
+    }
+}
+
+/// Nodes waiting on a `RequestKey`, each queued once, with urgent ones going first.
+#[derive(Debug, Default)]
+pub struct ResolveQueue {
+    urgent: VecDeque<Uuid>,
+    background: VecDeque<Uuid>,
+}
+
+impl ResolveQueue {
+    /// Queues a node, or moves it to the urgent queue if it's now wanted sooner.
+    pub fn push(&mut self, id: Uuid, priority: Priority) {
+        if self.urgent.contains(&id) {
+            return;
+        }
+
+        match priority {
+            Priority::Urgent => {
+                self.background.retain(|queued| *queued != id);
+                self.urgent.push_back(id);
+            }
+            Priority::Background if !self.background.contains(&id) => self.background.push_back(id),
+            Priority::Background => {}
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Uuid> { self.urgent.pop_front().or_else(|| self.background.pop_front()) }
+
+    pub fn is_empty(&self) -> bool { self.urgent.is_empty() && self.background.is_empty() }
+}
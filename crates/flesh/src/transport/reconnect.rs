@@ -0,0 +1,108 @@
+use {
+    crate::transport::{PacketTransport, TransportId},
+    async_trait::async_trait,
+    futures::future::BoxFuture,
+    std::{future::Future, io, sync::Arc},
+    tokio::{
+        select,
+        sync::{Mutex, watch},
+    },
+    tracing::{info, warn},
+};
+
+/// Opens a fresh connection for `Reconnecting`.
+type Connect<T> = Arc<dyn Fn() -> BoxFuture<'static, io::Result<T>> + Send + Sync>;
+
+/// Wraps a transport that can be lost, such as a LoRa module on a USB port that's unplugged and
+/// plugged back in, opening a fresh one with `connect` whenever sending or receiving over it fails.
+/// The failure is still returned, so the network retries as it would anyway and carries on over the
+/// new connection, see `Network::resilient_stream`.
+#[derive(Clone)]
+pub struct Reconnecting<T: PacketTransport> {
+    /// The connection in use, numbered by how many came before it
+    current: Arc<watch::Sender<(usize, T)>>,
+    connect: Connect<T>,
+    /// Held while reconnecting, so a send and receive failing together only reconnect once
+    reconnecting: Arc<Mutex<()>>,
+}
+
+impl<T: PacketTransport + Clone + 'static> Reconnecting<T> {
+    /// Connects with `connect`, failing if the first connection can't be made.
+    pub async fn new<F, Fut>(connect: F) -> io::Result<Self>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<T>> + Send + 'static,
+    {
+        let connect: Connect<T> = Arc::new(move || Box::pin(connect()));
+        let first = connect().await?;
+        Ok(Self { current: Arc::new(watch::Sender::new((0, first))), connect, reconnecting: Default::default() })
+    }
+
+    /// The connection in use.
+    pub fn inner(&self) -> T { self.current.borrow().1.clone() }
+
+    /// How many times the connection's been replaced since starting.
+    pub fn reconnects(&self) -> usize { self.current.borrow().0 }
+
+    fn current(&self) -> (usize, T) { self.current.borrow().clone() }
+
+    /// Replaces connection `failed` if `result` is an error, unless that's already been done. If a new one can't be
+    /// made the old one's kept, so the next send or receive fails and tries again.
+    async fn reconnect<R>(&self, failed: usize, result: io::Result<R>) -> io::Result<R> {
+        let Err(e) = &result else { return result };
+        let _reconnecting = self.reconnecting.lock().await;
+        if self.reconnects() != failed {
+            return result;
+        }
+
+        warn!("Transport failed ({e}), reconnecting");
+        match (self.connect)().await {
+            Ok(fresh) => {
+                self.current.send_replace((failed + 1, fresh));
+                info!("Transport reconnected");
+            }
+            Err(e) => warn!("Couldn't reconnect transport: {e}"),
+        }
+
+        result
+    }
+}
+
+#[async_trait]
+impl<T: PacketTransport + Clone + 'static> PacketTransport for Reconnecting<T> {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        let (generation, transport) = self.current();
+        self.reconnect(generation, transport.send(data).await).await
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { Ok(self.recv_from().await?.1) }
+
+    async fn send_via(&self, link: TransportId, data: &[u8]) -> io::Result<()> {
+        let (generation, transport) = self.current();
+        self.reconnect(generation, transport.send_via(link, data).await).await
+    }
+
+    fn max_frame(&self) -> Option<usize> { self.current.borrow().1.max_frame() }
+
+    async fn recv_from(&mut self) -> io::Result<(TransportId, Vec<u8>)> {
+        let (link, data, _) = self.recv_measured().await?;
+        Ok((link, data))
+    }
+
+    async fn recv_measured(&mut self) -> io::Result<(TransportId, Vec<u8>, Option<i16>)> {
+        let mut replaced = self.current.subscribe();
+        loop {
+            let (generation, mut transport) = self.current();
+
+            // A connection replaced after a failed send may never deliver anything again
+            select! {
+                received = transport.recv_measured() => return self.reconnect(generation, received).await,
+                _ = replaced.changed() => continue,
+            }
+        }
+    }
+
+    async fn ready(&self) -> io::Result<()> { self.inner().ready().await }
+
+    async fn flush(&self) -> io::Result<()> { self.inner().flush().await }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "crypto")]
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey, ed25519::signature::Signer};
+
+use crate::transport::encoding::MessageError;
+
+/// The scheme node identities use, and the one assumed for messages that don't name theirs.
+pub const ED25519: &str = "ed25519";
+
+/// Signs messages, see `FLESHMessage::sign_with`. Implemented for ed25519 keys (with `crypto`), and open
+/// to other schemes for talking to systems that don't use it.
+pub trait MessageSigner {
+    /// Name of the scheme, carried in the `scheme` header of messages signed with anything but `ED25519`
+    fn scheme(&self) -> &str;
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, MessageError>;
+}
+
+/// Checks signatures made by the matching `MessageSigner`, see `FLESHMessage::verify_with`.
+pub trait MessageVerifier {
+    fn scheme(&self) -> &str;
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> Result<(), MessageError>;
+}
+
+#[cfg(feature = "crypto")]
+impl MessageSigner for SigningKey {
+    fn scheme(&self) -> &str { ED25519 }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, MessageError> {
+        Ok(self.try_sign(data).map_err(|_| MessageError::InvalidSignature)?.to_bytes().to_vec())
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl MessageVerifier for VerifyingKey {
+    fn scheme(&self) -> &str { ED25519 }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> Result<(), MessageError> {
+        let signature = Signature::from_bytes(signature.try_into().map_err(|_| MessageError::InvalidSignature)?);
+        self.verify_strict(data, &signature).map_err(|_| MessageError::InvalidSignature)
+    }
+}
@@ -0,0 +1,52 @@
+use {
+    std::fmt::{self, Display},
+    uuid::Uuid,
+};
+
+pub const SHORT_ID_CHARS: usize = 6;
+
+const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// A node id shortened for people to read: the start of its base32 form, lengthened where needed to
+/// tell it apart from the other ids it's shown alongside. Anything addressing nodes keeps the full `Uuid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShortId {
+    id: Uuid,
+    len: usize,
+}
+
+impl ShortId {
+    /// Shortens `id`, keeping it distinct from every id in `known`.
+    pub fn new(id: Uuid, known: impl IntoIterator<Item = Uuid>) -> Self {
+        let full = base32(id.as_bytes());
+        let len = known
+            .into_iter()
+            .filter(|other| *other != id)
+            .map(|other| shared_prefix(&full, &base32(other.as_bytes())) + 1)
+            .fold(SHORT_ID_CHARS, usize::max)
+            .min(full.len());
+
+        Self { id, len }
+    }
+
+    /// The full id this stands for.
+    pub fn id(&self) -> Uuid { self.id }
+}
+
+impl Display for ShortId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&base32(self.id.as_bytes())[..self.len]) }
+}
+
+/// RFC 4648 base32, lowercase and unpadded.
+fn base32(data: &[u8]) -> String {
+    let bits = data.len() * 8;
+    (0..bits.div_ceil(5))
+        .map(|i| {
+            let (byte, shift) = (i * 5 / 8, i * 5 % 8);
+            let window = (data[byte] as u16) << 8 | data.get(byte + 1).copied().unwrap_or_default() as u16;
+            ALPHABET[(window >> (11 - shift) & 0x1F) as usize] as char
+        })
+        .collect()
+}
+
+fn shared_prefix(a: &str, b: &str) -> usize { a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count() }
@@ -0,0 +1,128 @@
+#[cfg(feature = "crypto")]
+use {
+    argon2::{Algorithm, Argon2, Params, Version},
+    chacha20poly1305::{
+        ChaCha20Poly1305, Key, Nonce,
+        aead::{Aead, KeyInit},
+    },
+    rand_core::{OsRng, RngCore},
+};
+use {
+    crate::transport::network::NetworkState,
+    std::{fs, io, path::Path},
+    thiserror::Error,
+};
+
+const PLAIN: u8 = 0;
+const SEALED: u8 = 1;
+#[cfg(feature = "crypto")]
+const SALT_LEN: usize = 16;
+#[cfg(feature = "crypto")]
+const NONCE_LEN: usize = 12;
+/// Argon2 memory, passes and lanes, each a little-endian `u32`
+#[cfg(feature = "crypto")]
+const PARAMS_LEN: usize = 12;
+/// Most memory (in KiB) saved state may ask to be unlocked with, so a tampered file can't exhaust it
+#[cfg(feature = "crypto")]
+const MAX_KDF_MEMORY_KIB: u32 = 1 << 20;
+/// Most passes over that memory, so a tampered file can't keep unlocking it going forever either
+#[cfg(feature = "crypto")]
+const MAX_KDF_PASSES: u32 = 16;
+/// Most lanes the memory may be split into
+#[cfg(feature = "crypto")]
+const MAX_KDF_LANES: u32 = 16;
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("Couldn't access saved state: {0}")]
+    Io(#[from] io::Error),
+    #[error("Saved state is malformed: {0}")]
+    Encoding(#[from] postcard::Error),
+    #[error("Saved state is encrypted, but no passphrase was given")]
+    PassphraseRequired,
+    #[error("Wrong passphrase, or the saved state was tampered with")]
+    WrongPassphrase,
+    #[error("Encrypting saved state needs the `crypto` feature")]
+    EncryptionUnavailable,
+    #[error("Saved state is in an unknown format ({0})")]
+    UnknownFormat(u8),
+}
+
+impl NetworkState {
+    /// Writes the state to `path`, encrypted with `passphrase` if one is given so that a copy of
+    /// the file alone doesn't give away our signing key.
+    pub fn save(&self, path: impl AsRef<Path>, passphrase: Option<&str>) -> Result<(), StateError> {
+        let plain = postcard::to_allocvec(self)?;
+        let data = match passphrase {
+            None => [&[PLAIN][..], &plain].concat(),
+            Some(passphrase) => [&[SEALED][..], &seal(&plain, passphrase)?].concat(),
+        };
+
+        Ok(fs::write(path, data)?)
+    }
+
+    /// Reads back state written by `save`. `passphrase` is only needed if it was saved with one.
+    pub fn load(path: impl AsRef<Path>, passphrase: Option<&str>) -> Result<Self, StateError> {
+        let data = fs::read(path)?;
+        let (format, rest) = data.split_first().ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        let plain = match *format {
+            PLAIN => rest.to_vec(),
+            SEALED => unseal(rest, passphrase.ok_or(StateError::PassphraseRequired)?)?,
+            other => return Err(StateError::UnknownFormat(other)),
+        };
+
+        Ok(postcard::from_bytes(&plain)?)
+    }
+}
+
+/// Encrypts saved state, prefixed with the salt, key derivation parameters and nonce needed to decrypt it.
+#[cfg(feature = "crypto")]
+fn seal(plain: &[u8], passphrase: &str) -> Result<Vec<u8>, StateError> {
+    let (mut salt, mut nonce) = ([0; SALT_LEN], [0; NONCE_LEN]);
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let params = Params::default();
+    let encoded = [params.m_cost(), params.t_cost(), params.p_cost()].map(u32::to_le_bytes).concat();
+    let sealed = cipher(passphrase, &salt, params)?
+        .encrypt(Nonce::from_slice(&nonce), plain)
+        .map_err(|_| io::Error::other("Failed to encrypt state"))?;
+    Ok([&salt[..], &encoded, &nonce, &sealed].concat())
+}
+
+#[cfg(feature = "crypto")]
+fn unseal(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>, StateError> {
+    if sealed.len() < SALT_LEN + PARAMS_LEN + NONCE_LEN {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (params, rest) = rest.split_at(PARAMS_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+    let [memory, passes, lanes] = [0, 4, 8].map(|at| u32::from_le_bytes([0, 1, 2, 3].map(|i| params[at + i])));
+    if memory > MAX_KDF_MEMORY_KIB || passes > MAX_KDF_PASSES || lanes > MAX_KDF_LANES {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Key derivation asks for too much work").into());
+    }
+
+    let params =
+        Params::new(memory, passes, lanes, None).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    cipher(passphrase, salt, params)?.decrypt(Nonce::from_slice(nonce), sealed).map_err(|_| StateError::WrongPassphrase)
+}
+
+#[cfg(not(feature = "crypto"))]
+fn seal(_: &[u8], _: &str) -> Result<Vec<u8>, StateError> { Err(StateError::EncryptionUnavailable) }
+
+#[cfg(not(feature = "crypto"))]
+fn unseal(_: &[u8], _: &str) -> Result<Vec<u8>, StateError> { Err(StateError::EncryptionUnavailable) }
+
+/// Stretches a passphrase into a key with Argon2id, which is as costly in memory as in time to guess against.
+#[cfg(feature = "crypto")]
+fn cipher(passphrase: &str, salt: &[u8], params: Params) -> Result<ChaCha20Poly1305, StateError> {
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(ChaCha20Poly1305::new(&key))
+}
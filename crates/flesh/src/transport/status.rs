@@ -16,11 +16,23 @@ pub enum Status {
     ProvideRelay,
     /// [008] -- Relay request
     Relay,
+    /// [009] -- Part of a message split across several frames
+    Fragment,
+    /// [010] -- Claim a human readable name
+    Alias,
+    /// [011] -- Leaving the network
+    Leave,
+    /// [012] -- Part of a reliable stream between two nodes
+    Channel,
+    /// [013] -- Ask relays for messages held while we were away
+    PullMail,
+    /// [014] -- Features a node offers for others to pick it out by
+    Capabilities,
     /// [015] -- Provided payload is too large (HTTP Equivalent 413)
     TooLarge,
     /// [016] -- Failed to receive ACK within timeframe (HTTP Equivalent 522)
     Timeout,
-    /// [017] --
+    /// [017] -- A relay couldn't pass the message on
     RelayFailure,
     /// [021] -- Immediate hints for a long processing request (HTTP Equivalent 103)
     EarlyHints,
@@ -30,7 +42,7 @@ pub enum Status {
     Acknowledge,
     /// [032] -- Non authorative information (fedi?) (HTTP Equivalent 203)
     NonAuthorative,
-    /// [033] -- " (HTTP Equivalent 208)
+    /// [033] -- Already received and handled (HTTP Equivalent 208)
     AlreadyReported,
     /// [041] -- Failed to deserialize, or unrecoverable error in processing (HTTP Equivalent 422)
     UnprocessableEntity,
@@ -47,7 +59,7 @@ pub enum Status {
     Custom(u8),
 }
 impl Status {
-    pub const STANDARD: [Self; 22usize] = [
+    pub const STANDARD: [Self; 28usize] = [
         Self::Announce,
         Self::Ping,
         Self::Pong,
@@ -56,6 +68,12 @@ impl Status {
         Self::RequestRelay,
         Self::ProvideRelay,
         Self::Relay,
+        Self::Fragment,
+        Self::Alias,
+        Self::Leave,
+        Self::Channel,
+        Self::PullMail,
+        Self::Capabilities,
         Self::TooLarge,
         Self::Timeout,
         Self::RelayFailure,
@@ -82,6 +100,12 @@ impl Status {
             Self::RequestRelay => 6u8,
             Self::ProvideRelay => 7u8,
             Self::Relay => 8u8,
+            Self::Fragment => 9u8,
+            Self::Alias => 10u8,
+            Self::Leave => 11u8,
+            Self::Channel => 12u8,
+            Self::PullMail => 13u8,
+            Self::Capabilities => 14u8,
             Self::TooLarge => 15u8,
             Self::Timeout => 16u8,
             Self::RelayFailure => 17u8,
@@ -110,6 +134,12 @@ impl Status {
             Self::RequestRelay => StatusType::Routing,
             Self::ProvideRelay => StatusType::Routing,
             Self::Relay => StatusType::Routing,
+            Self::Fragment => StatusType::Routing,
+            Self::Alias => StatusType::Routing,
+            Self::Leave => StatusType::Routing,
+            Self::Channel => StatusType::Routing,
+            Self::PullMail => StatusType::Routing,
+            Self::Capabilities => StatusType::Routing,
             Self::TooLarge => StatusType::RoutingError,
             Self::Timeout => StatusType::RoutingError,
             Self::RelayFailure => StatusType::RoutingError,
@@ -124,16 +154,119 @@ impl Status {
             Self::NotFound => StatusType::ClientErrors,
             Self::ServerError => StatusType::ServerErrors,
             Self::Teapot => StatusType::Oks,
-            Self::Custom(_) => StatusType::Unknown,
+            Self::Custom(int) => crate::transport::custom::lookup(*int).map_or(StatusType::Unknown, |(_, kind)| kind),
         }
     }
 
-    pub fn is_ok(&self) -> bool {
-        match self.as_type() {
-            StatusType::Routing | StatusType::Hints | StatusType::Oks => true,
-            _ => false,
+    /// Human readable name of the status
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Announce => "Announce",
+            Self::Ping => "Ping",
+            Self::Pong => "Pong",
+            Self::RequestKey => "Request Key",
+            Self::ProvideKey => "Provide Key",
+            Self::RequestRelay => "Request Relay",
+            Self::ProvideRelay => "Provide Relay",
+            Self::Relay => "Relay",
+            Self::Fragment => "Fragment",
+            Self::Alias => "Alias",
+            Self::Leave => "Leave",
+            Self::Channel => "Channel",
+            Self::PullMail => "Pull Mail",
+            Self::Capabilities => "Capabilities",
+            Self::TooLarge => "Too Large",
+            Self::Timeout => "Timeout",
+            Self::RelayFailure => "Relay Failure",
+            Self::EarlyHints => "Early Hints",
+            Self::Redirect => "Redirect",
+            Self::Acknowledge => "Acknowledge",
+            Self::NonAuthorative => "Non-Authorative",
+            Self::AlreadyReported => "Already reported",
+            Self::UnprocessableEntity => "Unprocessable Entity",
+            Self::Unauthorized => "Unauthorized",
+            Self::Forbidden => "Forbidden",
+            Self::NotFound => "Not Found",
+            Self::ServerError => "Server Error",
+            Self::Teapot => "Teapot",
+            Self::Custom(int) => crate::transport::custom::lookup(*int).map_or("Custom", |(name, _)| name),
+        }
+    }
+
+    /// Short description of what the status means
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Announce => "Announce self to network",
+            Self::Ping => "Request local availability",
+            Self::Pong => "Provides local availability",
+            Self::RequestKey => "Request Key",
+            Self::ProvideKey => "Provide Key",
+            Self::RequestRelay => "Request relay availability",
+            Self::ProvideRelay => "Provide relay availability",
+            Self::Relay => "Relay request",
+            Self::Fragment => "Part of a message split across several frames",
+            Self::Alias => "Claim a human readable name",
+            Self::Leave => "Leaving the network",
+            Self::Channel => "Part of a reliable stream between two nodes",
+            Self::PullMail => "Ask relays for messages held while we were away",
+            Self::Capabilities => "Features a node offers for others to pick it out by",
+            Self::TooLarge => "Provided payload is too large",
+            Self::Timeout => "Failed to receive ACK within timeframe",
+            Self::RelayFailure => "A relay couldn't pass the message on",
+            Self::EarlyHints => "Immediate hints for a long processing request",
+            Self::Redirect => "Hint that a path is no longer valid",
+            Self::Acknowledge => "Data received successfully",
+            Self::NonAuthorative => "Non authorative information (fedi?)",
+            Self::AlreadyReported => "Already received and handled",
+            Self::UnprocessableEntity => "Failed to deserialize, or unrecoverable error in processing",
+            Self::Unauthorized => "Unauthorized",
+            Self::Forbidden => "Forbidden",
+            Self::NotFound => "Not Found",
+            Self::ServerError => "Generic hint that there was a server failure while processing",
+            Self::Teapot => "Im a teapot dude. What do you want from me",
+            Self::Custom(_) => "",
         }
     }
+
+    /// Every standard status as (code, name, category, reason)
+    pub fn standard_with_metadata() -> impl Iterator<Item = (u8, &'static str, StatusType, &'static str)> {
+        Self::STANDARD.into_iter().map(|s| (s.as_u8(), s.name(), s.as_type(), s.reason()))
+    }
+
+    /// The HTTP status code listed as equivalent, if any
+    fn http(&self) -> Option<u16> {
+        match self {
+            Self::TooLarge => Some(413u16),
+            Self::Timeout => Some(522u16),
+            Self::EarlyHints => Some(103u16),
+            Self::Redirect => Some(300u16),
+            Self::Acknowledge => Some(200u16),
+            Self::NonAuthorative => Some(203u16),
+            Self::AlreadyReported => Some(208u16),
+            Self::UnprocessableEntity => Some(422u16),
+            Self::Unauthorized => Some(401u16),
+            Self::Forbidden => Some(403u16),
+            Self::NotFound => Some(404u16),
+            Self::ServerError => Some(500u16),
+            Self::Teapot => Some(218u16),
+            _ => None,
+        }
+    }
+
+    /// HTTP status code for bridging to HTTP, falling back to a generic code for the category
+    pub fn to_http(&self) -> u16 {
+        self.http().unwrap_or(match self.as_type() {
+            StatusType::Routing | StatusType::Hints | StatusType::Oks => 200,
+            StatusType::RoutingError => 502,
+            StatusType::ClientErrors => 400,
+            StatusType::ServerErrors | StatusType::Unknown => 500,
+        })
+    }
+
+    /// The standard status equivalent to an HTTP status code, if there is one
+    pub fn from_http(code: u16) -> Option<Self> { Self::STANDARD.into_iter().find(|s| s.http() == Some(code)) }
+
+    pub fn is_ok(&self) -> bool { matches!(self.as_type(), StatusType::Routing | StatusType::Hints | StatusType::Oks) }
 }
 #[derive(Clone, Copy, Debug)]
 pub enum StatusType {
@@ -149,6 +282,20 @@ pub enum StatusType {
     ClientErrors,
     /// 051 -> 060
     ServerErrors,
-    /// Currently unbound or in custom range 061->254(~)
+    /// Currently unbound, or in custom range 061->254(~) and not registered with `Status::register_custom`
     Unknown,
 }
+impl StatusType {
+    /// The codes set aside for the category, `None` for `Unknown`
+    pub fn codes(&self) -> Option<std::ops::RangeInclusive<u8>> {
+        match self {
+            Self::Routing => Some(1..=14),
+            Self::RoutingError => Some(15..=20),
+            Self::Hints => Some(21..=30),
+            Self::Oks => Some(31..=40),
+            Self::ClientErrors => Some(41..=50),
+            Self::ServerErrors => Some(51..=60),
+            Self::Unknown => None,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use {
+    crate::transport::{
+        PacketTransport, TransportId,
+        builder::NetworkBuilder,
+        memory::{MemoryMedium, MemoryTransport},
+        network::Network,
+    },
+    async_trait::async_trait,
+    std::{
+        io,
+        ops::{Index, Range},
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+    tokio::spawn,
+};
+
+/// How a `LossyTransport` mistreats outgoing frames.
+#[derive(Debug, Clone)]
+pub struct Impairment {
+    /// Chance (0.0 -> 1.0) that a frame is silently dropped
+    pub loss: f64,
+    /// Chance (0.0 -> 1.0) that a frame is delivered twice
+    pub duplication: f64,
+    /// Each delivery is delayed by a uniformly chosen time in this range, which also reorders frames
+    pub latency: Range<Duration>,
+    /// Seed for the random choices, so a run can be reproduced
+    pub seed: u64,
+}
+
+impl Default for Impairment {
+    fn default() -> Self { Self { loss: 0.0, duplication: 0.0, latency: Duration::ZERO..Duration::ZERO, seed: 0 } }
+}
+
+/// Wraps a transport, dropping, duplicating, delaying and reordering what's sent through it,
+/// for exercising retransmission, acknowledgement and reassembly against a bad link.
+#[derive(Clone)]
+pub struct LossyTransport<T: PacketTransport> {
+    inner: T,
+    impairment: Arc<Impairment>,
+    rng: Arc<Mutex<SplitMix64>>,
+}
+
+impl<T: PacketTransport + Clone + 'static> LossyTransport<T> {
+    pub fn new(inner: T, impairment: Impairment) -> Self {
+        let rng = Arc::new(Mutex::new(SplitMix64(impairment.seed)));
+        Self { inner, impairment: Arc::new(impairment), rng }
+    }
+
+    /// A random number in 0.0 -> 1.0
+    fn roll(&self) -> f64 { self.rng.lock().map(|mut rng| rng.next_f64()).unwrap_or_default() }
+
+    fn delay(&self) -> Duration {
+        let Range { start, end } = self.impairment.latency;
+        start + end.saturating_sub(start).mul_f64(self.roll())
+    }
+
+    async fn impair(&self, link: Option<TransportId>, data: &[u8]) -> io::Result<()> {
+        if self.roll() < self.impairment.loss {
+            return Ok(());
+        }
+
+        let copies = if self.roll() < self.impairment.duplication { 2 } else { 1 };
+        for _ in 0..copies {
+            let delay = self.delay();
+            if delay.is_zero() {
+                Self::deliver(&self.inner, link, data).await?;
+                continue;
+            }
+
+            let (inner, data) = (self.inner.clone(), data.to_vec());
+            spawn(async move {
+                tokio::time::sleep(delay).await;
+                let _ = Self::deliver(&inner, link, &data).await;
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn deliver(inner: &T, link: Option<TransportId>, data: &[u8]) -> io::Result<()> {
+        match link {
+            Some(link) => inner.send_via(link, data).await,
+            None => inner.send(data).await,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: PacketTransport + Clone + 'static> PacketTransport for LossyTransport<T> {
+    async fn send(&self, data: &[u8]) -> io::Result<()> { self.impair(None, data).await }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { self.inner.recv().await }
+
+    async fn send_via(&self, link: TransportId, data: &[u8]) -> io::Result<()> { self.impair(Some(link), data).await }
+
+    fn max_frame(&self) -> Option<usize> { self.inner.max_frame() }
+
+    async fn recv_from(&mut self) -> io::Result<(TransportId, Vec<u8>)> { self.inner.recv_from().await }
+
+    async fn recv_measured(&mut self) -> io::Result<(TransportId, Vec<u8>, Option<i16>)> { self.inner.recv_measured().await }
+
+    async fn ready(&self) -> io::Result<()> { self.inner.ready().await }
+
+    async fn flush(&self) -> io::Result<()> { self.inner.flush().await }
+}
+
+pub const MESH_MIN_ANNOUNCE_INTERVAL_MS: u64 = 50;
+pub const MESH_MAX_ANNOUNCE_INTERVAL_MS: u64 = 1000;
+pub const MESH_ANNOUNCE_JITTER_MS: u64 = 20;
+
+/// A handful of networks sharing one `MemoryMedium`, each only hearing the nodes it's adjacent to,
+/// for testing discovery and relaying across several hops without any radios.
+/// Nodes announce far more often than the defaults, so the mesh forms in test time.
+pub struct Mesh {
+    pub medium: MemoryMedium,
+    pub nodes: Vec<Network<MemoryTransport>>,
+}
+
+impl Mesh {
+    /// Starts a node per row of `adjacency`, where `adjacency[a][b]` is whether `b` hears what `a` sends.
+    pub fn new(adjacency: &[Vec<bool>]) -> Self { Self::with_builder(adjacency, |b| b) }
+
+    /// Like `new`, letting `configure` adjust each node's builder before it starts.
+    pub fn with_builder(
+        adjacency: &[Vec<bool>],
+        configure: impl Fn(NetworkBuilder<MemoryTransport>) -> NetworkBuilder<MemoryTransport>,
+    ) -> Self {
+        Self::start(adjacency, None, configure)
+    }
+
+    fn start(
+        adjacency: &[Vec<bool>],
+        max_frame: Option<usize>,
+        configure: impl Fn(NetworkBuilder<MemoryTransport>) -> NetworkBuilder<MemoryTransport>,
+    ) -> Self {
+        let medium = MemoryMedium::new();
+        let connect = || match max_frame {
+            Some(max) => medium.connect().with_max_frame(max),
+            None => medium.connect(),
+        };
+        let transports = adjacency.iter().map(|_| connect()).collect::<Vec<_>>();
+        for (transport, row) in transports.iter().zip(adjacency) {
+            medium.restrict(transport.id(), transports.iter().zip(row).filter(|(_, hears)| **hears).map(|(t, _)| t.id()));
+        }
+
+        let nodes = transports
+            .into_iter()
+            .map(|transport| {
+                let builder = Network::builder(transport)
+                    .announce_interval(
+                        Duration::from_millis(MESH_MIN_ANNOUNCE_INTERVAL_MS),
+                        Duration::from_millis(MESH_MAX_ANNOUNCE_INTERVAL_MS),
+                    )
+                    .announce_jitter(Duration::from_millis(MESH_ANNOUNCE_JITTER_MS));
+                configure(builder).build()
+            })
+            .collect();
+
+        Self { medium, nodes }
+    }
+
+    /// `n` nodes in a chain, each only hearing the one either side of it.
+    pub fn line(n: usize) -> Self { Self::new(&adjacency(n, |a, b| a.abs_diff(b) == 1)) }
+
+    /// Like `line`, with every transport refusing frames over `max` bytes, see `MemoryTransport::with_max_frame`.
+    pub fn line_with_max_frame(n: usize, max: usize) -> Self {
+        Self::start(&adjacency(n, |a, b| a.abs_diff(b) == 1), Some(max), |b| b)
+    }
+
+    /// `n` nodes that can all hear each other.
+    pub fn full(n: usize) -> Self { Self::new(&adjacency(n, |a, b| a != b)) }
+
+    pub fn len(&self) -> usize { self.nodes.len() }
+
+    pub fn is_empty(&self) -> bool { self.nodes.is_empty() }
+}
+
+impl Index<usize> for Mesh {
+    type Output = Network<MemoryTransport>;
+
+    fn index(&self, index: usize) -> &Self::Output { &self.nodes[index] }
+}
+
+fn adjacency(n: usize, hears: impl Fn(usize, usize) -> bool) -> Vec<Vec<bool>> {
+    (0..n).map(|a| (0..n).map(|b| hears(a, b)).collect()).collect()
+}
+
+/// Small, seedable generator, good enough for simulating a noisy channel.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_f64(&mut self) -> f64 { (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 }
+}
@@ -0,0 +1,49 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        fragment::InternalMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{AckMode, Network},
+        status::Status,
+    },
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Whether `listener` overhears a receipt within `within`.
+async fn hears_receipt(listener: &mut MemoryTransport, within: Duration) -> bool {
+    timeout(within, async {
+        loop {
+            let Ok(Some(InternalMessage::Complete(data))) =
+                listener.recv().await.map(|f| InternalMessage::from_frame(f).ok())
+            else {
+                continue;
+            };
+            if FLESHMessage::deserialize(&data).is_ok_and(|m| m.headers.contains_key("receipt_for")) {
+                return;
+            }
+        }
+    })
+    .await
+    .is_ok()
+}
+
+#[tokio::test]
+async fn only_acked_messages_are_answered() {
+    let medium = MemoryMedium::new();
+    let (sender, receiver) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let mut listener = medium.connect();
+    sender.resolve(receiver.id).await.unwrap();
+
+    // A receipt id left on the message doesn't get it answered when we've asked for no ack
+    let quiet = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_receipt().with_body("no answer");
+    sender.send_with_ack(quiet, AckMode::None, WAIT).await.unwrap();
+    assert!(!hears_receipt(&mut listener, Duration::from_millis(300)).await, "AckMode::None message was acknowledged");
+
+    let acked = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("answer me");
+    sender.send_with_ack(acked, AckMode::Ack, WAIT).await.unwrap();
+    assert!(hears_receipt(&mut listener, WAIT).await, "AckMode::Ack message was never acknowledged");
+}
@@ -0,0 +1,33 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, status::Status},
+    uuid::Uuid,
+};
+
+#[test]
+fn targeted_messages_are_addressed_to_their_target_only() {
+    let (me, someone_else) = (Uuid::new_v4(), Uuid::new_v4());
+    let m = FLESHMessage::new(Status::Acknowledge).with_target(me);
+
+    assert!(m.addressed_to(me));
+    assert!(!m.addressed_to(someone_else));
+    assert!(!m.is_broadcast());
+}
+
+#[test]
+fn untargeted_messages_are_broadcasts() {
+    let m = FLESHMessage::new(Status::Acknowledge);
+
+    assert!(m.is_broadcast());
+    assert!(!m.addressed_to(Uuid::new_v4()));
+}
+
+/// Clearing a target turns a message back into a broadcast, and survives the wire.
+#[test]
+fn cleared_targets_read_back_as_broadcasts() {
+    let me = Uuid::new_v4();
+    let m = FLESHMessage::new(Status::Acknowledge).with_target(me).clear_target();
+    let m = FLESHMessage::deserialize(&m.serialize().unwrap()).unwrap();
+
+    assert!(m.is_broadcast());
+    assert!(!m.addressed_to(me));
+}
@@ -0,0 +1,32 @@
+use {
+    flesh::modes::airtime::{AirtimeBudget, DutyCycle},
+    std::time::Duration,
+};
+
+#[test]
+fn unlimited_budgets_never_run_out() {
+    let mut budget = AirtimeBudget::new(None);
+    assert!(budget.spend(Duration::from_secs(3600)));
+    assert_eq!(budget.remaining(), Duration::MAX);
+}
+
+#[test]
+fn spending_stops_at_the_allowance() {
+    // 10% of a second
+    let mut budget = AirtimeBudget::new(Some(DutyCycle { fraction: 0.1, window: Duration::from_secs(1) }));
+    assert!(budget.spend(Duration::from_millis(60)));
+    assert!(!budget.spend(Duration::from_millis(60)), "spent past the allowance");
+    assert_eq!(budget.remaining(), Duration::from_millis(40));
+    assert!(budget.spend(Duration::from_millis(40)));
+    assert_eq!(budget.remaining(), Duration::ZERO);
+}
+
+#[test]
+fn airtime_is_given_back_once_its_window_passes() {
+    let mut budget = AirtimeBudget::new(Some(DutyCycle { fraction: 0.5, window: Duration::from_millis(100) }));
+    assert!(budget.spend(Duration::from_millis(50)));
+    assert!(!budget.spend(Duration::from_millis(1)));
+
+    std::thread::sleep(Duration::from_millis(120));
+    assert_eq!(budget.remaining(), Duration::from_millis(50));
+}
@@ -0,0 +1,31 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, status::Status, testing::Mesh},
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+};
+
+#[tokio::test]
+async fn send_to_an_announced_alias() {
+    let mesh = Mesh::full(2);
+    let (alice, bob) = (&mesh[0], &mesh[1]);
+    alice.set_alias("alice").await.unwrap();
+
+    let id = timeout(Duration::from_secs(5), async {
+        loop {
+            match bob.resolve_alias("alice").await {
+                Some(id) => break id,
+                None => sleep(Duration::from_millis(10)).await,
+            }
+        }
+    })
+    .await
+    .expect("alias never resolved");
+    assert_eq!(id, alice.id);
+
+    let mut inbox = alice.as_stream();
+    bob.send_to_alias("alice", FLESHMessage::new(Status::Acknowledge).with_body("hi alice")).await.unwrap();
+
+    let received = timeout(Duration::from_secs(5), inbox.next()).await.expect("message never arrived").unwrap();
+    assert_eq!(received.body, b"hi alice");
+}
@@ -0,0 +1,154 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, RoutingMessage},
+    },
+    std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    },
+    tokio::time::timeout,
+};
+
+/// Nodes powered up together would otherwise announce in lockstep, talking over each other every time.
+#[tokio::test]
+async fn simultaneous_starts_announce_at_different_times() {
+    let medium = MemoryMedium::new();
+    let mut listener = medium.connect();
+    let nodes = (0..8)
+        .map(|_| {
+            Network::builder(medium.connect())
+                .announce_interval(Duration::from_millis(50), Duration::from_secs(1))
+                .announce_jitter(Duration::from_secs(1))
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    let started = Instant::now();
+    let mut first = HashMap::new();
+    timeout(Duration::from_secs(5), async {
+        while first.len() < nodes.len() {
+            let frame = listener.recv().await.unwrap();
+            let Ok(message) = FLESHMessage::deserialize(&frame) else { continue };
+            if let Ok(Some(RoutingMessage::Announce(id))) = RoutingMessage::from_message(&message) {
+                first.entry(id).or_insert(started.elapsed());
+            }
+        }
+    })
+    .await
+    .expect("not every node announced");
+
+    let (earliest, latest) = (first.values().min().unwrap(), first.values().max().unwrap());
+    assert!(*latest - *earliest > Duration::from_millis(100), "announcements bunched within {:?}", *latest - *earliest);
+}
+
+#[tokio::test]
+async fn announce_now_skips_the_wait() {
+    let medium = MemoryMedium::new();
+    let mut listener = medium.connect();
+    // Far longer than the test runs, so any announcement heard is the one asked for
+    let hour = Duration::from_secs(3600);
+    let network = Network::builder(medium.connect()).announce_interval(hour, hour).announce_jitter(Duration::ZERO).build();
+
+    network.announce_now().await.unwrap();
+    let heard = timeout(Duration::from_secs(1), async {
+        loop {
+            let frame = listener.recv().await.unwrap();
+            let Ok(message) = FLESHMessage::deserialize(&frame) else { continue };
+            if let Ok(Some(RoutingMessage::Announce(id))) = RoutingMessage::from_message(&message) {
+                return id;
+            }
+        }
+    });
+    assert_eq!(heard.await.expect("no announcement"), network.id);
+}
+
+#[tokio::test]
+async fn passive_networks_refuse_to_announce() {
+    let network = Network::builder(MemoryMedium::new().connect()).passive(true).announce_jitter(Duration::ZERO).build();
+    assert!(network.announce_now().await.is_err());
+}
+
+/// Apps on one node each run a network speaking for it over the same transport, and shouldn't
+/// each announce it.
+#[tokio::test]
+async fn networks_sharing_a_transport_coalesce_announcements() {
+    let medium = MemoryMedium::new();
+    let mut listener = medium.connect();
+    let transport = medium.connect();
+    let interval = Duration::from_millis(100);
+
+    let first =
+        Network::builder(transport.clone()).announce_interval(interval, interval).announce_jitter(Duration::ZERO).build();
+    let _second = Network::builder(transport)
+        .state(first.export_state().await)
+        .announce_interval(interval, interval)
+        .announce_jitter(Duration::ZERO)
+        .share_announcements(first.share_announcements())
+        .build();
+
+    let mut heard = 0;
+    let _ = timeout(Duration::from_millis(550), async {
+        loop {
+            let frame = listener.recv().await.unwrap();
+            let Ok(message) = FLESHMessage::deserialize(&frame) else { continue };
+            if let Ok(Some(RoutingMessage::Announce(id))) = RoutingMessage::from_message(&message) {
+                assert_eq!(id, first.id);
+                heard += 1;
+            }
+        }
+    })
+    .await;
+
+    // Five rounds, one announcement each, with a little slack for a round landing either side of the cut off
+    assert!((3..=6).contains(&heard), "heard {heard} announcements");
+}
+
+/// A stable neighbourhood doubles the wait between announcements up to the maximum, and a node
+/// turning up drops it back to the minimum.
+#[tokio::test]
+async fn stability_lengthens_the_interval_and_churn_shortens_it() {
+    let medium = MemoryMedium::new();
+    let mut listener = medium.connect();
+    let (min, max) = (Duration::from_millis(25), Duration::from_millis(400));
+    let builder = || Network::builder(medium.connect()).announce_interval(min, max).announce_jitter(Duration::ZERO);
+    let node = builder().build();
+
+    let mut announced = vec![];
+    let mut newcomer = None;
+    while announced.len() < 12 {
+        let frame = listener.recv().await.unwrap();
+        let Ok(message) = FLESHMessage::deserialize(&frame) else { continue };
+        if let Ok(Some(RoutingMessage::Announce(id))) = RoutingMessage::from_message(&message)
+            && id == node.id
+        {
+            announced.push(Instant::now());
+            // Once settled at the maximum, someone new turns up
+            if announced.len() == 3 {
+                newcomer = Some(builder().build());
+            }
+        }
+    }
+
+    drop(newcomer);
+
+    // Settled at the maximum until the newcomer's noticed, then back to the minimum and doubling again
+    let gaps = announced.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+    let churn = gaps.iter().position(|gap| *gap < max / 2).unwrap_or_else(|| panic!("never shortened: {gaps:?}"));
+    assert!(churn >= 2 && gaps[..churn].iter().all(|gap| *gap > max * 9 / 10), "{gaps:?}");
+    assert!(gaps[churn] < min * 2, "{gaps:?}");
+    let settling = &gaps[churn..gaps.len().min(churn + 5)];
+    assert!(settling.len() >= 4, "{gaps:?}");
+    for pair in settling.windows(2) {
+        assert!(pair[1] > pair[0] * 3 / 2, "didn't lengthen while stable: {gaps:?}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "minimum")]
+fn announce_bounds_must_be_in_order() {
+    let (transport, _) = MemoryTransport::pair();
+    let _ = Network::builder(transport).announce_interval(Duration::from_secs(10), Duration::from_secs(1));
+}
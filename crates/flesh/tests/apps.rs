@@ -0,0 +1,41 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::Network,
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+async fn pair() -> (Network<MemoryTransport>, Network<MemoryTransport>) {
+    let medium = MemoryMedium::new();
+    let (sender, receiver) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    sender.resolve(receiver.id).await.unwrap();
+    (sender, receiver)
+}
+
+#[tokio::test]
+async fn apps_only_hear_their_own_port() {
+    let (sender, receiver) = pair().await;
+    let (mut chat, mut sensors) = (Box::pin(receiver.subscribe_app(1)), Box::pin(receiver.subscribe_app(2)));
+
+    let (to_chat, to_sensors) = (sender.app(1), sender.app(2));
+    to_sensors.send(FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("11.5C")).await.unwrap();
+    to_chat.send(FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("hi")).await.unwrap();
+    sender.send(FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("untagged")).await.unwrap();
+    to_chat.send(FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("bye")).await.unwrap();
+
+    for expected in ["hi", "bye"] {
+        let m = timeout(WAIT, chat.next()).await.expect("chat heard nothing").unwrap();
+        assert_eq!((m.app(), m.body.as_slice()), (Some(1), expected.as_bytes()));
+    }
+
+    let m = timeout(WAIT, sensors.next()).await.expect("sensors heard nothing").unwrap();
+    assert_eq!((m.app(), m.body.as_slice()), (Some(2), b"11.5C".as_slice()));
+    assert!(timeout(Duration::from_millis(100), sensors.next()).await.is_err(), "sensors heard another port");
+}
@@ -0,0 +1,62 @@
+use {
+    async_trait::async_trait,
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, RELIABLE_RETRY_MAX_MS, RELIABLE_RETRY_MS, SendError},
+        status::Status,
+    },
+    std::{
+        io,
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+    tokio::time::Instant,
+};
+
+/// A transport noting when it sent each message asking for a receipt.
+#[derive(Clone)]
+struct Recording(MemoryTransport, Arc<Mutex<Vec<Instant>>>);
+
+#[async_trait]
+impl PacketTransport for Recording {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        if FLESHMessage::deserialize(data).is_ok_and(|m| m.receipt().is_some()) {
+            self.1.lock().unwrap().push(Instant::now());
+        }
+        self.0.send(data).await
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { self.0.recv().await }
+}
+
+#[tokio::test(start_paused = true)]
+async fn retransmissions_back_off_with_jitter() {
+    let medium = MemoryMedium::new();
+    let sent = Arc::new(Mutex::new(vec![]));
+    let sender = Network::new(Recording(medium.connect(), sent.clone()));
+    let receiver_link = medium.connect();
+    let receiver_link_id = receiver_link.id();
+    let receiver = Network::new(receiver_link);
+    sender.resolve(receiver.id).await.unwrap();
+
+    // The receiver still hears everything, but its receipts never make it back
+    medium.restrict(receiver_link_id, []);
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("anyone there?");
+    let result = sender.send_reliable(message, Duration::from_secs(40)).await;
+    assert!(matches!(result, Err(SendError::Timeout)), "{result:?}");
+
+    let sent = sent.lock().unwrap();
+    let gaps = sent.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+    assert!(gaps.len() >= 4, "only {} retransmissions", gaps.len());
+
+    let mut base = Duration::from_millis(RELIABLE_RETRY_MS);
+    for gap in &gaps {
+        assert!(*gap >= base && *gap <= base * 3 / 2, "{gap:?} outside {base:?} plus half");
+        base = (base * 2).min(Duration::from_millis(RELIABLE_RETRY_MAX_MS));
+    }
+    assert!(gaps.windows(2).all(|w| w[1] > w[0]), "gaps didn't grow: {gaps:?}");
+    assert!(gaps.iter().any(|gap| gap.as_millis() % 1000 != 0), "no jitter: {gaps:?}");
+}
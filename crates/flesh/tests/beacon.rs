@@ -0,0 +1,72 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, ResolutionState, RoutingMessage},
+    },
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+    uuid::Uuid,
+};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// Everything heard over `window`, decoded as routing messages.
+async fn overhear(listener: &mut MemoryTransport, window: Duration) -> Vec<RoutingMessage> {
+    let mut heard = vec![];
+    let _ = timeout(window, async {
+        loop {
+            let frame = listener.recv().await.unwrap();
+            let Ok(message) = FLESHMessage::deserialize(&frame) else { continue };
+            if let Ok(Some(routing)) = RoutingMessage::from_message(&message) {
+                heard.push(routing);
+            }
+        }
+    })
+    .await;
+    heard
+}
+
+#[tokio::test]
+async fn beacons_keep_known_peers_fresh() {
+    let medium = MemoryMedium::new();
+    let network = Network::builder(medium.connect()).announce_interval(HOUR, HOUR).build();
+    let peer = Network::builder(medium.connect())
+        .announce_interval(HOUR, HOUR)
+        .announce_jitter(Duration::ZERO)
+        .beacon_interval(Duration::from_millis(100))
+        .build();
+
+    network.resolve(peer.id).await.unwrap();
+    // Let the handshake that follows first contact settle, it resolves us in turn
+    timeout(Duration::from_secs(5), async {
+        while peer.negotiated(&network.id).await.is_none() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("never negotiated");
+
+    let mut listener = medium.connect();
+    let heard = overhear(&mut listener, Duration::from_millis(500)).await;
+    assert!(heard.iter().any(|m| matches!(m, RoutingMessage::Beacon(id) if *id == peer.id)), "no beacons in {heard:?}");
+    assert!(!heard.iter().any(|m| matches!(m, RoutingMessage::RequestKey(_))), "beacons set off a resolution: {heard:?}");
+
+    let state = network.resolution_state(&peer.id).await;
+    assert!(matches!(state, ResolutionState::Resolved { age } if age < Duration::from_millis(250)), "{state:?}");
+}
+
+#[tokio::test]
+async fn beacons_from_strangers_are_ignored() {
+    let medium = MemoryMedium::new();
+    let network = Network::builder(medium.connect()).announce_interval(HOUR, HOUR).build();
+    let (stranger, mut listener) = (medium.connect(), medium.connect());
+
+    let id = Uuid::new_v4();
+    stranger.send(&RoutingMessage::Beacon(id).to_message().unwrap().serialize().unwrap()).await.unwrap();
+
+    let heard = overhear(&mut listener, Duration::from_millis(300)).await;
+    assert!(!heard.iter().any(|m| matches!(m, RoutingMessage::RequestKey(_))), "a stranger's beacon set off a resolution");
+    assert_eq!(network.resolution_state(&id).await, ResolutionState::Unknown);
+}
@@ -0,0 +1,30 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        blocking::BlockingNetwork, encoding::FLESHMessage, memory::MemoryMedium, network::Network, status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::{runtime::Runtime, time::timeout},
+};
+
+/// Plain threads can talk to an async network through the blocking facade, both ways.
+#[test]
+fn blocking_network_talks_to_an_async_one() {
+    let medium = MemoryMedium::new();
+    let runtime = Runtime::new().unwrap();
+    let peer = runtime.block_on(async { Network::new(medium.connect()) });
+    let mut inbox = runtime.block_on(async { peer.as_stream() });
+
+    let blocking = BlockingNetwork::new(medium.connect()).unwrap();
+    let key = SigningKey::from_bytes(&runtime.block_on(peer.export_state()).key).verifying_key();
+    assert_eq!(blocking.resolve(peer.id).unwrap(), key);
+
+    blocking.send(FLESHMessage::new(Status::Acknowledge).with_body("from sync")).unwrap();
+    let received =
+        runtime.block_on(async { timeout(Duration::from_secs(1), inbox.next()).await }).expect("nothing arrived").unwrap();
+    assert_eq!(received.body, b"from sync");
+
+    runtime.block_on(peer.send(FLESHMessage::new(Status::Acknowledge).with_body("from async"))).unwrap();
+    assert_eq!(blocking.recv().unwrap().body, b"from async");
+}
@@ -0,0 +1,41 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, status::Status, testing::Mesh},
+    futures::{Stream, StreamExt},
+    std::{sync::Arc, time::Duration},
+    tokio::time::timeout,
+};
+
+/// Counts copies of `body` arriving on `inbox` until it's been quiet for a while.
+async fn copies_of(inbox: impl Stream<Item = Arc<FLESHMessage>>, body: &[u8]) -> usize {
+    let mut inbox = Box::pin(inbox);
+    let mut copies = 0;
+    while let Ok(Some(m)) = timeout(Duration::from_millis(500), inbox.next()).await {
+        copies += (m.body == body) as usize;
+    }
+
+    copies
+}
+
+/// Every node hears every other, so each copy is also heard (and could be re-broadcast) by the rest.
+#[tokio::test]
+async fn local_echo_and_remote_copies_arrive_once() {
+    let mesh = Mesh::full(3);
+    let inboxes = (0..mesh.len()).map(|i| mesh[i].as_stream()).collect::<Vec<_>>();
+    let body = postcard::to_allocvec(&"hello everyone").unwrap();
+
+    mesh[0].broadcast(Status::Acknowledge, &"hello everyone", true).await.unwrap();
+
+    let counts = futures::future::join_all(inboxes.into_iter().map(|inbox| copies_of(inbox, &body))).await;
+    assert_eq!(counts, [1, 1, 1]);
+}
+
+#[tokio::test]
+async fn without_local_echo_only_peers_receive() {
+    let mesh = Mesh::full(2);
+    let (mine, theirs) = (mesh[0].as_stream(), mesh[1].as_stream());
+    let body = postcard::to_allocvec(&7u32).unwrap();
+
+    mesh[0].broadcast(Status::Acknowledge, &7u32, false).await.unwrap();
+
+    assert_eq!(futures::join!(copies_of(mine, &body), copies_of(theirs, &body)), (0, 1));
+}
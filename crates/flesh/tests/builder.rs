@@ -0,0 +1,51 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{encoding::FLESHMessage, status::Status},
+    uuid::Uuid,
+};
+
+fn identity(seed: u8) -> (Uuid, SigningKey) { (Uuid::new_v4(), SigningKey::from_bytes(&[seed; 32])) }
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encrypting_needs_a_target() {
+    let peer = identity(2);
+    let built = FLESHMessage::builder(Status::Acknowledge).with_body("secret").encrypt_for(peer.1.verifying_key()).build();
+    assert!(matches!(built, Err(flesh::transport::encoding::MessageError::MissingTarget)));
+}
+
+/// The body can be set after asking for encryption, and is still what gets encrypted.
+#[cfg(feature = "crypto")]
+#[test]
+fn encryption_covers_the_final_body() {
+    let (me, peer) = (identity(1), identity(2));
+    let message = FLESHMessage::builder(Status::Acknowledge)
+        .with_target(peer.0)
+        .encrypt_for(peer.1.verifying_key())
+        .with_body("secret")
+        .sign(me.clone())
+        .unwrap();
+
+    message.verify(&me.1.verifying_key()).unwrap();
+    assert_ne!(message.body, b"secret");
+    assert_eq!(message.decrypt_body(&peer).unwrap().body, b"secret");
+}
+
+/// Signing is the last thing a builder does, so a message it signed verifies as is.
+#[test]
+fn signed_messages_verify() {
+    let me = identity(1);
+    let message =
+        FLESHMessage::builder(Status::Acknowledge).with_header("topic", "news").with_body("hi").sign(me.clone()).unwrap();
+
+    assert_eq!(message.sender, Some(me.0));
+    message.verify(&me.1.verifying_key()).unwrap();
+    message.with_body("edited").verify(&me.1.verifying_key()).unwrap_err();
+}
+
+#[test]
+fn status_and_target_can_be_changed() {
+    let message = FLESHMessage::new(Status::Ping).with_target(Uuid::new_v4()).with_status(Status::Pong).clear_target();
+    assert!(matches!(message.status, Status::Pong));
+    assert!(message.is_broadcast());
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "crypto")]
+
+use {
+    flesh::transport::{status::Status, testing::Mesh},
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn only_capable_nodes_are_sent_to() {
+    let mesh = Mesh::full(4);
+    let (sender, relays, other) = (&mesh[0], [&mesh[1], &mesh[2]], &mesh[3]);
+    for relay in relays {
+        relay.set_capabilities(["relay"]).await.unwrap();
+    }
+    other.set_capabilities(["compression"]).await.unwrap();
+
+    timeout(WAIT, async {
+        while sender.capable("relay").await.len() < relays.len() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("relays never advertised");
+
+    let mut inboxes = relays.map(|relay| Box::pin(relay.messages::<String>()));
+    let mut elsewhere = Box::pin(other.messages::<String>());
+
+    let body = postcard::to_allocvec("regroup").unwrap();
+    let mut sent = sender.send_to_capable("relay", Status::Acknowledge, body).await.unwrap();
+    sent.sort();
+    let mut expected = relays.map(|relay| relay.id);
+    expected.sort();
+    assert_eq!(sent, expected);
+
+    for inbox in &mut inboxes {
+        let (from, body) = timeout(WAIT, inbox.next()).await.expect("relay heard nothing").unwrap();
+        assert_eq!((from, body.as_str()), (sender.id, "regroup"));
+    }
+
+    assert!(timeout(Duration::from_millis(200), elsewhere.next()).await.is_err(), "a node without the capability heard it");
+}
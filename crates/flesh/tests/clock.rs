@@ -0,0 +1,42 @@
+use {
+    flesh::transport::{clock, encoding::FLESHMessage, status::Status},
+    std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU64, Ordering},
+        },
+        time::Duration,
+    },
+};
+
+const SENT: u64 = 1_700_000_000;
+
+#[test]
+fn messages_expire_exactly_at_their_ttl() {
+    let now = Arc::new(AtomicU64::new(SENT));
+    let _clock = clock::set_clock({
+        let now = now.clone();
+        move || now.load(Ordering::Relaxed)
+    });
+
+    let message = FLESHMessage::new_at(Status::Acknowledge, SENT);
+    let ttl = Duration::from_secs(60);
+    assert!(!message.is_expired(ttl));
+
+    now.store(SENT + 59, Ordering::Relaxed);
+    assert!(!message.is_expired(ttl));
+
+    now.store(SENT + 60, Ordering::Relaxed);
+    assert!(message.is_expired(ttl));
+}
+
+#[test]
+fn new_messages_are_stamped_by_the_clock() {
+    {
+        let _clock = clock::set_clock(|| SENT);
+        assert_eq!(FLESHMessage::new(Status::Acknowledge).timestamp, SENT);
+    }
+
+    // Dropping the guard puts the system clock back
+    assert!(FLESHMessage::new(Status::Acknowledge).timestamp > SENT);
+}
@@ -0,0 +1,15 @@
+use {
+    flesh::transport::{status::Status, testing::Mesh},
+    std::time::Duration,
+};
+
+#[tokio::test]
+async fn broadcast_collect_hears_from_everyone_in_range() {
+    let mesh = Mesh::full(4);
+    let mut responders = mesh[0].broadcast_collect(Status::Custom(100), "anyone?", Duration::from_secs(2)).await.unwrap();
+    responders.sort();
+
+    let mut expected = mesh.nodes[1..].iter().map(|n| n.id).collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(responders, expected);
+}
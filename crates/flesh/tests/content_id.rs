@@ -0,0 +1,29 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, status::Status},
+    uuid::Uuid,
+};
+
+fn message() -> FLESHMessage { FLESHMessage { timestamp: 1_700_000_000, ..FLESHMessage::new(Status::Acknowledge) } }
+
+#[test]
+fn header_order_doesnt_change_the_id() {
+    let target = Uuid::new_v4();
+    let a = message().with_target(target).with_header("a", "1").with_header("b", "2").with_body("hi");
+    let b = message().with_target(target).with_header("b", "2").with_header("a", "1").with_body("hi");
+    assert_eq!(a.content_id(), b.content_id());
+}
+
+#[test]
+fn different_content_gets_a_different_id() {
+    let a = message().with_header("a", "1").with_body("hi");
+    assert_ne!(a.content_id(), a.clone().with_body("ho").content_id());
+    assert_ne!(a.content_id(), a.clone().with_header("a", "2").content_id());
+    assert_ne!(a.content_id(), a.clone().with_status(Status::NotFound).content_id());
+}
+
+#[test]
+fn signing_doesnt_change_the_id() {
+    let me = (Uuid::new_v4(), ed25519_dalek::SigningKey::from_bytes(&[1; 32]));
+    let unsigned = message().with_sender(me.0).with_body("hi");
+    assert_eq!(unsigned.content_id(), unsigned.clone().sign(me).unwrap().content_id());
+}
@@ -0,0 +1,54 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, NetworkEvent, RoutingMessage},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+#[tokio::test]
+async fn replies_echo_the_correlation_id() {
+    let (a, b) = MemoryTransport::pair();
+    let (asker, answerer) = (Network::new(a), Network::new(b));
+    let (mut questions, mut answers) = (answerer.as_stream(), asker.as_stream());
+
+    let question = FLESHMessage::new(Status::Acknowledge).with_sender(asker.id).with_correlation_id("req-42");
+    asker.send(question).await.unwrap();
+    let question = timeout(Duration::from_secs(5), questions.next()).await.expect("question never arrived").unwrap();
+    assert_eq!(question.correlation_id(), Some("req-42"));
+
+    answerer.reply(&question, FLESHMessage::new(Status::Acknowledge).with_body("yes")).await.unwrap();
+    let answer = timeout(Duration::from_secs(5), answers.next()).await.expect("reply never arrived").unwrap();
+    assert_eq!(answer.correlation_id(), Some("req-42"));
+}
+
+#[tokio::test]
+async fn relay_failures_carry_the_correlation_id() {
+    let medium = MemoryMedium::new();
+    let (origin, relayer) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let sender = medium.connect();
+    let mut events = origin.events().as_stream();
+
+    // Nobody's heard of the target, so the relay has to tell the origin it couldn't pass the message on
+    let message = FLESHMessage::new(Status::Acknowledge)
+        .with_sender(origin.id)
+        .with_target(Uuid::new_v4())
+        .with_correlation_id("req-7");
+    let wrapped = RoutingMessage::Relay(message.target.unwrap(), 3, message).to_message().unwrap().with_target(relayer.id);
+    sender.send(&wrapped.serialize().unwrap()).await.unwrap();
+
+    let correlation = timeout(Duration::from_secs(10), async {
+        loop {
+            if let Some(NetworkEvent::RelayFailed { correlation, .. }) = events.next().await.as_deref() {
+                return correlation.clone();
+            }
+        }
+    });
+    assert_eq!(correlation.await.expect("relay failure never arrived").as_deref(), Some("req-7"));
+}
@@ -0,0 +1,38 @@
+//! The registry is shared by the whole process, so each test claims its own codes.
+
+use flesh::transport::{
+    custom::RegisterError,
+    encoding::FLESHMessage,
+    status::{Status, StatusType},
+};
+
+#[test]
+fn custom_oks_count_as_ok() {
+    let status = Status::register_custom(100, "Stored", StatusType::Oks).unwrap();
+    assert!(status.is_ok());
+    assert_eq!(status.name(), "Stored");
+    assert!(matches!(status.as_type(), StatusType::Oks));
+
+    // Read off the wire it's still the registered status
+    let m = FLESHMessage::deserialize(&FLESHMessage::new(status).serialize().unwrap()).unwrap();
+    assert!(m.is_ok());
+}
+
+#[test]
+fn custom_errors_and_unregistered_codes_arent_ok() {
+    assert!(!Status::register_custom(101, "Disk Full", StatusType::ServerErrors).unwrap().is_ok());
+    assert!(!Status::Custom(102).is_ok());
+    assert!(matches!(Status::Custom(102).as_type(), StatusType::Unknown));
+}
+
+#[test]
+fn taken_and_standard_codes_cant_be_registered() {
+    Status::register_custom(103, "First", StatusType::Oks).unwrap();
+    assert!(matches!(Status::register_custom(103, "Second", StatusType::Oks), Err(RegisterError::Taken(103, "First"))));
+
+    let standard = Status::Acknowledge.as_u8();
+    assert!(matches!(
+        Status::register_custom(standard, "Mine", StatusType::Oks),
+        Err(RegisterError::Standard(code)) if code == standard
+    ));
+}
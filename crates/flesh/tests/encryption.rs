@@ -0,0 +1,27 @@
+#![cfg(feature = "crypto")]
+
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{encoding::FLESHMessage, status::Status},
+    rand_core::OsRng,
+    uuid::Uuid,
+};
+
+fn node() -> (Uuid, SigningKey) { (Uuid::new_v4(), SigningKey::generate(&mut OsRng)) }
+
+/// Bodies are encrypted to the X25519 form of the recipient's ed25519 key, which only their
+/// signing key can derive the matching secret for.
+#[test]
+fn only_the_recipient_can_decrypt() {
+    let (recipient, someone_else) = (node(), node());
+    for _ in 0..8 {
+        let sealed = FLESHMessage::new(Status::Acknowledge)
+            .with_body("for your eyes only")
+            .encrypt_body(&recipient.1.verifying_key())
+            .unwrap();
+        let sealed = FLESHMessage::deserialize(&sealed.serialize().unwrap()).unwrap();
+
+        assert_eq!(sealed.clone().decrypt_body(&recipient).unwrap().body, b"for your eyes only");
+        assert!(sealed.decrypt_body(&someone_else).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use {
+    flesh::events::{EventTarget, Subscription},
+    futures::StreamExt,
+    std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+fn counting(target: &EventTarget<u32>) -> (Arc<Subscription<u32>>, Arc<AtomicUsize>) {
+    let count = Arc::new(AtomicUsize::new(0));
+    let sub = target.on({
+        let count = count.clone();
+        move |_| {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    (sub, count)
+}
+
+/// Subscriptions find their target through the listeners it shares, so still unsubscribe after
+/// the target they were made from has moved.
+#[test]
+fn subscription_unsubscribes_after_its_target_moves() {
+    let make = || {
+        let target = EventTarget::new();
+        let (sub, count) = counting(&target);
+        (target, sub, count)
+    };
+
+    let (target, sub, count) = make();
+    let target = Box::new(target);
+    target.emit(1);
+    sub.off();
+    target.emit(2);
+    assert_eq!(count.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn subscription_outliving_its_target_is_dropped_safely() {
+    let target = EventTarget::new();
+    let (sub, count) = counting(&target);
+    drop(target);
+
+    sub.off();
+    drop(sub);
+    assert_eq!(count.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn clones_share_their_listeners() {
+    let target = EventTarget::new();
+    let (emitter, listener) = (target.clone(), target.clone());
+    let (_sub, count) = counting(&target);
+    let mut stream = listener.as_stream();
+
+    emitter.emit(7);
+    assert_eq!(count.load(Ordering::Relaxed), 1, "a handler on the original missed an emit on a clone");
+    assert_eq!(*stream.next().await.unwrap(), 7);
+
+    // A clone made after subscribing still reaches the same listeners
+    target.clone().emit(8);
+    assert_eq!(count.load(Ordering::Relaxed), 2);
+    assert_eq!(*stream.next().await.unwrap(), 8);
+}
@@ -0,0 +1,30 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::MemoryMedium,
+        network::{Network, NetworkEvent, ResolutionState, SendError},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+#[tokio::test]
+async fn forgotten_nodes_are_resolved_again() {
+    let medium = MemoryMedium::new();
+    let (network, peer) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let mut events = network.events().as_stream();
+    network.resolve(peer.id).await.unwrap();
+
+    network.forget(&peer.id).await;
+    assert_eq!(network.resolution_state(&peer.id).await, ResolutionState::Unknown);
+    let event = timeout(Duration::from_secs(1), events.next()).await.unwrap().unwrap();
+    assert!(matches!(&*event, NetworkEvent::Left { id } if *id == peer.id));
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(peer.id);
+    assert!(matches!(network.send(message.clone()).await, Err(SendError::UnknownNode(_))));
+
+    network.resolve(peer.id).await.unwrap();
+    network.send(message).await.unwrap();
+}
@@ -0,0 +1,124 @@
+use {
+    flesh::transport::{
+        encoding::{FLESHMessage, MessageError, WIRE_VERSION},
+        fragment::{Fragment, InternalMessage, MAX_PARTS, MAX_TRANSFERS, Reassembler},
+        status::Status,
+    },
+    uuid::Uuid,
+};
+
+#[test]
+fn fragment_metadata_round_trips() {
+    let fragment = Fragment { id: Uuid::new_v4(), index: 2, total: 5 };
+    let part = FLESHMessage::new(Status::Fragment).with_fragment(fragment).with_body([1, 2, 3]);
+
+    let decoded = FLESHMessage::deserialize(&part.serialize().unwrap()).unwrap();
+    assert_eq!(decoded.fragment, Some(fragment));
+    assert!(matches!(
+        InternalMessage::from_frame(part.serialize().unwrap()).unwrap(),
+        InternalMessage::Part { id, index: 2, total: 5, .. } if id == fragment.id
+    ));
+}
+
+#[test]
+fn interleaved_parts_reassemble_by_id() {
+    let (a, b) = (
+        FLESHMessage::new(Status::Acknowledge).with_body([1; 100]),
+        FLESHMessage::new(Status::NotFound).with_body([2; 100]),
+    );
+    let ((a_id, a_parts), (b_id, b_parts)) =
+        (InternalMessage::split(&a, 32).unwrap(), InternalMessage::split(&b, 32).unwrap());
+    assert_ne!(a_id, b_id);
+
+    // Parts of both messages arrive mixed together and out of order
+    let mut reassembler = Reassembler::default();
+    let mut complete = vec![];
+    for part in a_parts.iter().rev().zip(&b_parts).flat_map(|(a, b)| [a, b]) {
+        if let Some(InternalMessage::Complete(data)) =
+            reassembler.insert(InternalMessage::from_frame(part.serialize().unwrap()).unwrap())
+        {
+            complete.push(data);
+        }
+    }
+
+    assert_eq!(complete, [a.serialize().unwrap(), b.serialize().unwrap()]);
+}
+
+#[test]
+fn fragments_without_metadata_are_invalid() {
+    let part = FLESHMessage::new(Status::Fragment).with_body([1, 2, 3]);
+    assert!(matches!(InternalMessage::from_frame(part.serialize().unwrap()), Err(MessageError::InvalidFragment)));
+}
+
+#[test]
+fn other_wire_versions_are_refused() {
+    let old = FLESHMessage { version: WIRE_VERSION - 1, ..FLESHMessage::new(Status::Acknowledge) };
+    let result = FLESHMessage::deserialize(&old.serialize().unwrap());
+    assert!(matches!(result, Err(MessageError::UnsupportedVersion(v)) if v == WIRE_VERSION - 1), "{result:?}");
+}
+
+/// Signing and encryption happen before splitting, so both survive being reassembled.
+#[cfg(feature = "crypto")]
+#[test]
+fn signed_and_encrypted_messages_reassemble() {
+    use {ed25519_dalek::SigningKey, rand_core::OsRng};
+
+    let (sender, recipient) =
+        ((Uuid::new_v4(), SigningKey::generate(&mut OsRng)), (Uuid::new_v4(), SigningKey::generate(&mut OsRng)));
+    let body = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+    let message = FLESHMessage::new(Status::Acknowledge)
+        .with_target(recipient.0)
+        .with_body(body.clone())
+        .encrypt_body(&recipient.1.verifying_key())
+        .unwrap()
+        .sign(sender.clone())
+        .unwrap();
+
+    let (_, parts) = InternalMessage::split(&message, 64).unwrap();
+    assert!(parts.len() > 1);
+
+    let mut reassembler = Reassembler::default();
+    let complete = parts
+        .iter()
+        .filter_map(|part| reassembler.insert(InternalMessage::from_frame(part.serialize().unwrap()).unwrap()))
+        .collect::<Vec<_>>();
+    let [InternalMessage::Complete(data)] = complete.as_slice() else { panic!("{complete:?}") };
+
+    let received = FLESHMessage::deserialize(data).unwrap();
+    received.verify(&sender.1.verifying_key()).unwrap();
+    assert_eq!(received.decrypt_body(&recipient).unwrap().body, body);
+}
+
+#[test]
+fn parts_beyond_the_limit_are_refused() {
+    let part = |total| FLESHMessage::new(Status::Fragment).with_fragment(Fragment { id: Uuid::new_v4(), index: 0, total });
+    assert!(InternalMessage::from_frame(part(MAX_PARTS).serialize().unwrap()).is_ok());
+    assert!(matches!(
+        InternalMessage::from_frame(part(MAX_PARTS + 1).serialize().unwrap()),
+        Err(MessageError::InvalidFragment)
+    ));
+
+    let mut reassembler = Reassembler::default();
+    let oversized = InternalMessage::Part { id: Uuid::new_v4(), index: 0, total: u16::MAX, data: vec![1] };
+    assert!(reassembler.insert(oversized).is_none());
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_body(vec![0; MAX_PARTS as usize * 2]);
+    assert!(matches!(InternalMessage::split(&message, 1), Err(MessageError::InvalidFragment)));
+}
+
+#[test]
+fn oldest_transfer_gives_way_when_too_many_are_open() {
+    let part = |id, index| InternalMessage::Part { id, index, total: 2, data: vec![index as u8] };
+    let ids = (0..=MAX_TRANSFERS).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+
+    let mut reassembler = Reassembler::default();
+    for id in &ids {
+        assert!(reassembler.insert(part(*id, 0)).is_none());
+    }
+
+    // The first was dropped to make room for the last, so finishing it starts it over
+    assert!(reassembler.insert(part(ids[0], 1)).is_none());
+    assert!(
+        matches!(reassembler.insert(part(ids[MAX_TRANSFERS], 1)), Some(InternalMessage::Complete(data)) if data == [0, 1])
+    );
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "crypto")]
+
+use {
+    flesh::transport::{network::SendError, testing::Mesh},
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn removed_members_cant_read_the_group() {
+    let mesh = Mesh::full(3);
+    let (admin, stays, leaves) = (&mesh[0], &mesh[1], &mesh[2]);
+    let group = admin.create_group([stays.id, leaves.id]).await.unwrap();
+
+    timeout(WAIT, async {
+        while stays.group(group).await.is_none() || leaves.group(group).await.is_none() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("members never got the key");
+
+    let (mut stays_inbox, mut leaves_inbox) =
+        (Box::pin(stays.group_messages::<String>(group)), Box::pin(leaves.group_messages::<String>(group)));
+    admin.send_group(group, &"hello".to_string()).await.unwrap();
+    for inbox in [&mut stays_inbox, &mut leaves_inbox] {
+        assert_eq!(timeout(WAIT, inbox.next()).await.unwrap(), Some((admin.id, "hello".to_string())));
+    }
+
+    admin.remove_member(group, leaves.id).await.unwrap();
+    timeout(WAIT, async {
+        while stays.group(group).await.is_none_or(|g| g.epoch == 0) {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("the group was never re-keyed");
+    assert!(!stays.group(group).await.unwrap().members.contains(&leaves.id));
+
+    // Overhearing what's sent to the group from now on does them no good
+    let mut overheard = Box::pin(leaves.monitor().filter(|m| std::future::ready(m.headers.contains_key("group"))));
+    admin.send_group(group, &"secret".to_string()).await.unwrap();
+    let sealed = timeout(WAIT, overheard.next()).await.expect("nothing overheard").unwrap();
+
+    assert!(leaves.open_group::<String>(&sealed).await.is_err());
+    assert_eq!(stays.open_group::<String>(&sealed).await.unwrap(), (admin.id, "secret".to_string()));
+    assert_eq!(timeout(WAIT, stays_inbox.next()).await.unwrap(), Some((admin.id, "secret".to_string())));
+}
+
+#[tokio::test]
+async fn only_the_admin_changes_members() {
+    let mesh = Mesh::full(3);
+    let group = mesh[0].create_group([mesh[1].id]).await.unwrap();
+    timeout(WAIT, async {
+        while mesh[1].group(group).await.is_none() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("member never got the key");
+
+    let result = mesh[1].add_member(group, mesh[2].id).await;
+    assert!(matches!(result, Err(SendError::NotGroupAdmin(id)) if id == group), "{result:?}");
+    assert!(matches!(mesh[2].send_group(group, &()).await, Err(SendError::UnknownGroup(_))));
+}
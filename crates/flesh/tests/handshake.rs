@@ -0,0 +1,77 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::{FLESHMessage, WIRE_VERSION},
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, PeerParams, SendError},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+const MAX: usize = 300;
+
+/// A sender, and a receiver accepting frames of at most `MAX` bytes, once they've exchanged keys
+/// and told each other what they handle.
+async fn negotiated(
+    sender: Network<MemoryTransport>,
+    receiver: Network<MemoryTransport>,
+) -> (Network<MemoryTransport>, Network<MemoryTransport>) {
+    sender.resolve(receiver.id).await.unwrap();
+    timeout(WAIT, async {
+        while sender.negotiated(&receiver.id).await.is_none() || receiver.negotiated(&sender.id).await.is_none() {
+            sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("never negotiated");
+
+    (sender, receiver)
+}
+
+#[tokio::test]
+async fn first_contact_exchanges_what_each_node_handles() {
+    let medium = MemoryMedium::new();
+    let receiver = Network::builder(medium.connect()).max_inbound_frame(MAX).build();
+    receiver.set_capabilities(["relay"]).await.unwrap();
+    let (sender, receiver) = negotiated(Network::new(medium.connect()), receiver).await;
+
+    let params = sender.negotiated(&receiver.id).await.unwrap();
+    assert_eq!(params, PeerParams { version: WIRE_VERSION, max_frame: Some(MAX as u32), features: vec!["relay".into()] });
+    assert_eq!(receiver.negotiated(&sender.id).await.unwrap().max_frame, None);
+    assert_eq!(sender.capable("relay").await, vec![receiver.id]);
+}
+
+#[tokio::test]
+async fn sends_keep_to_the_smaller_limit() {
+    let medium = MemoryMedium::new();
+    let mut listener = medium.connect();
+    let receiver = Network::builder(medium.connect()).max_inbound_frame(MAX).build();
+    let (sender, receiver) =
+        negotiated(Network::builder(medium.connect()).max_inbound_frame(4 * MAX).build(), receiver).await;
+    let mut inbox = receiver.as_stream();
+
+    let body = vec![7; 3 * MAX];
+    sender.send(FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body(body.clone())).await.unwrap();
+    let received = timeout(WAIT, inbox.next()).await.expect("never delivered").unwrap();
+    assert_eq!(received.body, body);
+
+    // Everything sent since was the message's parts, each within the receiver's limit
+    while let Ok(Ok(frame)) = timeout(Duration::from_millis(100), listener.recv()).await {
+        assert!(frame.len() <= MAX, "sent a {} byte frame", frame.len());
+    }
+}
+
+#[tokio::test]
+async fn unsplittable_sends_over_the_limit_fail_up_front() {
+    let medium = MemoryMedium::new();
+    let receiver = Network::builder(medium.connect()).max_inbound_frame(MAX).build();
+    let (sender, receiver) = negotiated(Network::builder(medium.connect()).fragment(false).build(), receiver).await;
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body(vec![7; 2 * MAX]);
+    let result = sender.send(message).await;
+    assert!(matches!(result, Err(SendError::TooLarge { max: MAX, .. })), "{result:?}");
+}
@@ -0,0 +1,67 @@
+use {
+    flesh::transport::{
+        encoding::{FLESHMessage, MessageError, RESERVED_HEADERS},
+        status::Status,
+    },
+    std::{collections::BTreeSet, fs, path::Path},
+};
+
+#[test]
+fn reserved_headers_are_refused() {
+    let result = FLESHMessage::new(Status::Acknowledge).try_with_header("nonce", [0; 12]);
+    assert!(matches!(result, Err(MessageError::ReservedHeader(h)) if h == "nonce"));
+    assert!(RESERVED_HEADERS.iter().all(|h| FLESHMessage::new(Status::Acknowledge).try_with_header(h, []).is_err()));
+
+    let message = FLESHMessage::new(Status::Acknowledge).try_with_header("content-type", "text/plain").unwrap();
+    assert_eq!(message.headers["content-type"], b"text/plain");
+}
+
+/// Every header name set anywhere in the crate's source, as `with_header("name", ..)` or `headers.insert("name", ..)`.
+fn headers_set_in(dir: &Path, names: &mut BTreeSet<String>) {
+    for entry in fs::read_dir(dir).unwrap().map(Result::unwrap) {
+        let path = entry.path();
+        if path.is_dir() {
+            headers_set_in(&path, names);
+            continue;
+        }
+
+        let source = fs::read_to_string(&path).unwrap();
+        for setter in ["with_header(\"", "headers.insert(\""] {
+            names.extend(source.split(setter).skip(1).filter_map(|rest| Some(rest.split_once('"')?.0.to_string())));
+        }
+    }
+}
+
+/// Anything the crate sets itself has to be reserved, or a bridge could set it on someone's behalf.
+#[test]
+fn headers_the_crate_sets_are_reserved() {
+    let mut names = BTreeSet::new();
+    headers_set_in(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut names);
+
+    // Correlation ids are set by applications, on their own messages
+    names.remove("correlation");
+    assert!(names.contains("channel") && names.contains("receipt"), "only found {names:?}");
+    for name in names {
+        let result = FLESHMessage::new(Status::Acknowledge).try_with_header(&name, []);
+        assert!(matches!(result, Err(MessageError::ReservedHeader(_))), "{name} isn't reserved");
+    }
+}
+
+/// Headers encode in the same order however they were added, so a signature made over one copy of
+/// a message still verifies against another, such as the one that comes off the wire.
+#[test]
+fn headers_encode_in_a_fixed_order() {
+    let names = ["content-type", "lang", "reply-to", "x-trace"];
+    let build = |names: Vec<&str>| {
+        names.into_iter().fold(FLESHMessage::new_at(Status::Acknowledge, 0), |m, name| m.with_header(name, name))
+    };
+
+    let forwards = build(names.to_vec()).serialize().unwrap();
+    let backwards = build(names.iter().rev().copied().collect()).serialize().unwrap();
+    assert_eq!(forwards, backwards);
+
+    let me = (uuid::Uuid::new_v4(), ed25519_dalek::SigningKey::from_bytes(&[3; 32]));
+    let signed = build(names.to_vec()).sign(me.clone()).unwrap();
+    let received = FLESHMessage::deserialize(&signed.serialize().unwrap()).unwrap();
+    received.verify(&me.1.verifying_key()).unwrap();
+}
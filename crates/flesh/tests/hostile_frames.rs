@@ -0,0 +1,58 @@
+//! Frames crafted to make decoding allocate far more than they carry.
+
+use {
+    flesh::transport::{
+        encoding::{FLESHMessage, MessageError},
+        status::Status,
+    },
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// Tracks the largest single allocation, so tests can tell decoding didn't trust a length.
+struct Watching;
+
+static LARGEST: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Watching {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LARGEST.fetch_max(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) { unsafe { System.dealloc(ptr, layout) } }
+}
+
+#[global_allocator]
+static ALLOCATOR: Watching = Watching;
+
+/// A varint claiming just under 4GiB.
+const HUGE: [u8; 5] = [0xff, 0xff, 0xff, 0xff, 0x0f];
+
+fn decodes_cleanly(frame: &[u8]) {
+    LARGEST.store(0, Ordering::Relaxed);
+    let result = FLESHMessage::deserialize(frame);
+    let largest = LARGEST.load(Ordering::Relaxed);
+
+    assert!(matches!(result, Err(MessageError::DeserializationError(_))), "{result:?}");
+    assert!(largest < 4096, "decoding a {} byte frame allocated {largest} bytes at once", frame.len());
+}
+
+#[test]
+fn oversized_lengths_fail_without_allocating() {
+    // version, no target, no sender, timestamp, then the headers
+    let start = [1, 0, 0, 0];
+    decodes_cleanly(&[&start[..], &[0], &HUGE].concat());
+    decodes_cleanly(&[&start[..], &HUGE].concat());
+    decodes_cleanly(&[&start[..], &[1, 1, b'a'], &HUGE].concat());
+}
+
+#[test]
+fn truncated_frames_fail_cleanly() {
+    let frame = FLESHMessage::new(Status::Acknowledge).with_header("h", [1; 100]).with_body([2; 100]).serialize().unwrap();
+    for len in 0..frame.len() {
+        assert!(FLESHMessage::deserialize(&frame[..len]).is_err(), "{len} bytes decoded");
+    }
+}
@@ -0,0 +1,47 @@
+use {
+    flesh::transport::{memory::MemoryTransport, network::Network},
+    uuid::Uuid,
+};
+
+/// Ids can be derived from a name, so the same app always comes up as the same node.
+#[tokio::test]
+async fn builder_takes_a_name_derived_id() {
+    let id = Uuid::from_bytes(*b"chat-app.manager");
+    let (transport, _) = MemoryTransport::pair();
+    let network = Network::builder(transport).id(id).build();
+
+    assert_eq!(network.local_id(), id);
+}
+
+/// Asked for its key, a node must hand out the verifying half. Answering with the signing key would
+/// let whoever asked sign as it.
+#[tokio::test]
+async fn key_requests_are_answered_with_the_public_key() {
+    use {
+        ed25519_dalek::SigningKey,
+        flesh::transport::{PacketTransport, encoding::FLESHMessage, network::RoutingMessage},
+        std::time::Duration,
+        tokio::time::timeout,
+    };
+
+    let (transport, mut asker) = MemoryTransport::pair();
+    let network = Network::builder(transport).build();
+    let secret = network.export_state().await.key;
+
+    asker.send(&RoutingMessage::RequestKey(network.id).to_message().unwrap().serialize().unwrap()).await.unwrap();
+    let provided = timeout(Duration::from_secs(5), async {
+        loop {
+            let m = FLESHMessage::deserialize(&asker.recv().await.unwrap()).unwrap();
+            if let Ok(Some(RoutingMessage::ProvideKey(id, key, _))) = RoutingMessage::from_message(&m)
+                && id == network.id
+            {
+                break key;
+            }
+        }
+    })
+    .await
+    .expect("the key was never provided");
+
+    assert_eq!(provided, SigningKey::from_bytes(&secret).verifying_key().as_bytes());
+    assert_ne!(provided, secret);
+}
@@ -0,0 +1,64 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::MemoryMedium,
+        network::{Network, SendError},
+        status::Status,
+    },
+    std::{collections::HashMap, time::Duration},
+    tokio::time::{sleep, timeout},
+    uuid::Uuid,
+};
+
+const WAIT: Duration = Duration::from_secs(30);
+
+#[tokio::test]
+async fn cancelled_sends_stop_retransmitting() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+
+    // A peer we've a key for, that never answers
+    let silent = Uuid::new_v4();
+    network.preload_keys(&[(silent, SigningKey::from_bytes(&[3; 32]).verifying_key())]).await;
+
+    let messages = ["cancelled", "kept"]
+        .map(|body| FLESHMessage::new(Status::Acknowledge).with_target(silent).with_body(body).with_receipt());
+    let [cancelled, kept] = messages.each_ref().map(|message| message.receipt().unwrap());
+    let [cancelled_send, kept_send] = messages.map(|message| {
+        let network = network.clone();
+        tokio::spawn(async move { network.send_reliable(message, WAIT).await })
+    });
+
+    timeout(Duration::from_secs(1), async {
+        while network.inflight().await.len() < 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("sends never listed");
+    assert!(network.inflight().await.iter().all(|send| send.target == silent && send.attempts >= 1));
+
+    assert!(network.cancel(cancelled).await);
+    let result = cancelled_send.await.unwrap();
+    assert!(matches!(result, Err(SendError::Withdrawn(id)) if id == cancelled), "{result:?}");
+
+    // The cancelled send drops out of the list, and off the air, while the other carries on
+    let mut listener = medium.connect();
+    let mut heard = HashMap::<Uuid, usize>::new();
+    let _ = timeout(Duration::from_millis(2500), async {
+        loop {
+            let frame = listener.recv().await.unwrap();
+            if let Some(receipt) = FLESHMessage::deserialize(&frame).ok().and_then(|m| m.receipt()) {
+                *heard.entry(receipt).or_default() += 1;
+            }
+        }
+    })
+    .await;
+
+    assert_eq!(network.inflight().await.iter().map(|send| send.id).collect::<Vec<_>>(), vec![kept]);
+    assert_eq!(heard.get(&cancelled), None);
+    assert!(heard.get(&kept).is_some_and(|n| *n >= 1), "{heard:?}");
+    kept_send.abort();
+}
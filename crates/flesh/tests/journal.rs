@@ -0,0 +1,45 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        journal::{read_journal, replay_journal},
+        memory::MemoryMedium,
+        middleware::Direction,
+        network::Network,
+        status::Status,
+    },
+    futures::StreamExt,
+    std::{env, fs, time::Duration},
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+#[tokio::test]
+async fn replaying_a_journal_redelivers_what_was_received() {
+    let path = env::temp_dir().join(format!("flesh-journal-{}", Uuid::new_v4()));
+
+    let medium = MemoryMedium::new();
+    let recorded = Network::with_journal(medium.connect(), &path).unwrap();
+    let state = recorded.export_state().await;
+    let peer = Network::new(medium.connect());
+
+    let mut inbox = recorded.as_stream();
+    timeout(Duration::from_secs(5), peer.resolve(recorded.id)).await.expect("resolution timed out").unwrap();
+    peer.send(FLESHMessage::new(Status::Acknowledge).with_target(recorded.id).with_body("hello")).await.unwrap();
+    let original = timeout(Duration::from_secs(5), inbox.next()).await.expect("message never arrived").unwrap();
+
+    let entries = read_journal(&path).unwrap();
+    assert!(entries.iter().any(|e| e.direction == Direction::Inbound));
+    assert!(entries.iter().any(|e| e.direction == Direction::Outbound));
+
+    // A fresh medium with only the replay on it, heard by the recorded node's identity
+    let medium = MemoryMedium::new();
+    let replayed = Network::from_state(medium.connect(), state);
+    let mut inbox = replayed.as_stream();
+    replay_journal(&path, &medium.connect()).await.unwrap();
+
+    let received = timeout(Duration::from_secs(5), inbox.next()).await.expect("replay delivered nothing").unwrap();
+    assert_eq!((received.sender, received.target), (original.sender, original.target));
+    assert_eq!(received.body, original.body);
+
+    fs::remove_file(path).unwrap();
+}
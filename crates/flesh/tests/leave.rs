@@ -0,0 +1,49 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        memory::MemoryMedium,
+        network::{Network, NetworkEvent, RoutingMessage},
+        testing::Mesh,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+};
+
+#[tokio::test]
+async fn leaving_node_is_forgotten_straight_away() {
+    let mesh = Mesh::full(2);
+    let (stays, leaves) = (&mesh[0], &mesh[1]);
+    stays.resolve(leaves.id).await.unwrap();
+    assert!(stays.export_state().await.nodes.contains_key(&leaves.id));
+
+    let mut events = stays.events().as_stream();
+    leaves.shutdown().await.unwrap();
+
+    timeout(Duration::from_secs(1), async {
+        while let Some(event) = events.next().await {
+            if matches!(*event, NetworkEvent::Left { id } if id == leaves.id) {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("never heard the node leave");
+
+    assert!(!stays.export_state().await.nodes.contains_key(&leaves.id));
+}
+
+#[tokio::test]
+async fn forged_leave_is_ignored() {
+    let medium = MemoryMedium::new();
+    let (stays, victim, forger) = (Network::new(medium.connect()), Network::new(medium.connect()), medium.connect());
+    stays.resolve(victim.id).await.unwrap();
+
+    for signature in [vec![], vec![0; 64]] {
+        let leave = RoutingMessage::Leave(victim.id, signature).to_message().unwrap();
+        forger.send(&leave.serialize().unwrap()).await.unwrap();
+    }
+
+    sleep(Duration::from_millis(200)).await;
+    assert!(stays.export_state().await.nodes.contains_key(&victim.id), "forgot a node on a forged leave");
+}
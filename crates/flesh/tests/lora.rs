@@ -0,0 +1,414 @@
+#![cfg(feature = "lora")]
+
+use {
+    flesh::{
+        modes::{
+            airtime::{DutyCycle, time_on_air},
+            framing::{Endian, Framing, crc32, whiten, with_checksum},
+            lora::{Lora, LoraError, LoraEvent, LoraSettings, QueueFull},
+        },
+        transport::PacketTransport,
+    },
+    futures::StreamExt,
+    std::{
+        io,
+        path::PathBuf,
+        pin::Pin,
+        task::{Context, Poll},
+        time::{Duration, Instant},
+    },
+    tokio::{
+        io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, DuplexStream, ReadBuf},
+        time::timeout,
+    },
+};
+
+#[test]
+fn crc32_matches_the_check_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+/// A frame with a flipped payload byte still passes the length codec, so only the checksum catches it.
+#[tokio::test]
+async fn corrupted_frames_are_dropped() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let mut lora = Lora::from_stream(radio, LoraSettings { checksum: true, ..Default::default() }, false).await.unwrap();
+
+    let mut corrupted = with_checksum(b"garbled");
+    corrupted[2] ^= 0x40;
+    for payload in [corrupted, with_checksum(b"intact")] {
+        module.write_all(&[payload.len() as u8]).await.unwrap();
+        module.write_all(&payload).await.unwrap();
+    }
+
+    let received = timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap();
+    assert_eq!(received, b"intact");
+    assert_eq!(lora.corrupted_frames(), 1);
+}
+
+/// Serial reads don't line up with frames, so two arriving in one read are both decoded, and a frame split
+/// across reads waits for the rest.
+#[tokio::test]
+async fn frames_are_found_whatever_the_reads() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let mut lora = Lora::from_stream(radio, LoraSettings::default(), false).await.unwrap();
+
+    module.write_all(&[&[5][..], b"first", &[6], b"second", &[5], b"th"].concat()).await.unwrap();
+    for expected in [&b"first"[..], b"second"] {
+        let received = timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap();
+        assert_eq!(received, expected);
+    }
+
+    assert!(timeout(Duration::from_millis(100), lora.recv()).await.is_err(), "a partial frame was passed up");
+    module.write_all(b"ird").await.unwrap();
+    assert_eq!(timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap(), b"third");
+}
+
+/// A run of frames that all fail their checksum means we've lost sync, so the buffer's dropped,
+/// taking the stray header at the end of the run with it, and the frames after decode.
+#[tokio::test]
+async fn garbage_triggers_a_resync() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let settings = LoraSettings { checksum: true, resync_after: Some(3), ..Default::default() };
+    let mut lora = Lora::from_stream(radio, settings, false).await.unwrap();
+    let mut events = lora.events().as_stream();
+
+    let garbage = [[8, 0xde, 0xad, 0xbe, 0xef, 0x01, 0x02, 0x03, 0x04]; 3].concat();
+    module.write_all(&[&garbage[..], &[0x30]].concat()).await.unwrap();
+
+    let mut seen = vec![];
+    while seen.len() < 2 {
+        match &*timeout(Duration::from_secs(1), events.next()).await.expect("never resynced").unwrap() {
+            LoraEvent::Corrupted { .. } => {}
+            e => seen.push(format!("{e:?}")),
+        }
+    }
+    assert_eq!(seen, ["Desync { errors: 3 }", "Resynced { reconfigured: false }"]);
+
+    let frame = with_checksum(b"back in sync");
+    module.write_all(&[&[frame.len() as u8], &frame[..]].concat()).await.unwrap();
+    let received = timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap();
+    assert_eq!(received, b"back in sync");
+}
+
+#[tokio::test]
+async fn whitened_frames_differ_on_the_wire_and_round_trip() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let settings = LoraSettings { whitening: true, checksum: true, ..Default::default() };
+    let mut lora = Lora::from_stream(radio, settings, false).await.unwrap();
+
+    let payload = [0u8; 16];
+    lora.send(&payload).await.unwrap();
+    let mut wire = vec![0; 1 + payload.len() + 4];
+    timeout(Duration::from_secs(1), module.read_exact(&mut wire)).await.expect("nothing written").unwrap();
+    assert!(wire[1..=payload.len()].iter().any(|b| *b != 0), "payload went out unwhitened");
+
+    let mut unwhitened = wire[1..].to_vec();
+    whiten(&mut unwhitened);
+    assert_eq!(unwhitened, with_checksum(&payload));
+
+    // Sent back as it went out, it's dewhitened and passes its checksum
+    module.write_all(&wire).await.unwrap();
+    let received = timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap();
+    assert_eq!(received, payload);
+}
+
+/// A module on its AT interface counts the hex characters it sends and receives, not the bytes they encode.
+#[tokio::test]
+async fn line_framing_round_trips_through_an_at_module() {
+    let (radio, module) = tokio::io::duplex(1024);
+    let mut lora =
+        Lora::from_stream(radio, LoraSettings { framing: Framing::Lines, ..Default::default() }, false).await.unwrap();
+    let (reader, mut module) = tokio::io::split(module);
+    let mut commands = BufReader::new(reader).lines();
+
+    lora.send(&[0xde, 0xad, 0x00, 0x01]).await.unwrap();
+    let command = timeout(Duration::from_secs(1), commands.next_line()).await.expect("nothing sent").unwrap().unwrap();
+    assert_eq!(command, "AT+SEND=0,8,DEAD0001");
+
+    // Lines the module says in between, and one whose length is the byte count, aren't payloads
+    module.write_all(b"+OK\r\n+RCV=5,4,DEAD0001,-40,9\r\n+RCV=5,8,BEEF0002,-40,9\r\n").await.unwrap();
+    let received = timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap();
+    assert_eq!(received, [0xbe, 0xef, 0x00, 0x02]);
+}
+
+/// A serial port that takes at most a few bytes per write, as real UARTs often do.
+struct Trickle(DuplexStream);
+
+impl AsyncRead for Trickle {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Trickle {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, &buf[..buf.len().min(3)])
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
+
+#[tokio::test]
+async fn partial_writes_still_send_whole_frames() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let lora = Lora::from_stream(Trickle(radio), LoraSettings::default(), false).await.unwrap();
+
+    let payload = (0..100).collect::<Vec<u8>>();
+    lora.send(&payload).await.unwrap();
+
+    let mut written = vec![0; 1 + payload.len()];
+    timeout(Duration::from_secs(1), module.read_exact(&mut written)).await.expect("frame never written").unwrap();
+    assert_eq!(written[0] as usize, payload.len());
+    assert_eq!(&written[1..], payload);
+}
+
+/// A module that's stopped reading, leaving the writer task stuck mid-frame and the queue behind it filling up.
+async fn stalled(when_full: QueueFull) -> (Lora, DuplexStream) {
+    let (radio, module) = tokio::io::duplex(8);
+    let settings = LoraSettings { send_queue: 2, when_full, ..Default::default() };
+    (Lora::from_stream(radio, settings, false).await.unwrap(), module)
+}
+
+#[tokio::test]
+async fn full_queue_errors_when_asked_to() {
+    let (lora, _module) = stalled(QueueFull::Error).await;
+
+    let mut refused = None;
+    for _ in 0..4 {
+        if let Err(e) = lora.send(&[0; 32]).await {
+            refused = Some(e);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(refused.expect("queue never filled").kind(), io::ErrorKind::WouldBlock);
+    assert_eq!(lora.queue_depth(), 2);
+}
+
+/// Once a frame would take more airtime than the duty cycle has left, it's refused before queuing, and said so.
+#[tokio::test]
+async fn exhausted_airtime_refuses_frames() {
+    let (radio, _module) = tokio::io::duplex(4096);
+    let frame = time_on_air(9, 125, 32);
+    // Enough for one frame and a half in the window
+    let window = Duration::from_secs(3600);
+    let duty_cycle = DutyCycle { fraction: frame.as_secs_f64() * 1.5 / window.as_secs_f64(), window };
+    let lora =
+        Lora::from_stream(radio, LoraSettings { duty_cycle: Some(duty_cycle), ..Default::default() }, false).await.unwrap();
+    let mut events = lora.events().as_stream();
+
+    lora.send(&[0; 32]).await.unwrap();
+    assert_eq!(lora.send(&[0; 32]).await.unwrap_err().kind(), io::ErrorKind::QuotaExceeded);
+
+    let event = timeout(Duration::from_secs(1), events.next()).await.expect("no event").unwrap();
+    assert!(
+        matches!(*event, LoraEvent::BudgetExhausted { needed, remaining } if needed == frame && remaining < frame),
+        "{event:?}"
+    );
+}
+
+#[tokio::test]
+async fn full_queue_waits_for_room() {
+    let (lora, mut module) = stalled(QueueFull::Wait).await;
+    for _ in 0..3 {
+        lora.send(&[0; 32]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(lora.queue_depth(), 2);
+    assert!(timeout(Duration::from_millis(100), lora.send(&[0; 32])).await.is_err(), "send didn't wait for room");
+
+    // Draining the module makes room again
+    tokio::spawn(async move { while module.read(&mut [0; 64]).await.is_ok_and(|n| n > 0) {} });
+    timeout(Duration::from_secs(1), lora.send(&[0; 32])).await.expect("send never found room").unwrap();
+}
+
+#[tokio::test]
+async fn reconfigure_issues_commands_and_keeps_sending() {
+    let (radio, module) = tokio::io::duplex(1024);
+    let mut lora = Lora::from_stream(radio, LoraSettings::default(), false).await.unwrap();
+    let mut events = lora.events().as_stream();
+
+    // The mock module acknowledges each command it's given, then echoes the next frame back
+    let module = tokio::spawn(async move {
+        let mut module = BufReader::new(module);
+        let mut commands = vec![];
+        for _ in 0..4 {
+            let mut line = String::new();
+            module.read_line(&mut line).await.unwrap();
+            commands.push(line);
+            module.write_all(b"OK\r\n").await.unwrap();
+        }
+
+        let mut frame = vec![0; 1 + 5];
+        module.read_exact(&mut frame).await.unwrap();
+        module.write_all(&frame).await.unwrap();
+        commands
+    });
+
+    let settings = LoraSettings { spread_factor: 12, frequency_hz: 868_100_000, bandwidth_khz: 250, ..Default::default() };
+    timeout(Duration::from_secs(1), lora.reconfigure(settings)).await.expect("module never acknowledged").unwrap();
+    assert!(matches!(*events.next().await.unwrap(), LoraEvent::Reconfigured { settings } if settings.spread_factor == 12));
+    assert_eq!(lora.settings().frequency_hz, 868_100_000);
+
+    lora.send(b"hello").await.unwrap();
+    let echoed = timeout(Duration::from_secs(1), lora.recv()).await.expect("nothing received").unwrap();
+    assert_eq!(echoed, b"hello");
+    assert_eq!(module.await.unwrap(), ["AT+SF=12\r\n", "AT+FREQ=868100000\r\n", "AT+BW=250\r\n", "AT+PWR=14\r\n"]);
+}
+
+#[tokio::test]
+async fn invalid_settings_are_refused_before_reaching_the_module() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let lora = Lora::from_stream(radio, LoraSettings::default(), false).await.unwrap();
+
+    for settings in [
+        LoraSettings { spread_factor: 13, ..Default::default() },
+        LoraSettings { bandwidth_khz: 100, ..Default::default() },
+        LoraSettings { frequency_hz: 5_000, ..Default::default() },
+    ] {
+        assert_eq!(lora.reconfigure(settings).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    assert!(timeout(Duration::from_millis(50), module.read(&mut [0; 16])).await.is_err(), "module was sent something");
+    assert_eq!(lora.settings().spread_factor, LoraSettings::default().spread_factor);
+}
+
+#[tokio::test]
+async fn transmit_power_is_checked_then_applied() {
+    let (radio, module) = tokio::io::duplex(1024);
+    let lora = Lora::from_stream(radio, LoraSettings::default(), false).await.unwrap();
+
+    let too_loud = LoraSettings { tx_power_dbm: 30, ..Default::default() };
+    assert_eq!(lora.reconfigure(too_loud).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    let module = tokio::spawn(async move {
+        let mut module = BufReader::new(module);
+        let mut commands = vec![];
+        for _ in 0..4 {
+            let mut line = String::new();
+            module.read_line(&mut line).await.unwrap();
+            commands.push(line);
+            module.write_all(b"OK\r\n").await.unwrap();
+        }
+        commands
+    });
+
+    let quieter = LoraSettings { tx_power_dbm: 2, ..Default::default() };
+    timeout(Duration::from_secs(1), lora.reconfigure(quieter)).await.expect("module never acknowledged").unwrap();
+    assert_eq!(lora.settings().tx_power_dbm, 2);
+    assert_eq!(module.await.unwrap().last().unwrap(), "AT+PWR=2\r\n");
+}
+
+#[tokio::test]
+async fn flush_waits_for_queued_frames_to_go_out() {
+    let (radio, mut module) = tokio::io::duplex(1024);
+    let lora = Lora::from_stream(radio, LoraSettings::default(), false).await.unwrap();
+
+    let started = Instant::now();
+    for _ in 0..5 {
+        lora.send(b"hello").await.unwrap();
+    }
+    timeout(Duration::from_secs(5), lora.flush()).await.expect("flush never finished").unwrap();
+
+    // Every frame was already written to the module, and it's had time to transmit at least one
+    let mut written = vec![0; 5 * (1 + 5)];
+    timeout(Duration::from_millis(10), module.read_exact(&mut written)).await.expect("frames still queued").unwrap();
+    let settings = LoraSettings::default();
+    assert!(started.elapsed() >= time_on_air(settings.spread_factor, settings.bandwidth_khz, 5));
+}
+
+#[tokio::test]
+async fn missing_devices_are_named() {
+    let path = PathBuf::from("/dev/serial/by-id/no-such-lora-module");
+    let Err(e) = Lora::new(path.clone(), 9600, LoraSettings::default(), false).await else {
+        panic!("opened a missing device")
+    };
+
+    assert!(matches!(&e, LoraError::DeviceUnavailable(p, _) if *p == path));
+    assert!(e.to_string().contains("no-such-lora-module") && e.to_string().contains("doesn't exist"), "{e}");
+}
+
+/// A frame has to fit the length header and the module's AT command as well as the radio, less the checksum.
+#[tokio::test]
+async fn max_frame_accounts_for_the_framing() {
+    let one_byte = Framing::default();
+    let two_bytes = Framing::LengthDelimited { bytes: 2, endian: Endian::Big };
+    for (framing, checksum, max) in [
+        (one_byte, false, 255),
+        (one_byte, true, 251),
+        (two_bytes, false, 1200),
+        (two_bytes, true, 1196),
+        (Framing::Lines, false, 120),
+        (Framing::Lines, true, 116),
+    ] {
+        let (radio, _module) = tokio::io::duplex(1024);
+        let lora = Lora::from_stream(radio, LoraSettings { framing, checksum, ..Default::default() }, false).await.unwrap();
+        assert_eq!(lora.max_frame(), Some(max), "{framing:?} with checksum {checksum}");
+    }
+
+    let (radio, _module) = tokio::io::duplex(1024);
+    let lora = Lora::from_stream(radio, LoraSettings::default(), false).await.unwrap();
+    assert_eq!(lora.send(&[0; 256]).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+/// A mock module that answers each configuration command with the next of `replies`, handing back what it was sent.
+fn answering(module: DuplexStream, replies: &'static [&'static str]) -> tokio::task::JoinHandle<Vec<String>> {
+    tokio::spawn(async move {
+        let mut module = BufReader::new(module);
+        let mut commands = vec![];
+        for reply in replies {
+            let mut line = String::new();
+            module.read_line(&mut line).await.unwrap();
+            commands.push(line.trim_end().to_string());
+            module.write_all(format!("{reply}\r\n").as_bytes()).await.unwrap();
+        }
+
+        commands
+    })
+}
+
+#[tokio::test]
+async fn ready_waits_for_every_command_to_be_acknowledged() {
+    let (radio, module) = tokio::io::duplex(1024);
+    let settings = LoraSettings { spread_factor: 7, frequency_hz: 868_100_000, bandwidth_khz: 250, ..Default::default() };
+    let lora = Lora::from_stream(radio, settings, true).await.unwrap();
+
+    // Every command but the last is acknowledged straight away
+    let mut module = BufReader::new(module);
+    let mut commands = vec![];
+    for _ in 0..4 {
+        let mut line = String::new();
+        module.read_line(&mut line).await.unwrap();
+        commands.push(line.trim_end().to_string());
+        if commands.len() < 4 {
+            module.write_all(b"OK\r\n").await.unwrap();
+        }
+    }
+
+    assert_eq!(commands, ["AT+SF=7", "AT+FREQ=868100000", "AT+BW=250", &format!("AT+PWR={}", settings.tx_power_dbm)]);
+    assert!(timeout(Duration::from_millis(100), lora.ready()).await.is_err(), "ready before the last OK");
+
+    module.write_all(b"OK\r\n").await.unwrap();
+    timeout(Duration::from_secs(1), lora.ready()).await.expect("never became ready").unwrap();
+}
+
+/// A refused command stops configuration there, and `ready` says which one it was.
+#[tokio::test]
+async fn ready_reports_the_command_the_module_refused() {
+    let (radio, module) = tokio::io::duplex(1024);
+    let commands = answering(module, &["OK", "+ERR=4"]);
+    let lora = Lora::from_stream(radio, LoraSettings::default(), true).await.unwrap();
+
+    let error = timeout(Duration::from_secs(1), lora.ready()).await.expect("never settled").unwrap_err();
+    assert!(error.to_string().contains("FREQ") && error.to_string().contains("+ERR=4"), "{error}");
+    assert_eq!(commands.await.unwrap().len(), 2);
+}
@@ -0,0 +1,89 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::Network,
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+fn node(transport: MemoryTransport) -> Network<MemoryTransport> {
+    Network::builder(transport)
+        .announce_interval(Duration::from_millis(50), Duration::from_millis(100))
+        .announce_jitter(Duration::ZERO)
+        .mailbox(8, Duration::from_secs(60))
+        .build()
+}
+
+/// A and C are out of each other's range, so only reach each other through B, which holds mail.
+async fn line() -> (MemoryMedium, [Network<MemoryTransport>; 3]) {
+    let medium = MemoryMedium::new();
+    let (a, b, c) = (node(medium.connect()), node(medium.connect()), node(medium.connect()));
+    medium.restrict(0, [1]);
+    medium.restrict(2, [1]);
+    timeout(WAIT, a.resolve(c.id)).await.expect("resolution timed out").unwrap();
+    (medium, [a, b, c])
+}
+
+/// Takes C off the air, waiting until B has noticed it's gone quiet.
+async fn sleep_c(medium: &MemoryMedium) {
+    medium.restrict(2, []);
+    medium.restrict(1, [0]);
+    sleep(Duration::from_millis(300)).await;
+}
+
+fn wake_c(medium: &MemoryMedium) {
+    medium.restrict(2, [1]);
+    medium.restrict(1, [0, 2]);
+}
+
+async fn held(b: &Network<MemoryTransport>) {
+    timeout(WAIT, async {
+        while b.held_messages().await == 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("B never held the message");
+}
+
+#[tokio::test]
+async fn held_mail_is_delivered_when_the_node_announces() {
+    let (medium, [a, b, c]) = line().await;
+    let mut inbox = c.as_stream();
+
+    sleep_c(&medium).await;
+    a.send(FLESHMessage::new(Status::Acknowledge).with_target(c.id).with_body("while you were out")).await.unwrap();
+    held(&b).await;
+
+    wake_c(&medium);
+    let received = timeout(WAIT, inbox.next()).await.expect("held message never arrived").unwrap();
+    assert_eq!((received.sender, received.body.as_slice()), (Some(a.id), b"while you were out".as_slice()));
+    assert_eq!(b.held_messages().await, 0);
+}
+
+#[tokio::test]
+async fn held_mail_can_be_pulled() {
+    let medium = MemoryMedium::new();
+    let (a, b) = (node(medium.connect()), node(medium.connect()));
+    // C never announces, so only hears its mail by asking for it
+    let c = Network::builder(medium.connect()).announce_interval(Duration::from_secs(60), Duration::from_secs(60)).build();
+    medium.restrict(0, [1]);
+    medium.restrict(2, [1]);
+    timeout(WAIT, a.resolve(c.id)).await.expect("resolution timed out").unwrap();
+    let mut inbox = c.as_stream();
+
+    sleep_c(&medium).await;
+    a.send(FLESHMessage::new(Status::Acknowledge).with_target(c.id).with_body("pulled")).await.unwrap();
+    held(&b).await;
+
+    wake_c(&medium);
+    c.pull_mail().await.unwrap();
+    let received = timeout(WAIT, inbox.next()).await.expect("held message never arrived").unwrap();
+    assert_eq!(received.body, b"pulled");
+}
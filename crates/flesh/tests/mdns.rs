@@ -0,0 +1,90 @@
+#![cfg(feature = "mdns")]
+
+use {
+    flesh::transport::mdns::{MdnsDiscovery, SERVICE},
+    futures::StreamExt,
+    std::{
+        net::{Ipv4Addr, SocketAddr},
+        time::Duration,
+    },
+    tokio::{net::UdpSocket, time::timeout},
+};
+
+fn name(out: &mut Vec<u8>, name: &str) {
+    for label in name.split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+}
+
+/// A response advertising `instance` on `port`, as another node's responder would send it.
+fn srv_response(instance: &str, port: u16) -> Vec<u8> {
+    let mut packet = [0u16, 0x8400, 0, 1, 0, 0].iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<_>>();
+    name(&mut packet, &format!("{instance}.{SERVICE}"));
+    packet.extend_from_slice(&[0, 33, 0x80, 1, 0, 0, 0, 120]);
+
+    let mut target = [0u16, 0, port].iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<_>>();
+    name(&mut target, &format!("{instance}.local"));
+    packet.extend_from_slice(&(target.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&target);
+    packet
+}
+
+/// Whether a packet is a question asking for the service's PTR records.
+fn asks_for_service(packet: &[u8]) -> bool {
+    let mut question = vec![];
+    name(&mut question, SERVICE);
+    question.extend_from_slice(&[0, 12, 0, 1]);
+    packet[4..6] == [0, 1] && packet[12..] == question[..]
+}
+
+/// Discovery queries the group it's given, and turns the answer from a mock responder into a peer address.
+#[tokio::test]
+async fn peers_are_found_from_a_responders_answer() {
+    let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let discovery = MdnsDiscovery::from_socket(socket, responder.local_addr().unwrap(), 4000);
+    let mut discovered = discovery.discovered().as_stream();
+
+    let mut buf = [0; 1500];
+    let from = timeout(Duration::from_secs(1), async {
+        loop {
+            let (len, from) = responder.recv_from(&mut buf).await.unwrap();
+            if asks_for_service(&buf[..len]) {
+                break from;
+            }
+        }
+    })
+    .await
+    .expect("never queried");
+
+    responder.send_to(&srv_response("peer", 4242), from).await.unwrap();
+    let found = timeout(Duration::from_secs(1), discovered.next()).await.expect("nothing discovered").unwrap();
+    assert_eq!(*found, SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 4242));
+}
+
+/// Asked who offers the service, discovery answers with its own SRV record.
+#[tokio::test]
+async fn questions_are_answered_with_our_port() {
+    let responder = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let at = socket.local_addr().unwrap();
+    let _discovery = MdnsDiscovery::from_socket(socket, responder.local_addr().unwrap(), 4000);
+
+    // Let the opening announce and query go by, then ask
+    let mut buf = [0; 1500];
+    for _ in 0..2 {
+        timeout(Duration::from_secs(1), responder.recv_from(&mut buf)).await.expect("nothing sent").unwrap();
+    }
+
+    let mut question = [0u16, 0, 1, 0, 0, 0].iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<_>>();
+    name(&mut question, SERVICE);
+    question.extend_from_slice(&[0, 12, 0, 1]);
+    responder.send_to(&question, at).await.unwrap();
+
+    let (len, _) = timeout(Duration::from_secs(1), responder.recv_from(&mut buf)).await.expect("never answered").unwrap();
+    let answer = &buf[..len];
+    assert_eq!(answer[2..4], [0x84, 0x00], "not an authoritative response");
+    assert!(answer.windows(2).any(|w| w == 4000u16.to_be_bytes()), "our port isn't in the answer");
+}
@@ -0,0 +1,26 @@
+use {
+    flesh::transport::{PacketTransport, memory::MemoryMedium},
+    std::{io, time::Duration},
+    tokio::time::timeout,
+};
+
+/// A frame sent on the medium is heard by every other transport on it, but not echoed to its sender.
+#[tokio::test]
+async fn frames_reach_everyone_but_the_sender() {
+    let medium = MemoryMedium::new();
+    let (mut a, mut b, mut c) = (medium.connect(), medium.connect(), medium.connect());
+
+    a.send(b"hello").await.unwrap();
+    for receiver in [&mut b, &mut c] {
+        assert_eq!(timeout(Duration::from_secs(1), receiver.recv()).await.unwrap().unwrap(), b"hello");
+    }
+    assert!(timeout(Duration::from_millis(50), a.recv()).await.is_err(), "the sender heard its own frame");
+}
+
+#[tokio::test]
+async fn oversized_frames_are_refused() {
+    let medium = MemoryMedium::new();
+    let (a, mut b) = (medium.connect().with_max_frame(4), medium.connect());
+    assert_eq!(a.send(b"too long").await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    assert!(timeout(Duration::from_millis(50), b.recv()).await.is_err(), "the refused frame went out anyway");
+}
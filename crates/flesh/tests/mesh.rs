@@ -0,0 +1,57 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, network::SendError, status::Status, testing::Mesh},
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+/// The ends of a 5 node line are 4 hops apart, so can only find and reach each other through the middle.
+#[tokio::test]
+async fn line_endpoints_resolve_through_relays() {
+    let mesh = Mesh::line(5);
+    let (first, last) = (&mesh[0], &mesh[4]);
+
+    timeout(Duration::from_secs(5), first.resolve(last.id)).await.expect("resolution timed out").unwrap();
+
+    let mut inbox = last.as_stream();
+    first.send(FLESHMessage::new(Status::Acknowledge).with_target(last.id).with_body("hello")).await.unwrap();
+
+    let received = timeout(Duration::from_secs(5), inbox.next()).await.expect("message never arrived").unwrap();
+    assert_eq!(received.sender, Some(first.id));
+    assert_eq!(received.body, b"hello");
+}
+
+/// Across a 4 node line a message passes through 2 relays, so a hop limit of 1 stops it short.
+#[tokio::test]
+async fn hop_limits_are_enforced_along_the_way() {
+    let mesh = Mesh::line(4);
+    let (first, last) = (&mesh[0], &mesh[3]);
+    timeout(Duration::from_secs(5), first.resolve(last.id)).await.expect("resolution timed out").unwrap();
+    let mut inbox = last.as_stream();
+
+    let message = |body: &str| FLESHMessage::new(Status::Acknowledge).with_target(last.id).with_body(body);
+    let result = first.send_with_hops(message("no relays"), 0).await;
+    assert!(matches!(result, Err(SendError::HopLimit(id)) if id == last.id), "{result:?}");
+
+    first.send_with_hops(message("one relay"), 1).await.unwrap();
+    assert!(timeout(Duration::from_secs(1), inbox.next()).await.is_err(), "went further than allowed");
+
+    first.send_with_hops(message("two relays"), 2).await.unwrap();
+    let received = timeout(Duration::from_secs(5), inbox.next()).await.expect("message never arrived").unwrap();
+    assert_eq!(received.body, b"two relays");
+}
+
+/// A message too large for one frame is split, its parts relayed across the middle, and put back together at the end.
+#[tokio::test(start_paused = true)]
+async fn split_messages_cross_relays() {
+    let mesh = Mesh::line_with_max_frame(3, 256);
+    let (first, last) = (&mesh[0], &mesh[2]);
+    timeout(Duration::from_secs(5), first.resolve(last.id)).await.expect("resolution timed out").unwrap();
+    let mut inbox = last.as_stream();
+
+    let body = (0..1000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    first.send(FLESHMessage::new(Status::Acknowledge).with_target(last.id).with_body(body.clone())).await.unwrap();
+
+    let received = timeout(Duration::from_secs(30), inbox.next()).await.expect("message never arrived").unwrap();
+    assert_eq!(received.body, body);
+}
@@ -0,0 +1,73 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, NetworkEvent},
+        status::Status,
+    },
+    futures::StreamExt,
+    serde::{Deserialize, Serialize},
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Reading {
+    sensor: String,
+    celsius: f32,
+}
+
+async fn pair() -> (Network<MemoryTransport>, Network<MemoryTransport>) {
+    let medium = MemoryMedium::new();
+    let (sender, receiver) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    receiver.resolve(sender.id).await.unwrap();
+    sender.resolve(receiver.id).await.unwrap();
+    (sender, receiver)
+}
+
+#[cfg(feature = "crypto")]
+#[tokio::test]
+async fn secure_messages_arrive_attributed() {
+    let (sender, receiver) = pair().await;
+    let mut messages = Box::pin(receiver.messages::<Reading>());
+
+    let reading = Reading { sensor: "shed".into(), celsius: 11.5 };
+    sender.send_secure(receiver.id, &reading).await.unwrap();
+
+    let (from, received) = timeout(WAIT, messages.next()).await.expect("nothing arrived").unwrap();
+    assert_eq!((from, received), (sender.id, reading));
+}
+
+#[tokio::test]
+async fn unsigned_messages_are_reported() {
+    let (sender, receiver) = pair().await;
+    let mut messages = Box::pin(receiver.messages::<Reading>());
+    let mut events = receiver.events().as_stream();
+
+    sender.send(FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("not signed")).await.unwrap();
+
+    // The stream only reads messages as it's polled, reporting the ones it skips as it goes
+    assert!(timeout(Duration::from_millis(500), messages.next()).await.is_err());
+    let event = timeout(WAIT, events.next()).await.expect("no event").unwrap();
+    assert!(matches!(&*event, NetworkEvent::Unreadable { from: Some(id), .. } if *id == sender.id), "{event:?}");
+}
+
+/// Without `crypto` there's nothing to sign with, so messages are read as whoever they say they're from.
+#[cfg(not(feature = "crypto"))]
+#[tokio::test]
+async fn messages_arrive_unauthenticated_without_crypto() {
+    let (sender, receiver) = pair().await;
+    let mut messages = Box::pin(receiver.messages::<Reading>());
+
+    let reading = Reading { sensor: "shed".into(), celsius: 11.5 };
+    let body = postcard::to_allocvec(&reading).unwrap();
+    sender
+        .send(FLESHMessage::new(Status::Acknowledge).with_sender(sender.id).with_target(receiver.id).with_body(body))
+        .await
+        .unwrap();
+
+    let (from, received) = timeout(WAIT, messages.next()).await.expect("nothing arrived").unwrap();
+    assert_eq!((from, received), (sender.id, reading));
+}
@@ -0,0 +1,62 @@
+use {
+    async_trait::async_trait,
+    flesh::transport::{
+        PacketTransport,
+        memory::{MemoryMedium, MemoryTransport},
+        metrics::LatencyHistogram,
+        network::Network,
+    },
+    std::{io, time::Duration},
+};
+
+const DELAY: Duration = Duration::from_millis(40);
+
+/// A link that takes a while to carry anything, each way.
+#[derive(Clone)]
+struct Delayed(MemoryTransport);
+
+#[async_trait]
+impl PacketTransport for Delayed {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        tokio::time::sleep(DELAY).await;
+        self.0.send(data).await
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { self.0.recv().await }
+}
+
+#[test]
+fn percentiles_come_from_bucket_bounds() {
+    let mut histogram = LatencyHistogram::default();
+    assert_eq!(histogram.p50(), None);
+
+    for ms in [3, 5, 6, 7, 100] {
+        histogram.record(Duration::from_millis(ms));
+    }
+
+    assert_eq!(histogram.count(), 5);
+    assert_eq!(histogram.p50(), Some(Duration::from_millis(8)));
+    assert_eq!(histogram.p95(), Some(Duration::from_millis(128)));
+    assert_eq!(histogram.buckets().map(|(_, count)| count).sum::<usize>(), 5);
+}
+
+#[tokio::test]
+async fn resolutions_are_timed() {
+    let medium = MemoryMedium::new();
+    let resolver = Network::new(Delayed(medium.connect()));
+    let others = (0..4).map(|_| Network::new(Delayed(medium.connect()))).collect::<Vec<_>>();
+
+    for other in &others {
+        resolver.resolve(other.id).await.unwrap();
+    }
+
+    // Asking again is answered from what we know, so isn't timed
+    resolver.resolve(others[0].id).await.unwrap();
+
+    let histogram = resolver.resolution_latency_histogram().await;
+    assert_eq!(histogram.count(), others.len());
+
+    // A request and its answer each spend `DELAY` on the link
+    let (p50, p95) = (histogram.p50().unwrap(), histogram.p95().unwrap());
+    assert!(p50 >= DELAY * 2 && p95 < Duration::from_secs(2), "implausible latencies, p50 {p50:?}, p95 {p95:?}");
+}
@@ -0,0 +1,31 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        middleware::{Decision, Direction},
+        status::Status,
+        testing::Mesh,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+#[tokio::test]
+async fn middleware_can_drop_inbound_messages() {
+    let mesh = Mesh::full(2);
+    let (sender, receiver) = (&mesh[0], &mesh[1]);
+    sender.resolve(receiver.id).await.unwrap();
+
+    receiver.add_middleware(|m, direction| match direction == Direction::Inbound && m.headers.contains_key("spam") {
+        true => Decision::Drop,
+        false => Decision::Pass,
+    });
+
+    let mut inbox = receiver.as_stream();
+    let message = || FLESHMessage::new(Status::Acknowledge).with_target(receiver.id);
+    sender.send(message().with_header("spam", "yes").with_body("buy now")).await.unwrap();
+    sender.send(message().with_body("hello")).await.unwrap();
+
+    let received = timeout(Duration::from_secs(1), inbox.next()).await.expect("nothing delivered").unwrap();
+    assert_eq!(received.body, b"hello");
+}
@@ -0,0 +1,45 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::MemoryMedium,
+        network::{Network, RoutingMessage},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+#[tokio::test]
+async fn monitor_sees_everything_the_inbox_filters_out() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+    let other = medium.connect();
+    let (mut monitor, mut inbox) = (Box::pin(network.monitor()), network.as_stream());
+
+    let stranger = Uuid::new_v4();
+    for message in [
+        RoutingMessage::Announce(stranger).to_message().unwrap(),
+        FLESHMessage::new(Status::Acknowledge).with_target(stranger).with_body("not for us"),
+        FLESHMessage::new(Status::Acknowledge).with_body("for everyone"),
+    ] {
+        other.send(&message.serialize().unwrap()).await.unwrap();
+    }
+
+    let mut seen = vec![];
+    for _ in 0..3 {
+        let m = timeout(Duration::from_secs(1), monitor.next()).await.expect("monitor missed a message").unwrap();
+        seen.push((m.status.as_u8(), m.body.clone()));
+    }
+    assert_eq!(seen, [
+        (Status::Announce.as_u8(), vec![]),
+        (Status::Acknowledge.as_u8(), b"not for us".to_vec()),
+        (Status::Acknowledge.as_u8(), b"for everyone".to_vec())
+    ]);
+
+    let delivered = timeout(Duration::from_secs(1), inbox.next()).await.expect("nothing delivered").unwrap();
+    assert_eq!(delivered.body, b"for everyone");
+    assert!(timeout(Duration::from_millis(100), inbox.next()).await.is_err(), "inbox saw more than the data message");
+}
@@ -0,0 +1,103 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        PacketTransport, encoding::FLESHMessage, memory::MemoryTransport, multi::MultiTransport, network::Network,
+        status::Status,
+    },
+    rand_core::OsRng,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+/// A network on two links, and the far end of each.
+fn gateway() -> (Network<MultiTransport>, MemoryTransport, MemoryTransport) {
+    let ((a, far_a), (b, far_b)) = (MemoryTransport::pair(), MemoryTransport::pair());
+    (Network::builder(MultiTransport::new().with(a).with(b)).build(), far_a, far_b)
+}
+
+/// Whether a frame carrying `body` arrives on `link` before it goes quiet.
+async fn hears(link: &mut MemoryTransport, body: &[u8]) -> bool {
+    while let Ok(Ok(frame)) = timeout(Duration::from_millis(300), link.recv()).await {
+        if FLESHMessage::deserialize(&frame).is_ok_and(|m| m.body == body) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// A peer the network holds the key of, so can be sent to.
+async fn known_peer(network: &Network<MultiTransport>) -> (Uuid, SigningKey) {
+    let (peer, key) = (Uuid::new_v4(), SigningKey::generate(&mut OsRng));
+    network.preload_keys(&[(peer, key.verifying_key())]).await;
+    (peer, key)
+}
+
+async fn reply(network: &Network<MultiTransport>, to: Uuid, body: &str) {
+    network.send(FLESHMessage::new(Status::Acknowledge).with_target(to).with_body(body)).await.unwrap();
+}
+
+#[tokio::test]
+async fn replies_go_out_on_the_link_a_node_was_heard_on() {
+    let (network, mut first, mut second) = gateway();
+    let (peer, _) = known_peer(&network).await;
+
+    second.send(&FLESHMessage::new(Status::Acknowledge).with_sender(peer).serialize().unwrap()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    reply(&network, peer, "over the second").await;
+
+    assert!(hears(&mut second, b"over the second").await);
+    assert!(!hears(&mut first, b"over the second").await);
+}
+
+/// Anyone can claim to be `peer`, so an unsigned claim on another link can't take its replies away.
+#[tokio::test]
+async fn spoofed_senders_dont_redirect_replies() {
+    let (network, mut genuine, mut spoofer) = gateway();
+    let (peer, key) = known_peer(&network).await;
+
+    let signed = FLESHMessage::new(Status::Acknowledge).with_target(network.id).sign_with(peer, &key).unwrap();
+    genuine.send(&signed.serialize().unwrap()).await.unwrap();
+    let spoofed = FLESHMessage::new(Status::Acknowledge).with_target(network.id).with_sender(peer);
+    spoofer.send(&spoofed.serialize().unwrap()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    reply(&network, peer, "for the genuine peer").await;
+    assert!(hears(&mut genuine, b"for the genuine peer").await);
+    assert!(!hears(&mut spoofer, b"for the genuine peer").await);
+}
+
+/// Without a signature to go on, conflicting claims send replies everywhere rather than picking one.
+#[tokio::test]
+async fn conflicting_unsigned_claims_reach_every_link() {
+    let (network, mut first, mut second) = gateway();
+    let (peer, _) = known_peer(&network).await;
+
+    for link in [&first, &second] {
+        link.send(&FLESHMessage::new(Status::Acknowledge).with_sender(peer).serialize().unwrap()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    reply(&network, peer, "to whoever").await;
+    assert!(hears(&mut first, b"to whoever").await);
+    assert!(hears(&mut second, b"to whoever").await);
+}
+
+/// Each message says which link it came in on, and that's never taken from the wire.
+#[tokio::test]
+async fn messages_are_attributed_to_their_link() {
+    use {flesh::transport::TransportId, futures::StreamExt};
+
+    let (network, first, second) = gateway();
+    let mut inbox = network.as_stream();
+    for (link, body, id) in [(&second, "second", 1), (&first, "first", 0)] {
+        let mut m = FLESHMessage::new(Status::Acknowledge).with_body(body);
+        m.source = Some(TransportId(7));
+        link.send(&m.serialize().unwrap()).await.unwrap();
+
+        let received = timeout(Duration::from_secs(1), inbox.next()).await.expect("nothing delivered").unwrap();
+        assert_eq!(received.body, body.as_bytes());
+        assert_eq!(received.source, Some(TransportId(id)));
+    }
+}
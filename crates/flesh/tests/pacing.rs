@@ -0,0 +1,37 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::MemoryMedium,
+        network::{Network, RoutingMessage},
+    },
+    std::time::{Duration, Instant},
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+/// Ten nodes announcing at once should be asked for their keys at the configured pace, not all together.
+#[tokio::test]
+async fn key_requests_are_paced() {
+    let medium = MemoryMedium::new();
+    let _network = Network::builder(medium.connect()).resolve_rate(20, 1).build();
+    let mut crowd = medium.connect();
+
+    for _ in 0..10 {
+        let announce = RoutingMessage::Announce(Uuid::new_v4()).to_message().unwrap();
+        crowd.send(&announce.serialize().unwrap()).await.unwrap();
+    }
+
+    let mut requested = Vec::new();
+    while requested.len() < 10 {
+        let frame = timeout(Duration::from_secs(5), crowd.recv()).await.expect("ran out of key requests").unwrap();
+        let message = FLESHMessage::deserialize(&frame).unwrap();
+        if let Ok(Some(RoutingMessage::RequestKey(_))) = RoutingMessage::from_message(&message) {
+            requested.push(Instant::now());
+        }
+    }
+
+    // One up front, then one every 50ms
+    let spread = requested[9] - requested[0];
+    assert!(spread >= Duration::from_millis(400), "ten requests went out within {spread:?}");
+}
@@ -0,0 +1,68 @@
+use {
+    async_trait::async_trait,
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, RoutingMessage},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::{
+        io,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    },
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+/// A transport that counts what's sent through it.
+#[derive(Clone)]
+struct Counting(MemoryTransport, Arc<AtomicUsize>);
+
+#[async_trait]
+impl PacketTransport for Counting {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        self.0.send(data).await
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { self.0.recv().await }
+}
+
+#[tokio::test]
+async fn passive_network_never_transmits() {
+    let medium = MemoryMedium::new();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let passive = Network::builder(Counting(medium.connect(), sent.clone()))
+        .passive(true)
+        .announce_interval(Duration::from_millis(50), Duration::from_millis(100))
+        .announce_jitter(Duration::ZERO)
+        .build();
+    let other = medium.connect();
+
+    let mut inbox = passive.as_stream();
+    let (stranger, origin) = (Uuid::new_v4(), Uuid::new_v4());
+    let relayed = FLESHMessage::new(Status::Acknowledge).with_sender(origin).with_target(stranger);
+    for prompt in [
+        RoutingMessage::Announce(stranger).to_message().unwrap(),
+        RoutingMessage::RequestKey(passive.id).to_message().unwrap(),
+        RoutingMessage::RequestKey(stranger).to_message().unwrap(),
+        RoutingMessage::Ping(passive.id, stranger).to_message().unwrap(),
+        RoutingMessage::Relay(stranger, 3, relayed).to_message().unwrap().with_target(passive.id),
+        FLESHMessage::new(Status::Acknowledge).with_body("overheard"),
+    ] {
+        other.send(&prompt.serialize().unwrap()).await.unwrap();
+    }
+
+    let received = timeout(Duration::from_secs(1), inbox.next()).await.expect("nothing delivered").unwrap();
+    assert_eq!(received.body, b"overheard");
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(sent.load(Ordering::Relaxed), 0);
+    assert!(passive.send(FLESHMessage::new(Status::Acknowledge)).await.is_err());
+}
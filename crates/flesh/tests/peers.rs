@@ -0,0 +1,28 @@
+use {
+    flesh::transport::{memory::MemoryMedium, network::Network},
+    std::time::Duration,
+};
+
+#[tokio::test]
+async fn waits_for_a_peer_to_announce() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+
+    let waiting = tokio::spawn({
+        let network = network.clone();
+        async move { network.wait_for_peers(1, Duration::from_secs(5)).await }
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let _other = Network::builder(medium.connect())
+        .announce_interval(Duration::from_millis(50), Duration::from_millis(100))
+        .announce_jitter(Duration::ZERO)
+        .build();
+    assert!(waiting.await.unwrap());
+}
+
+#[tokio::test]
+async fn gives_up_when_nobody_appears() {
+    let network = Network::new(MemoryMedium::new().connect());
+    assert!(!network.wait_for_peers(1, Duration::from_millis(300)).await);
+}
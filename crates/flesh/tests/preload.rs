@@ -0,0 +1,84 @@
+#![cfg(feature = "crypto")]
+
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        fragment::InternalMessage,
+        memory::MemoryMedium,
+        network::{Network, NetworkEvent, NetworkState, RoutingMessage},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::{sleep, timeout},
+    uuid::Uuid,
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn preloaded_peers_are_reached_without_resolving() {
+    let medium = MemoryMedium::new();
+    let (a, b) = (NetworkState::new(Uuid::new_v4()), NetworkState::new(Uuid::new_v4()));
+    let roster =
+        [(a.id, SigningKey::from_bytes(&a.key).verifying_key()), (b.id, SigningKey::from_bytes(&b.key).verifying_key())];
+    let (sender, receiver) =
+        (Network::builder(medium.connect()).state(a).build(), Network::builder(medium.connect()).state(b).build());
+    let mut listener = medium.connect();
+    sender.preload_keys(&roster).await;
+    receiver.preload_keys(&roster).await;
+
+    let mut inbox = Box::pin(receiver.messages::<String>());
+    timeout(WAIT, sender.send_secure(receiver.id, &"roll call".to_string())).await.expect("send timed out").unwrap();
+    let (from, body) = timeout(WAIT, inbox.next()).await.expect("never delivered").unwrap();
+    assert_eq!((from, body.as_str()), (sender.id, "roll call"));
+
+    // Everything that went over the air was the message itself, with no key requests before it
+    let mut statuses = vec![];
+    while let Ok(Ok(frame)) = timeout(Duration::from_millis(100), listener.recv()).await {
+        if let Ok(InternalMessage::Complete(data)) = InternalMessage::from_frame(frame) {
+            statuses.push(FLESHMessage::deserialize(&data).unwrap().status);
+        }
+    }
+    assert!(!statuses.is_empty() && statuses.iter().all(|s| matches!(s, Status::Acknowledge)), "{statuses:?}");
+}
+
+/// A node leaving doesn't make someone else's key for it any more believable, so the pinned one stays
+/// until it's unpinned.
+#[tokio::test]
+async fn pinned_keys_outlast_the_node_leaving() {
+    let medium = MemoryMedium::new();
+    let (state, leaving) = (NetworkState::new(Uuid::new_v4()), NetworkState::new(Uuid::new_v4()));
+    let pinned = SigningKey::from_bytes(&leaving.key).verifying_key();
+    let (stays, leaves) =
+        (Network::builder(medium.connect()).state(state).build(), Network::builder(medium.connect()).state(leaving).build());
+    let forger = medium.connect();
+    stays.preload_keys(&[(leaves.id, pinned)]).await;
+
+    let mut events = stays.events().as_stream();
+    leaves.shutdown().await.unwrap();
+    timeout(WAIT, async {
+        while let Some(event) = events.next().await {
+            if matches!(*event, NetworkEvent::Left { id } if id == leaves.id) {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("never heard the node leave");
+
+    let impostor = SigningKey::from_bytes(&[9; 32]).verifying_key();
+    let offer = RoutingMessage::ProvideKey(leaves.id, impostor.as_bytes().to_vec(), None).to_message().unwrap();
+    let key_held = || async { stays.export_state().await.nodes.get(&leaves.id).copied() };
+
+    forger.send(&offer.serialize().unwrap()).await.unwrap();
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(key_held().await, Some(pinned.to_bytes()));
+
+    stays.unpin_key(&leaves.id).await;
+    forger.send(&offer.serialize().unwrap()).await.unwrap();
+    sleep(Duration::from_millis(200)).await;
+    assert_eq!(key_held().await, Some(impostor.to_bytes()));
+}
@@ -0,0 +1,90 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, NetworkState, SendError},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+/// A sender whose key we hold, so tests can sign as it, and a receiver checking what it gets.
+async fn pair() -> (Network<MemoryTransport>, SigningKey, Network<MemoryTransport>) {
+    let medium = MemoryMedium::new();
+    let state = NetworkState::new(Uuid::new_v4());
+    let key = SigningKey::from_bytes(&state.key);
+    let sender = Network::builder(medium.connect()).state(state).build();
+    let receiver = Network::builder(medium.connect()).check_inbound(true).build();
+
+    receiver.resolve(sender.id).await.unwrap();
+    sender.resolve(receiver.id).await.unwrap();
+    (sender, key, receiver)
+}
+
+#[tokio::test]
+async fn intact_messages_are_confirmed() {
+    let (sender, key, receiver) = pair().await;
+    let mut inbox = receiver.as_stream();
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_receipt().with_body("hi");
+    sender.send_reliable(message.sign((sender.id, key)).unwrap(), WAIT).await.unwrap();
+
+    let received = timeout(WAIT, inbox.next()).await.expect("never delivered").unwrap();
+    assert_eq!(received.body, b"hi");
+}
+
+#[tokio::test]
+async fn tampered_signature_is_reported() {
+    let (sender, key, receiver) = pair().await;
+    let mut inbox = receiver.as_stream();
+
+    let signed = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_receipt().with_body("hi");
+    let tampered = signed.sign((sender.id, key)).unwrap().with_body("bye");
+
+    let result = sender.send_reliable(tampered, WAIT).await;
+    assert!(matches!(result, Err(SendError::Unverified(id)) if id == receiver.id), "{result:?}");
+    assert!(timeout(Duration::from_millis(200), inbox.next()).await.is_err(), "tampered message was delivered");
+}
+
+#[cfg(feature = "crypto")]
+#[tokio::test]
+async fn wrong_key_encryption_is_reported() {
+    let (sender, _, receiver) = pair().await;
+    let someone_else = SigningKey::from_bytes(&[3; 32]).verifying_key();
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body("secret");
+    let result = sender.send_reliable(message.encrypt_body(&someone_else).unwrap(), WAIT).await;
+    assert!(matches!(result, Err(SendError::Undecryptable(id)) if id == receiver.id), "{result:?}");
+}
+
+/// Retries carry messages through a link losing 30% of frames each way, and each is delivered once
+/// however many copies get through.
+#[tokio::test(start_paused = true)]
+async fn reliable_sends_survive_a_lossy_link() {
+    use flesh::transport::testing::{Impairment, LossyTransport};
+
+    let medium = MemoryMedium::new();
+    let lossy = |seed| LossyTransport::new(medium.connect(), Impairment { loss: 0.3, seed, ..Default::default() });
+    let (sender, receiver) = (Network::new(lossy(1)), Network::new(lossy(2)));
+    let key = |secret| SigningKey::from_bytes(&secret).verifying_key();
+    sender.preload_keys(&[(receiver.id, key(receiver.export_state().await.key))]).await;
+    receiver.preload_keys(&[(sender.id, key(sender.export_state().await.key))]).await;
+    let mut inbox = receiver.as_stream();
+
+    for i in 0..10u8 {
+        let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body([i]);
+        sender.send_reliable(message, Duration::from_secs(120)).await.unwrap();
+    }
+
+    let mut received = vec![];
+    while let Ok(Some(m)) = timeout(Duration::from_secs(30), inbox.next()).await {
+        received.push(m.body[0]);
+    }
+    assert_eq!(received, (0..10).collect::<Vec<_>>());
+}
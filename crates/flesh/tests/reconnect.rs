@@ -0,0 +1,89 @@
+use {
+    async_trait::async_trait,
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, NetworkEvent},
+        reconnect::Reconnecting,
+        status::Status,
+    },
+    futures::StreamExt,
+    std::{
+        io,
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+    tokio::{select, time::timeout},
+    tokio_util::sync::CancellationToken,
+};
+
+/// A connection to a medium that can be pulled out, after which it fails like an unplugged device.
+#[derive(Clone)]
+struct Unpluggable(MemoryTransport, CancellationToken);
+
+#[async_trait]
+impl PacketTransport for Unpluggable {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        match self.1.is_cancelled() {
+            true => Err(io::Error::new(io::ErrorKind::BrokenPipe, "unplugged")),
+            false => self.0.send(data).await,
+        }
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> {
+        select! {
+            received = self.0.recv() => received,
+            _ = self.1.cancelled() => Err(io::Error::new(io::ErrorKind::BrokenPipe, "unplugged")),
+        }
+    }
+}
+
+async fn broadcast(from: &MemoryTransport, body: &str) {
+    from.send(&FLESHMessage::new(Status::Acknowledge).with_body(body).serialize().unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn resilient_stream_carries_on_after_reconnecting() {
+    let medium = MemoryMedium::new();
+    let plug = Arc::new(Mutex::new(CancellationToken::new()));
+    let transport = Reconnecting::new({
+        let (medium, plug) = (medium.clone(), plug.clone());
+        move || {
+            let (connection, plug) = (medium.connect(), plug.clone());
+            async move {
+                let token = CancellationToken::new();
+                *plug.lock().unwrap() = token.clone();
+                Ok(Unpluggable(connection, token))
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    let network = Network::new(transport.clone());
+    let (mut stream, mut events) = (network.resilient_stream(), network.events().as_stream());
+    let sender = medium.connect();
+
+    broadcast(&sender, "before").await;
+    let received = timeout(Duration::from_secs(1), stream.next()).await.expect("nothing received").unwrap();
+    assert_eq!(received.body, b"before");
+
+    plug.lock().unwrap().cancel();
+    timeout(Duration::from_secs(1), async {
+        while transport.reconnects() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("never reconnected");
+
+    broadcast(&sender, "after").await;
+    let received = timeout(Duration::from_secs(3), stream.next()).await.expect("stream stopped delivering").unwrap();
+    assert_eq!(received.body, b"after");
+
+    let reconnected = timeout(Duration::from_secs(1), async {
+        while !matches!(*events.next().await.unwrap(), NetworkEvent::Reconnected) {}
+    });
+    reconnected.await.expect("reconnection was never reported");
+}
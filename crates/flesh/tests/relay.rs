@@ -0,0 +1,150 @@
+use {
+    async_trait::async_trait,
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, NetworkEvent, RoutingMessage},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::{io, time::Duration},
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+async fn relay(from: &impl PacketTransport, via: Uuid, to: Uuid, hops: u8, m: FLESHMessage) {
+    let wrapped = RoutingMessage::Relay(to, hops, m).to_message().unwrap().with_target(via);
+    from.send(&wrapped.serialize().unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn own_message_relayed_back_is_dropped() {
+    let medium = MemoryMedium::new();
+    let origin = Network::new(medium.connect());
+    let looped = medium.connect();
+
+    let mut inbox = origin.as_stream();
+    let message = FLESHMessage::new(Status::Acknowledge).with_sender(origin.id).with_target(origin.id);
+    relay(&looped, origin.id, origin.id, 3, message).await;
+
+    assert!(timeout(Duration::from_millis(200), inbox.next()).await.is_err(), "our own message was delivered back to us");
+}
+
+#[tokio::test]
+async fn looped_relay_is_not_passed_on_again() {
+    let medium = MemoryMedium::new();
+    let (relayer, target) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let looped = medium.connect();
+    relayer.resolve(target.id).await.unwrap();
+
+    let mut inbox = target.as_stream();
+    let message = FLESHMessage::new(Status::Acknowledge).with_sender(Uuid::new_v4()).with_target(target.id);
+
+    // The second copy has spent hops going round a loop, so isn't a retransmission
+    relay(&looped, relayer.id, target.id, 5, message.clone()).await;
+    relay(&looped, relayer.id, target.id, 3, message).await;
+
+    timeout(Duration::from_millis(200), inbox.next()).await.expect("relayed message never arrived");
+    assert!(timeout(Duration::from_millis(200), inbox.next()).await.is_err(), "looped copy was relayed again");
+}
+
+/// A transport that takes a while to put anything on the air, so relays stay in flight.
+#[derive(Clone)]
+struct Slow(MemoryTransport);
+
+#[async_trait]
+impl PacketTransport for Slow {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        self.0.send(data).await
+    }
+
+    async fn recv(&mut self) -> io::Result<Vec<u8>> { self.0.recv().await }
+}
+
+#[tokio::test]
+async fn busy_relay_declines_more_work() {
+    let medium = MemoryMedium::new();
+    let relayer = Network::builder(Slow(medium.connect())).max_concurrent_relays(1).build();
+    let (origin, target) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let sender = medium.connect();
+    relayer.resolve(target.id).await.unwrap();
+
+    let (mut events, mut inbox) = (origin.events().as_stream(), target.as_stream());
+    for body in ["first", "second"] {
+        let message = FLESHMessage::new(Status::Acknowledge).with_sender(origin.id).with_target(target.id).with_body(body);
+        relay(&sender, relayer.id, target.id, 3, message).await;
+    }
+
+    let declined = timeout(Duration::from_secs(2), async {
+        loop {
+            if let Some(NetworkEvent::RelayFailed { reason, .. }) = events.next().await.as_deref() {
+                return reason.clone();
+            }
+        }
+    });
+    assert!(declined.await.expect("second relay wasn't declined").contains("busy"));
+
+    let relayed = timeout(Duration::from_secs(2), inbox.next()).await.expect("first relay never arrived").unwrap();
+    assert_eq!(relayed.body, b"first");
+}
+
+#[tokio::test]
+async fn non_forwarding_relay_refuses_even_after_offering() {
+    let medium = MemoryMedium::new();
+    let relayer = Network::builder(medium.connect()).forward(false).build();
+    let (origin, target) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let sender = medium.connect();
+    relayer.resolve(target.id).await.unwrap();
+
+    // It still tells the origin it can reach the target
+    let mut overheard = medium.connect();
+    let ask = RoutingMessage::RequestRelayCapability(target.id).to_message().unwrap();
+    sender.send(&ask.serialize().unwrap()).await.unwrap();
+    let offered = timeout(Duration::from_secs(1), async {
+        loop {
+            let m = FLESHMessage::deserialize(&overheard.recv().await.unwrap()).unwrap();
+            if let Ok(Some(RoutingMessage::ProvideRelayCapability(from, to, true))) = RoutingMessage::from_message(&m) {
+                return (from, to);
+            }
+        }
+    });
+    assert_eq!(offered.await.expect("relay never offered"), (relayer.id, target.id));
+
+    let (mut events, mut inbox) = (origin.events().as_stream(), target.as_stream());
+    let message = FLESHMessage::new(Status::Acknowledge).with_sender(origin.id).with_target(target.id);
+    relay(&sender, relayer.id, target.id, 3, message).await;
+
+    let refused = timeout(Duration::from_secs(2), async {
+        loop {
+            if let Some(NetworkEvent::RelayFailed { reason, .. }) = events.next().await.as_deref() {
+                return reason.clone();
+            }
+        }
+    });
+    assert!(refused.await.expect("relay wasn't refused").contains("doesn't forward"));
+    assert!(timeout(Duration::from_millis(200), inbox.next()).await.is_err(), "message was forwarded anyway");
+}
+
+/// Parts relayed to us one by one are reassembled like those heard directly, rather than delivered as fragments.
+#[tokio::test]
+async fn relayed_parts_are_reassembled() {
+    use flesh::transport::fragment::InternalMessage;
+
+    let (transport, relayer) = MemoryTransport::pair();
+    let network = Network::builder(transport).build();
+    let mut inbox = network.as_stream();
+
+    let body = (0..600u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let whole =
+        FLESHMessage::new(Status::Acknowledge).with_target(network.id).with_sender(Uuid::new_v4()).with_body(body.clone());
+    let (_, parts) = InternalMessage::split(&whole, 100).unwrap();
+    for part in parts {
+        relay(&relayer, network.id, network.id, 3, part).await;
+    }
+
+    let received = timeout(Duration::from_secs(1), inbox.next()).await.expect("nothing delivered").unwrap();
+    assert!(matches!(received.status, Status::Acknowledge), "{:?} delivered", received.status);
+    assert_eq!(received.body, body);
+}
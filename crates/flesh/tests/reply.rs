@@ -0,0 +1,25 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, memory::MemoryTransport, network::Network, status::Status},
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+#[tokio::test]
+async fn replies_reach_the_original_sender() {
+    let (a, b) = MemoryTransport::pair();
+    let (asker, answerer) = (Network::new(a), Network::new(b));
+    let (mut questions, mut answers) = (answerer.as_stream(), asker.as_stream());
+
+    asker.send(FLESHMessage::new(Status::Acknowledge).with_sender(asker.id).with_body("anyone there?")).await.unwrap();
+    let question = timeout(Duration::from_secs(5), questions.next()).await.expect("question never arrived").unwrap();
+
+    timeout(Duration::from_secs(5), answerer.reply(&question, FLESHMessage::new(Status::Acknowledge).with_body("yes")))
+        .await
+        .expect("reply timed out")
+        .unwrap();
+
+    let answer = timeout(Duration::from_secs(5), answers.next()).await.expect("reply never arrived").unwrap();
+    assert_eq!((answer.sender, answer.target), (Some(answerer.id), Some(asker.id)));
+    assert_eq!(answer.body, b"yes");
+}
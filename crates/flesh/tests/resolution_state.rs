@@ -0,0 +1,34 @@
+use {
+    flesh::transport::{
+        memory::MemoryMedium,
+        network::{Network, ResolutionState},
+    },
+    std::time::Duration,
+    uuid::Uuid,
+};
+
+#[tokio::test]
+async fn resolution_moves_from_unknown_to_resolved() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+    let id = Uuid::new_v4();
+    assert_eq!(network.resolution_state(&id).await, ResolutionState::Unknown);
+
+    let resolving = tokio::spawn({
+        let network = network.clone();
+        async move { network.resolve(id).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(network.resolution_state(&id).await, ResolutionState::Pending);
+
+    // They weren't around to hear the request, so it's their announcement that gets them resolved
+    let _other = Network::builder(medium.connect())
+        .id(id)
+        .announce_interval(Duration::from_millis(50), Duration::from_millis(100))
+        .announce_jitter(Duration::ZERO)
+        .build();
+    resolving.await.unwrap().unwrap();
+    assert!(
+        matches!(network.resolution_state(&id).await, ResolutionState::Resolved { age } if age < Duration::from_secs(1))
+    );
+}
@@ -0,0 +1,83 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, RoutingMessage},
+    },
+    futures::future::join_all,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+/// Key requests for `id` heard by `listener` until it's gone quiet for `quiet`.
+async fn requests_for(listener: &mut MemoryTransport, id: Uuid, quiet: Duration) -> usize {
+    let mut requests = 0;
+    while let Ok(frame) = timeout(quiet, listener.recv()).await {
+        let message = FLESHMessage::deserialize(&frame.unwrap()).unwrap();
+        if let Ok(Some(RoutingMessage::RequestKey(for_id))) = RoutingMessage::from_message(&message) {
+            requests += usize::from(for_id == id);
+        }
+    }
+    requests
+}
+
+#[tokio::test]
+async fn concurrent_resolves_share_one_request() {
+    let medium = MemoryMedium::new();
+    let (network, target) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let mut listener = medium.connect();
+
+    let keys = join_all((0..10).map(|_| network.resolve(target.id))).await;
+    assert!(keys.iter().all(Result::is_ok), "{keys:?}");
+    assert_eq!(requests_for(&mut listener, target.id, Duration::from_millis(200)).await, 1);
+}
+
+/// A caller that joined late keeps waiting when the first gives up, and the request is sent again for it.
+#[tokio::test(start_paused = true)]
+async fn late_caller_outlasts_the_first() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+    let mut listener = medium.connect();
+    let id = Uuid::new_v4();
+
+    let first = tokio::spawn({
+        let network = network.clone();
+        async move { network.resolve(id).await }
+    });
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    let late = tokio::spawn({
+        let network = network.clone();
+        async move { network.resolve(id).await }
+    });
+
+    assert!(first.await.unwrap().is_err());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!late.is_finished(), "the late caller failed along with the first");
+    assert_eq!(requests_for(&mut listener, id, Duration::from_millis(100)).await, 2);
+}
+
+/// Dropping the caller whose request the others are waiting on has it sent again for them.
+#[tokio::test]
+async fn cancelled_first_caller_is_asked_for_again() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+    let mut listener = medium.connect();
+    let id = Uuid::new_v4();
+
+    let first = tokio::spawn({
+        let network = network.clone();
+        async move { network.resolve(id).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let late = tokio::spawn({
+        let network = network.clone();
+        async move { network.resolve(id).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    first.abort();
+    assert_eq!(requests_for(&mut listener, id, Duration::from_millis(300)).await, 2);
+    assert!(!late.is_finished(), "the late caller failed along with the first");
+}
@@ -0,0 +1,40 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        network::{PeerParams, RoutingMessage},
+        status::Status,
+    },
+    uuid::Uuid,
+};
+
+/// Every routing message reads back as what was written, having been through the wire format.
+#[test]
+fn routing_messages_round_trip() {
+    let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+    let params = PeerParams { version: 1, max_frame: Some(255), features: vec!["relay".into()] };
+    let inner = FLESHMessage::new(Status::Acknowledge).with_sender(a).with_target(b).with_body("hello");
+    let messages = [
+        RoutingMessage::Announce(a),
+        RoutingMessage::Beacon(a),
+        RoutingMessage::Ping(a, b),
+        RoutingMessage::Pong(a, b),
+        RoutingMessage::RequestKey(a),
+        RoutingMessage::ProvideKey(a, vec![7; 32], None),
+        RoutingMessage::ProvideKey(a, vec![7; 32], Some(b)),
+        RoutingMessage::RequestRelayCapability(a),
+        RoutingMessage::ProvideRelayCapability(a, b, true),
+        RoutingMessage::Relay(b, 3, inner),
+        RoutingMessage::RelayFailure(a, "no route".into(), Some("abc".into())),
+        RoutingMessage::Alias(a, "node".into(), vec![1; 64]),
+        RoutingMessage::Leave(a, vec![3; 64]),
+        RoutingMessage::PullMail(a),
+        RoutingMessage::Capabilities(a, vec!["relay".into(), "gps".into()]),
+        RoutingMessage::Handshake(a, b, params, vec![2; 64]),
+    ];
+
+    for message in messages {
+        let wire = message.clone().to_message().unwrap().serialize().unwrap();
+        let read = RoutingMessage::from_message(&FLESHMessage::deserialize(&wire).unwrap()).unwrap();
+        assert_eq!(format!("{read:?}"), format!("{:?}", Some(&message)), "{message:?} didn't survive the wire");
+    }
+}
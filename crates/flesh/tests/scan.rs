@@ -0,0 +1,75 @@
+//! Tests for `Network::scan`.
+
+use {
+    flesh::{
+        modes::{
+            framing::Framing,
+            lora::{Lora, LoraSettings},
+        },
+        transport::{
+            memory::{MemoryMedium, MemoryTransport},
+            network::Network,
+            testing::{Impairment, LossyTransport},
+        },
+    },
+    std::time::Duration,
+    tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, ReadHalf, WriteHalf, split},
+};
+
+fn delayed(medium: &MemoryMedium, by: Duration) -> Network<LossyTransport<MemoryTransport>> {
+    let latency = by..by + Duration::from_millis(1);
+    Network::new(LossyTransport::new(medium.connect(), Impairment { latency, ..Default::default() }))
+}
+
+#[tokio::test]
+async fn scan_reports_nodes_that_answer_within_the_window() {
+    let medium = MemoryMedium::new();
+    let scanner = Network::new(medium.connect());
+    let (near, slow) = (delayed(&medium, Duration::from_millis(50)), delayed(&medium, Duration::from_millis(500)));
+    let nearest = Network::new(medium.connect());
+
+    let found = scanner.scan(Duration::from_millis(200)).await.unwrap();
+    let mut ids: Vec<_> = found.iter().map(|n| n.id).collect();
+    ids.sort();
+    let mut expected = vec![near.id, nearest.id];
+    expected.sort();
+    assert_eq!(ids, expected, "{} answered too late to be included", slow.id);
+
+    for node in &found {
+        assert!(node.rtt < Duration::from_millis(200), "{node:?}");
+        assert_eq!(node.rssi, None, "memory transports don't measure signal");
+    }
+
+    let near = found.iter().find(|n| n.id == near.id).unwrap();
+    assert!(near.rtt >= Duration::from_millis(50), "{near:?}");
+}
+
+/// Stands in for the air between two AT modules, passing what one sends to the other as a `+RCV` line
+/// heard at `rssi`.
+fn air(from: ReadHalf<DuplexStream>, mut to: WriteHalf<DuplexStream>, rssi: i16) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(from).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some(payload) = line.strip_prefix("AT+SEND=0,") else { continue };
+            to.write_all(format!("+RCV=0,{payload},{rssi},9\r\n").as_bytes()).await.unwrap();
+        }
+    });
+}
+
+#[tokio::test]
+async fn scan_reports_rssi_from_lora() {
+    let settings = LoraSettings { framing: Framing::Lines, ..Default::default() };
+    let (scanner_radio, scanner_module) = tokio::io::duplex(4096);
+    let (peer_radio, peer_module) = tokio::io::duplex(4096);
+    let ((scanner_heard, scanner_says), (peer_heard, peer_says)) = (split(scanner_module), split(peer_module));
+    air(scanner_heard, peer_says, -60);
+    air(peer_heard, scanner_says, -42);
+
+    let scanner = Network::new(Lora::from_stream(scanner_radio, settings, false).await.unwrap());
+    let peer = Network::new(Lora::from_stream(peer_radio, settings, false).await.unwrap());
+
+    let found = scanner.scan(Duration::from_millis(500)).await.unwrap();
+    assert_eq!(found.len(), 1, "{found:?}");
+    assert_eq!(found[0].id, peer.id);
+    assert_eq!(found[0].rssi, Some(-42));
+}
@@ -0,0 +1,56 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        encoding::{FLESHMessage, MessageError},
+        scheme::{MessageSigner, MessageVerifier},
+        status::Status,
+    },
+    sha2::{Digest, Sha256},
+    uuid::Uuid,
+};
+
+/// A stand-in scheme: a hash of a shared secret and the data.
+struct Keyed([u8; 16]);
+
+impl Keyed {
+    fn mac(&self, data: &[u8]) -> Vec<u8> { Sha256::new().chain_update(self.0).chain_update(data).finalize().to_vec() }
+}
+
+impl MessageSigner for Keyed {
+    fn scheme(&self) -> &str { "keyed-sha256" }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, MessageError> { Ok(self.mac(data)) }
+}
+
+impl MessageVerifier for Keyed {
+    fn scheme(&self) -> &str { "keyed-sha256" }
+
+    fn verify(&self, data: &[u8], signature: &[u8]) -> Result<(), MessageError> {
+        (self.mac(data) == signature).then_some(()).ok_or(MessageError::InvalidSignature)
+    }
+}
+
+#[test]
+fn other_schemes_round_trip() {
+    let key = Keyed([3; 16]);
+    let signed = FLESHMessage::new(Status::Acknowledge).with_body("hello").sign_with(Uuid::new_v4(), &key).unwrap();
+    let received = FLESHMessage::deserialize(&signed.serialize().unwrap()).unwrap();
+
+    assert_eq!(received.scheme(), "keyed-sha256");
+    received.verify_with(&key).unwrap();
+    assert!(matches!(received.clone().with_body("goodbye").verify_with(&key), Err(MessageError::InvalidSignature)));
+    assert!(matches!(received.verify_with(&Keyed([4; 16])), Err(MessageError::InvalidSignature)));
+}
+
+#[test]
+fn schemes_arent_mixed_up() {
+    let ed25519 = SigningKey::from_bytes(&[7; 32]);
+    let keyed = FLESHMessage::new(Status::Acknowledge).sign_with(Uuid::new_v4(), &Keyed([3; 16])).unwrap();
+    assert!(matches!(keyed.verify(&ed25519.verifying_key()), Err(MessageError::WrongScheme(s)) if s == "keyed-sha256"));
+
+    let signed = FLESHMessage::new(Status::Acknowledge).sign((Uuid::new_v4(), ed25519.clone())).unwrap();
+    assert_eq!(signed.scheme(), "ed25519");
+    assert!(!signed.headers.contains_key("scheme"));
+    assert!(matches!(signed.verify_with(&Keyed([3; 16])), Err(MessageError::WrongScheme(_))));
+    signed.verify_with(&ed25519.verifying_key()).unwrap();
+}
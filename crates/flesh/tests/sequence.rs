@@ -0,0 +1,48 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::MemoryMedium,
+        network::{Network, NetworkEvent},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+/// Numbered messages from one peer, sent straight onto the medium in the given order.
+/// Returns the missing ranges reported, as `(start, end)`.
+async fn deliver_numbered(seqs: &[u32]) -> Vec<(u32, u32)> {
+    let medium = MemoryMedium::new();
+    let (network, peer) = (Network::builder(medium.connect()).build(), medium.connect());
+    let mut events = network.events().as_stream();
+    let peer_id = Uuid::new_v4();
+
+    for seq in seqs {
+        let m = FLESHMessage::new(Status::Acknowledge).with_target(network.id).with_sender(peer_id);
+        peer.send(&m.with_header("seq", seq.to_le_bytes()).serialize().unwrap()).await.unwrap();
+    }
+
+    let mut gaps = vec![];
+    while let Ok(Some(event)) = timeout(Duration::from_millis(300), events.next()).await {
+        if let NetworkEvent::Gap { from, missing } = &*event {
+            assert_eq!(*from, peer_id);
+            gaps.push((missing.start, missing.end));
+        }
+    }
+
+    gaps
+}
+
+#[tokio::test]
+async fn skipped_numbers_are_reported() {
+    assert_eq!(deliver_numbered(&[1, 3]).await, [(2, 3)]);
+}
+
+/// Late and repeated messages don't wind the count back, which would report the next one as a gap.
+#[tokio::test]
+async fn late_and_repeated_numbers_are_ignored() {
+    assert_eq!(deliver_numbered(&[1, 2, 4, 3, 4, 2, 5]).await, [(3, 4)]);
+}
@@ -0,0 +1,39 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::MemoryMedium,
+        network::{Network, NetworkState, SendError},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+#[tokio::test]
+async fn keys_learned_on_one_network_are_used_on_another() {
+    let (lora, ip) = (MemoryMedium::new(), MemoryMedium::new());
+
+    // A peer on both networks, as the same node
+    let peer = NetworkState::new(Uuid::new_v4());
+    let _peer_on_lora = Network::builder(lora.connect()).state(peer.clone()).build();
+    let peer_on_ip = Network::builder(ip.connect()).state(peer.clone()).build();
+    let mut inbox = peer_on_ip.as_stream();
+
+    let on_lora = Network::new(lora.connect());
+    let on_ip = Network::builder(ip.connect())
+        .state(on_lora.export_state().await)
+        .share_resolution(on_lora.share_resolution())
+        .build();
+    let unshared = Network::new(ip.connect());
+
+    on_lora.resolve(peer.id).await.unwrap();
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(peer.id).with_body("over ip");
+    assert!(matches!(unshared.send(message.clone()).await, Err(SendError::UnknownNode(_))));
+    on_ip.send(message).await.unwrap();
+
+    let received = timeout(Duration::from_secs(1), inbox.next()).await.expect("never arrived").unwrap();
+    assert_eq!(received.body, b"over ip");
+}
@@ -0,0 +1,19 @@
+use {flesh::transport::short_id::ShortId, uuid::Uuid};
+
+#[test]
+fn short_ids_are_stable() {
+    let id = Uuid::from_u128(0xdead_beef_0000_0000_0000_0000_0000_0000);
+    assert_eq!(ShortId::new(id, []).to_string(), "32w353");
+    assert_eq!(ShortId::new(id, [id, Uuid::from_u128(1)]).to_string(), "32w353");
+}
+
+#[test]
+fn colliding_short_ids_grow_until_distinct() {
+    let id = Uuid::from_u128(0xdead_beef_0000_0000_0000_0000_0000_0000);
+    let lookalike = Uuid::from_u128(0xdead_beef_ff00_0000_0000_0000_0000_0000);
+
+    let (short, other) = (ShortId::new(id, [lookalike]), ShortId::new(lookalike, [id]));
+    assert_ne!(short.to_string(), other.to_string());
+    assert!(short.to_string().len() > 6);
+    assert!(other.to_string().starts_with("32w353"));
+}
@@ -0,0 +1,83 @@
+use {
+    flesh::transport::{
+        PacketTransport,
+        encoding::FLESHMessage,
+        memory::MemoryTransport,
+        network::{Network, NetworkEvent, SendError},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+    tokio_util::sync::CancellationToken,
+};
+
+/// Collects the fragments heard on `listener` until it's been quiet for a while.
+async fn fragments_heard(listener: &mut MemoryTransport) -> usize {
+    let mut heard = 0;
+    while let Ok(Ok(frame)) = timeout(Duration::from_millis(500), listener.recv()).await {
+        heard += FLESHMessage::deserialize(&frame).is_ok_and(|m| matches!(m.status, Status::Fragment)) as usize;
+    }
+
+    heard
+}
+
+/// Cancelling once two parts are out stops the rest, and says how far the transfer got.
+#[tokio::test]
+async fn cancelling_part_way_stops_the_remaining_parts() {
+    let (transport, mut listener) = MemoryTransport::pair();
+    let network = Network::builder(transport).build();
+    let mut events = network.events().as_stream();
+    let cancel = CancellationToken::new();
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_body([7; 400]);
+    let sending = tokio::spawn({
+        let (network, cancel) = (network.clone(), cancel.clone());
+        async move { network.send_with_splitting_cancellable(message, 64, cancel).await }
+    });
+
+    timeout(Duration::from_secs(5), async {
+        while let Some(event) = events.next().await {
+            if let NetworkEvent::SendProgress { sent: 2, .. } = *event {
+                cancel.cancel();
+                break;
+            }
+        }
+    })
+    .await
+    .expect("the first parts never went out");
+
+    let error = sending.await.unwrap().unwrap_err();
+    assert!(
+        matches!(error.downcast_ref::<SendError>(), Some(SendError::Cancelled { sent: 2, total }) if *total > 2),
+        "{error:?}"
+    );
+    assert_eq!(fragments_heard(&mut listener).await, 2);
+}
+
+/// Each part is reported as it goes out, so a 4 part message counts 1/4 through 4/4 in order.
+#[tokio::test(start_paused = true)]
+async fn progress_is_reported_for_every_part_in_order() {
+    let (transport, _listener) = MemoryTransport::pair();
+    let network = Network::builder(transport).build();
+    let mut events = network.events().as_stream();
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_body([7; 400]);
+    let chunk = message.serialize().unwrap().len().div_ceil(4);
+    network.send_with_splitting(message, chunk).await.unwrap();
+
+    let mut progress = vec![];
+    while let Ok(Some(event)) = timeout(Duration::from_millis(100), events.next()).await {
+        if let NetworkEvent::SendProgress { id, sent, total } = *event {
+            progress.push((id, sent, total));
+        }
+    }
+
+    assert!(progress.iter().all(|(id, ..)| *id == progress[0].0), "{progress:?}");
+    assert_eq!(progress.iter().map(|(_, sent, total)| (*sent, *total)).collect::<Vec<_>>(), [
+        (1, 4),
+        (2, 4),
+        (3, 4),
+        (4, 4)
+    ]);
+}
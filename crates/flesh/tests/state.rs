@@ -0,0 +1,74 @@
+use {
+    flesh::transport::network::NetworkState,
+    std::{env, fs},
+    uuid::Uuid,
+};
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encrypted_state_needs_the_right_passphrase() {
+    let path = env::temp_dir().join(format!("flesh-state-{}", Uuid::new_v4()));
+    let state = NetworkState::new(Uuid::new_v4());
+    state.save(&path, Some("correct horse")).unwrap();
+
+    // The key mustn't be sitting in the file in the clear
+    assert!(!fs::read(&path).unwrap().windows(32).any(|w| w == state.key));
+
+    let loaded = NetworkState::load(&path, Some("correct horse")).unwrap();
+    assert_eq!((loaded.id, loaded.key), (state.id, state.key));
+
+    assert!(matches!(
+        NetworkState::load(&path, Some("battery staple")),
+        Err(flesh::transport::state::StateError::WrongPassphrase)
+    ));
+    assert!(matches!(NetworkState::load(&path, None), Err(flesh::transport::state::StateError::PassphraseRequired)));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn plain_state_round_trips() {
+    let path = env::temp_dir().join(format!("flesh-state-{}", Uuid::new_v4()));
+    let state = NetworkState::new(Uuid::new_v4());
+    state.save(&path, None).unwrap();
+
+    let loaded = NetworkState::load(&path, None).unwrap();
+    assert_eq!((loaded.id, loaded.key), (state.id, state.key));
+
+    fs::remove_file(path).unwrap();
+}
+
+/// Argon2's costs are saved after the salt, so what a file was sealed with is what unlocks it.
+#[cfg(feature = "crypto")]
+#[test]
+fn encrypted_state_is_unlocked_with_the_costs_it_was_saved_with() {
+    use {
+        flesh::transport::state::StateError,
+        std::time::{Duration, Instant},
+    };
+
+    let path = env::temp_dir().join(format!("flesh-state-{}", Uuid::new_v4()));
+    NetworkState::new(Uuid::new_v4()).save(&path, Some("correct horse")).unwrap();
+    let saved = fs::read(&path).unwrap();
+
+    // Format, then the salt, then memory, passes and lanes
+    let cost = |field: usize| u32::from_le_bytes(saved[17 + field * 4..][..4].try_into().unwrap());
+    assert_eq!([cost(0), cost(1), cost(2)], [19 * 1024, 2, 1]);
+
+    let mut cheaper = saved.clone();
+    cheaper[21..25].copy_from_slice(&1u32.to_le_bytes());
+    fs::write(&path, &cheaper).unwrap();
+    assert!(matches!(NetworkState::load(&path, Some("correct horse")), Err(StateError::WrongPassphrase)));
+
+    // Each cost is capped, so a tampered file can't make unlocking it hang or exhaust memory
+    for field in 0..3 {
+        let mut greedy = saved.clone();
+        greedy[17 + field * 4..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &greedy).unwrap();
+        let started = Instant::now();
+        assert!(matches!(NetworkState::load(&path, Some("correct horse")), Err(StateError::Io(_))), "cost {field}");
+        assert!(started.elapsed() < Duration::from_secs(1), "cost {field} was taken up");
+    }
+
+    fs::remove_file(path).unwrap();
+}
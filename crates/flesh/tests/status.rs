@@ -0,0 +1,57 @@
+use flesh::transport::status::{Status, StatusType};
+
+#[test]
+fn standard_codes_sit_in_their_category() {
+    for status in Status::STANDARD {
+        // The teapot is a joke, and like HTTP's 418 deliberately sits outside the ranges
+        if matches!(status, Status::Teapot) {
+            continue;
+        }
+
+        let codes = status.as_type().codes().unwrap_or_else(|| panic!("{} has no category", status.name()));
+        assert!(
+            codes.contains(&status.as_u8()),
+            "{} ({}) is outside {:?}'s {codes:?}",
+            status.name(),
+            status.as_u8(),
+            status.as_type()
+        );
+    }
+}
+
+#[test]
+fn standard_statuses_have_a_reason() {
+    for status in Status::STANDARD {
+        let reason = status.reason();
+        assert!(!reason.trim().is_empty() && reason.trim() != "\"", "{} has no reason: {reason:?}", status.name());
+    }
+
+    assert_eq!(Status::RelayFailure.reason(), "A relay couldn't pass the message on");
+    assert_eq!(Status::AlreadyReported.reason(), "Already received and handled");
+}
+
+#[test]
+fn http_codes_map_both_ways() {
+    for (status, code) in
+        [(Status::Acknowledge, 200), (Status::NotFound, 404), (Status::Teapot, 218), (Status::TooLarge, 413)]
+    {
+        assert_eq!(status.to_http(), code, "{}", status.name());
+        assert_eq!(Status::from_http(code).map(|s| s.as_u8()), Some(status.as_u8()), "{code}");
+    }
+
+    // Statuses without an equivalent fall back on their category, and unmapped codes have no status
+    assert_eq!(Status::Announce.to_http(), 200);
+    assert_eq!(Status::RelayFailure.to_http(), 502);
+    assert_eq!(Status::Custom(200).to_http(), 500);
+    assert!(Status::from_http(999).is_none());
+}
+
+#[test]
+fn standard_statuses_are_listed_with_their_metadata() {
+    let listed = Status::standard_with_metadata().collect::<Vec<_>>();
+    assert_eq!(listed.len(), Status::STANDARD.len());
+    assert!(
+        listed.iter().any(|status| matches!(status, (31, "Acknowledge", StatusType::Oks, _))),
+        "Acknowledge is missing from {listed:?}"
+    );
+}
@@ -0,0 +1,30 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, memory::MemoryMedium, network::Network, status::Status},
+    std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+};
+
+#[tokio::test]
+async fn taps_see_frames_as_sent_and_received() {
+    let medium = MemoryMedium::new();
+    let (sender, receiver) = (Network::new(medium.connect()), Network::new(medium.connect()));
+    let (sent, received) = (Arc::new(Mutex::new(vec![])), Arc::new(Mutex::new(vec![])));
+    sender.on_tx({
+        let sent = sent.clone();
+        move |frame| sent.lock().unwrap().push(frame.to_vec())
+    });
+    receiver.on_rx({
+        let received = received.clone();
+        move |frame| received.lock().unwrap().push(frame.to_vec())
+    });
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_sender(sender.id).with_body("for the record");
+    sender.send(message.clone()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let frame = message.serialize().unwrap();
+    assert!(sent.lock().unwrap().contains(&frame));
+    assert!(received.lock().unwrap().contains(&frame));
+}
@@ -0,0 +1,23 @@
+use {
+    flesh::transport::{PacketTransport, encoding::FLESHMessage, memory::MemoryMedium, network::Network, status::Status},
+    futures::StreamExt,
+    std::time::{Duration, SystemTime, UNIX_EPOCH},
+    tokio::time::timeout,
+};
+
+#[tokio::test]
+async fn future_messages_are_dropped() {
+    let medium = MemoryMedium::new();
+    let network = Network::new(medium.connect());
+    let other = medium.connect();
+    let mut inbox = network.as_stream();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let future = FLESHMessage { timestamp: now + 3600, ..FLESHMessage::new(Status::Acknowledge).with_body("future") };
+    other.send(&future.serialize().unwrap()).await.unwrap();
+    other.send(&FLESHMessage::new(Status::Acknowledge).with_body("current").serialize().unwrap()).await.unwrap();
+
+    let received = timeout(Duration::from_secs(1), inbox.next()).await.expect("nothing delivered").unwrap();
+    assert_eq!(received.body, b"current");
+    assert_eq!(network.mistimed_messages(), 1);
+}
@@ -0,0 +1,46 @@
+use {
+    flesh::transport::{
+        encoding::FLESHMessage,
+        memory::{MemoryMedium, MemoryTransport},
+        network::{Network, SendError},
+        status::Status,
+    },
+    futures::StreamExt,
+    std::time::Duration,
+    tokio::time::timeout,
+};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+/// A sender, and a receiver refusing frames over `max` bytes.
+async fn pair(max: usize) -> (Network<MemoryTransport>, Network<MemoryTransport>) {
+    let medium = MemoryMedium::new();
+    let sender = Network::new(medium.connect());
+    let receiver = Network::builder(medium.connect()).max_inbound_frame(max).build();
+
+    receiver.resolve(sender.id).await.unwrap();
+    sender.resolve(receiver.id).await.unwrap();
+    (sender, receiver)
+}
+
+#[tokio::test]
+async fn refused_messages_are_split_and_resent() {
+    let (sender, receiver) = pair(400).await;
+    let mut inbox = receiver.as_stream();
+
+    let body = vec![7; 700];
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body(body.clone());
+    sender.send_reliable(message, WAIT).await.unwrap();
+
+    let received = timeout(WAIT, inbox.next()).await.expect("never delivered").unwrap();
+    assert_eq!(received.body, body);
+}
+
+#[tokio::test]
+async fn sender_hears_when_nothing_fits() {
+    let (sender, receiver) = pair(40).await;
+
+    let message = FLESHMessage::new(Status::Acknowledge).with_target(receiver.id).with_body(vec![7; 100]);
+    let result = sender.send_reliable(message, WAIT).await;
+    assert!(matches!(result, Err(SendError::TooLarge { max: 40, .. })), "{result:?}");
+}
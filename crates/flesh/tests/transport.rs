@@ -0,0 +1,11 @@
+use flesh::transport::{PacketTransport, memory::MemoryMedium, network::Network};
+
+#[tokio::test]
+async fn transport_accessor_reaches_the_networks_transport() {
+    let medium = MemoryMedium::new();
+    let _first = medium.connect();
+    let network = Network::new(medium.connect().with_max_frame(512));
+
+    assert_eq!(network.transport().id(), 1);
+    assert_eq!(network.transport().max_frame(), Some(512));
+}
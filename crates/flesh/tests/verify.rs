@@ -0,0 +1,40 @@
+use {
+    ed25519_dalek::SigningKey,
+    flesh::transport::{
+        encoding::{FLESHMessage, MessageError},
+        memory::MemoryMedium,
+        network::{Network, NetworkState},
+        status::Status,
+    },
+    rand_core::OsRng,
+    uuid::Uuid,
+};
+
+/// The sender's key is resolved over the network, so only their id is needed to check their signature.
+#[tokio::test(start_paused = true)]
+async fn messages_from_resolvable_peers_verify() {
+    let medium = MemoryMedium::new();
+    let state = NetworkState::new(Uuid::new_v4());
+    let key = SigningKey::from_bytes(&state.key);
+    let sender = Network::builder(medium.connect()).state(state).build();
+    let verifier = Network::builder(medium.connect()).build();
+
+    let signed = FLESHMessage::new(Status::Acknowledge).with_body("hello").sign((sender.id, key)).unwrap();
+    verifier.verify_message(&signed).await.unwrap();
+
+    let tampered = signed.with_body("goodbye");
+    assert!(verifier.verify_message(&tampered).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn messages_from_unknown_senders_dont_verify() {
+    let verifier = Network::builder(MemoryMedium::new().connect()).build();
+    let stranger = Uuid::new_v4();
+
+    let signed = FLESHMessage::new(Status::Acknowledge).sign((stranger, SigningKey::generate(&mut OsRng))).unwrap();
+    let result = verifier.verify_message(&signed).await;
+    assert!(matches!(result, Err(MessageError::UnknownSender(id)) if id == stranger), "{result:?}");
+
+    let unsigned = FLESHMessage::new(Status::Acknowledge);
+    assert!(matches!(verifier.verify_message(&unsigned).await, Err(MessageError::MissingSender)));
+}
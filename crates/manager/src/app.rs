@@ -1,34 +1,64 @@
-use core::net;
 use std::sync::Arc;
 
-use futures::{lock::Mutex, stream_select};
-use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::UnixStream};
+use futures::lock::Mutex;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf}, net::UnixStream};
 
 use {
     crate::{Deserialize, Serialize, helpers::TaskList},
     anyhow::bail,
     fl_uid::Fluid,
-    flesh::Network,
+    flesh::transport::{PacketTransport, network::Network},
     futures::FutureExt,
     libloading::{Library, Symbol},
     signal_hook::{consts::signal::*, iterator::Signals},
     std::{
-        env,
-        ffi::{c_uint, c_void},
+        env::{
+            self,
+            consts::{DLL_PREFIX, DLL_SUFFIX},
+        },
+        ffi::c_void,
         fmt::Display,
         fs::create_dir_all,
+        hash::{DefaultHasher, Hash, Hasher},
+        io,
         os::raw::c_int,
-        path::PathBuf,
+        path::{Path, PathBuf},
         process::ExitStatus,
     },
+    thiserror::Error,
     tokio::{fs, process::Command},
 };
 
+/// Why an app couldn't be prepared or started.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("Failed to fetch app: {0}")]
+    CloneFailed(String),
+    #[error("Failed to build app: {0}")]
+    BuildFailed(String),
+    #[error("No library found at {0:?}")]
+    NoArtifact(PathBuf),
+    #[error("Failed to load app: {0}")]
+    LoadFailed(#[from] libloading::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct App {
     pub subdomain: String,
     pub module_path: String,
     pub root_dir: String,
+    /// Source revision and lockfile hash the module was built from
+    #[serde(default)]
+    pub build_hash: Option<String>,
+}
+
+/// What was last built for an app, so unchanged sources can skip `cargo build`.
+#[derive(Serialize, Deserialize)]
+struct BuildCache {
+    hash: String,
+    module_path: String,
 }
 
 #[derive(Clone)]
@@ -39,53 +69,58 @@ pub struct RunningApp {
     pub stream: Arc<MessageStream>,
 }
 
-
-pub struct MessageStream(Mutex<tokio::net::UnixStream>);
-
+impl RunningApp {
+    /// The app as it was configured when started.
+    pub fn app(&self) -> &App { &self.app }
+}
 
 
+/// Newline delimited JSON messages over the socket between the manager and an app. Both ends are
+/// read and written through this, so the app's thread (see `App::run`) frames messages the same way.
+/// Reads and writes go through separate halves, so a pending `recv` never holds up a `send`.
+pub struct MessageStream {
+    reader: Mutex<(ReadHalf<UnixStream>, Vec<u8>)>,
+    writer: Mutex<WriteHalf<UnixStream>>,
+}
 
 impl MessageStream {
-    pub fn new(socket: tokio::net::UnixStream) -> Self {
-        Self(Mutex::new(socket))
+    pub fn new(socket: UnixStream) -> Self {
+        let (reader, writer) = tokio::io::split(socket);
+        Self { reader: Mutex::new((reader, Vec::new())), writer: Mutex::new(writer) }
     }
 
+    /// Waits for the next message. Cancel safe, as anything read towards a message is kept
+    /// buffered for the next call rather than lost with the future.
     pub async fn recv(&self) -> anyhow::Result<Message> {
-        let mut buf = [0u8; 1024];
-        let mut socket = self.0.lock().await;
-        let n = socket.read(&mut buf).await?;
-        if n == 0 {
-            bail!("Socket closed");
+        let mut reader = self.reader.lock().await;
+        let (socket, buffer) = &mut *reader;
+        loop {
+            if let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                let line = buffer.drain(..=end).collect::<Vec<_>>();
+                return Ok(serde_json::from_slice(&line[..end])?);
+            }
+
+            let mut buf = [0u8; 1024];
+            let n = socket.read(&mut buf).await?;
+            if n == 0 {
+                bail!("Socket closed");
+            }
+
+            buffer.extend_from_slice(&buf[..n]);
         }
-        let msg: Message = serde_json::from_slice(&buf[..n])?;
-        Ok(msg)
     }
 
     pub async fn send(&self, msg: Message) -> anyhow::Result<()> {
-        let buf = serde_json::to_vec(&msg)?;
-        let mut socket = self.0.lock().await;
-        let _ = socket.write(&buf).await?;
+        let mut buf = serde_json::to_vec(&msg)?;
+        buf.push(b'\n');
+        self.writer.lock().await.write_all(&buf).await?;
         Ok(())
     }
-    pub fn blocking_send(&self, msg: Message) -> anyhow::Result<()> {
-        let buf = serde_json::to_vec(&msg)?;
-        futures::executor::block_on(async {
-            let mut socket = self.0.lock().await;
-            let _ = socket.write(&buf).await?;
-            Ok(())
-        })
-    }
-    pub fn blocking_recv(&self) -> anyhow::Result<Message> {
-        let mut buf = [0u8; 1024];
-        let n = futures::executor::block_on(async { self.0.lock().await.read(&mut buf).await })?;
-        if n == 0 {
-            bail!("Socket closed");
-        }
-        let msg: Message = serde_json::from_slice(&buf[..n])?;
-        Ok(msg)
-    }
-}
 
+    pub fn blocking_send(&self, msg: Message) -> anyhow::Result<()> { futures::executor::block_on(self.send(msg)) }
+
+    pub fn blocking_recv(&self) -> anyhow::Result<Message> { futures::executor::block_on(self.recv()) }
+}
 
 #[derive(Deserialize, Serialize)]
 pub enum Message {
@@ -109,84 +144,132 @@ fn config_dir() -> PathBuf {
 
 
 
-async fn find_so(p: PathBuf) -> anyhow::Result<PathBuf> {
-    let target_dir = p.join("target").join("release");
-    let mut entries = match fs::read_dir(&target_dir).await {
-        Ok(e) => e,
-        Err(e) => bail!("Failed to read target directory {:?}: {}", target_dir, e),
-    };
+// The file cargo gives the app's cdylib on this platform, named after the library (or package) in its manifest
+async fn artifact_name(p: &Path) -> Result<String, AppError> {
+    let manifest = fs::read_to_string(p.join("Cargo.toml")).await?;
+    let manifest = manifest.parse::<toml::Table>().map_err(|e| AppError::BuildFailed(format!("Invalid Cargo.toml: {e}")))?;
 
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
-        match path.extension() {
-            Some(ext) if (ext == "so" || ext == "dylib") => {
-                return Ok(path);
-            }
-            _ => continue,
-        }
+    let name = ["lib", "package"]
+        .into_iter()
+        .find_map(|section| manifest.get(section)?.get("name")?.as_str())
+        .ok_or(AppError::BuildFailed("Cargo.toml has no package name".to_string()))?;
+
+    Ok(format!("{DLL_PREFIX}{}{DLL_SUFFIX}", name.replace('-', "_")))
+}
+
+/// The app's library in the release build under `p`, which must be there.
+pub async fn find_so(p: PathBuf) -> Result<PathBuf, AppError> {
+    let path = p.join("target").join("release").join(artifact_name(&p).await?);
+    match fs::try_exists(&path).await? {
+        true => Ok(path),
+        false => Err(AppError::NoArtifact(path)),
     }
+}
+
+
 
-    bail!("No .so file found in {:?}", target_dir);
+
+/// Identifies the exact sources a build would be made from: the checked out revision plus the lockfile.
+pub async fn build_hash(wd: &Path) -> anyhow::Result<String> {
+    let rev = Command::new("git").current_dir(wd).args(["rev-parse", "HEAD"]).output().await?;
+    if !rev.status.success() {
+        bail!("Failed to read git revision of {:?}", wd);
+    }
+
+    let mut hasher = DefaultHasher::new();
+    fs::read(wd.join("Cargo.lock")).await.unwrap_or_default().hash(&mut hasher);
+
+    Ok(format!("{}-{:016x}", String::from_utf8_lossy(&rev.stdout).trim(), hasher.finish()))
 }
 
+fn build_cache_path(wd: &Path) -> PathBuf { wd.with_extension("build.json") }
 
+/// The module last built from `wd`, if it was built from sources matching `hash` and is still there.
+pub async fn cached_build(wd: &Path, hash: &str) -> Option<String> {
+    let cache: BuildCache = serde_json::from_slice(&fs::read(build_cache_path(wd)).await.ok()?).ok()?;
+    (cache.hash == hash && Path::new(&cache.module_path).exists()).then_some(cache.module_path)
+}
 
+/// Remembers `module_path` as built from `wd` at `hash`, see `cached_build`.
+pub async fn record_build(wd: &Path, hash: &str, module_path: &str) -> io::Result<()> {
+    let cache = BuildCache { hash: hash.to_string(), module_path: module_path.to_string() };
+    fs::write(build_cache_path(wd), serde_json::to_vec(&cache).map_err(io::Error::other)?).await
+}
 
 impl App {
-    pub async fn new(url: impl Display + Sync + Send + 'static) -> anyhow::Result<Self> {
+    pub async fn new(url: impl Display + Sync + Send + 'static) -> Result<Self, AppError> {
         let name = url.to_string().replace(|c: char| !c.is_alphanumeric(), "_");
         let wd = config_dir().join(name);
 
-        let tl = TaskList::new("Prepare app")
-            .add_task("Clone repo", {
-                let wd = wd.clone();
-                async move {
-                    Command::new("git")
-                        .args(["clone", &url.to_string(), &wd.display().to_string(), "--quiet"])
-                        .status()
-                        .map(status_error)
-                        .await
-                }
-            })
-            .add_task("Cargo build", {
-                let wd = wd.clone();
-                async move {
-                    Command::new("cargo")
-                        .current_dir(wd)
-                        .args(["build", "--release", "--quiet"])
-                        .status()
-                        .map(status_error)
-                        .await
-                }
-            });
+        // Already cloned apps are brought up to date rather than cloned again
+        let tl = TaskList::new("Prepare app").add_task("Fetch repo", {
+            let wd = wd.clone();
+            async move {
+                let mut git = Command::new("git");
+                match wd.exists() {
+                    true => git.current_dir(&wd).args(["pull", "--quiet"]),
+                    false => git.args(["clone", &url.to_string(), &wd.display().to_string(), "--quiet"]),
+                };
+
+                git.status().map(status_error).await
+            }
+        });
+
+        tl.await.map_err(|e| AppError::CloneFailed(e.to_string()))?;
+
+        let hash = build_hash(&wd).await.map_err(|e| AppError::CloneFailed(e.to_string()))?;
+        let module_path = match cached_build(&wd, &hash).await {
+            Some(module_path) => module_path,
+            None => {
+                let tl = TaskList::new("Build app").add_task("Cargo build", {
+                    let wd = wd.clone();
+                    async move {
+                        Command::new("cargo")
+                            .current_dir(wd)
+                            .args(["build", "--release", "--quiet"])
+                            .status()
+                            .map(status_error)
+                            .await
+                    }
+                });
+
+                tl.await.map_err(|e| AppError::BuildFailed(e.to_string()))?;
+
+                let module_path = find_so(wd.clone()).await?.display().to_string();
+                record_build(&wd, &hash, &module_path).await?;
+                module_path
+            }
+        };
 
-        tl.await?;
         Ok(Self {
             subdomain: Fluid::new().to_string(),
-            module_path: find_so(wd.clone()).await?.display().to_string(),
+            module_path,
             root_dir: wd.display().to_string(),
+            build_hash: Some(hash),
         })
     }
 
-    pub async fn run(&self,   network: Network, port: usize) -> anyhow::Result<RunningApp> {
+    pub async fn run<T: PacketTransport + Clone + 'static>(&self, network: Network<T>, port: usize) -> Result<RunningApp, AppError> {
         unsafe {
             let path = PathBuf::from(format!("/tmp/flesh-{}.sock", self.subdomain));
             let server_socket = tokio::net::UnixSocket::new_stream()?;
             server_socket.bind(&path)?;
             let client_socket = tokio::net::UnixSocket::new_stream()?;
-            let (server_socket,_    ) = server_socket.listen(1)?.accept().await?;
-            let stream = Arc::new(MessageStream::new(client_socket.connect(path).await?));
+            // The app's end connects while the manager's accepts, either alone would wait on the other forever
+            let listener = server_socket.listen(1)?;
+            let ((server_socket, _), client) = tokio::try_join!(listener.accept(), client_socket.connect(&path))?;
+            let stream = Arc::new(MessageStream::new(client));
             let lib = Library::new(self.module_path.clone())?;
 
             std::thread::spawn(move || {
 
-            let network_ptr = &network as *const Network as *mut c_void;
+            let network_ptr = &network as *const Network<T> as *mut c_void;
                 macro_rules! send_if_error {
                     ($msg:expr, $val:expr) => {
                         match $val {
                             Ok(v) => v,
                             Err(e) => {
-                                let _ = stream.send(Message::ErrorLoading(anyhow::anyhow!(concat!("Failed to ", $msg, ": {}"), e).to_string()));
+                                let _ = stream.blocking_send(Message::ErrorLoading(anyhow::anyhow!(concat!("Failed to ", $msg, ": {}"), e).to_string()));
                                 return;
                             }
                         }
@@ -197,13 +280,13 @@ impl App {
                     lib.get(b"__flesh_entrypoint")
                 );
                 let mut signals = send_if_error!(
-                    "create signal handler (SIGINT, SIGTERM, SIGQUIT, SIGSEGV)",
-                    Signals::new([SIGINT, SIGTERM, SIGQUIT, SIGSEGV])
+                    "create signal handler (SIGINT, SIGTERM, SIGQUIT)",
+                    Signals::new([SIGINT, SIGTERM, SIGQUIT])
                 );
                 let stream_a = stream.clone();
                 std::thread::spawn(move || {
                     for sig in signals.forever() {
-                        let _ = stream_a.send(Message::ErrorSignal(sig as c_int));
+                        let _ = stream_a.blocking_send(Message::ErrorSignal(sig as c_int));
                     }
                 });
 
@@ -0,0 +1,48 @@
+use {
+    futures::future::BoxFuture,
+    owo_colors::OwoColorize,
+    spinoff::{Color, Spinner, spinners},
+    std::future::{Future, IntoFuture},
+};
+
+/// Steps run one after another under a heading, each shown with a spinner while it runs. Awaiting the list
+/// runs it, stopping at the first step to fail.
+pub struct TaskList {
+    title: String,
+    tasks: Vec<(String, BoxFuture<'static, anyhow::Result<()>>)>,
+}
+
+impl TaskList {
+    pub fn new(title: impl Into<String>) -> Self { Self { title: title.into(), tasks: vec![] } }
+
+    pub fn add_task<F>(mut self, name: impl Into<String>, task: F) -> Self
+    where
+        F: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.tasks.push((name.into(), Box::pin(task)));
+        self
+    }
+}
+
+impl IntoFuture for TaskList {
+    type IntoFuture = BoxFuture<'static, anyhow::Result<()>>;
+    type Output = anyhow::Result<()>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move {
+            println!("{} {}", "⟶".bright_blue().bold(), self.title.bold());
+            for (name, task) in self.tasks {
+                let mut spinner = Spinner::new(spinners::Dots, name.clone(), Color::Blue);
+                match task.await {
+                    Ok(()) => spinner.success(&name),
+                    Err(e) => {
+                        spinner.fail(&format!("{name}: {e}"));
+                        return Err(e);
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -1,14 +1,22 @@
-use std::sync::Arc;
-
-use tokio::sync::Mutex;
-
 use {
     crate::{app::App, helpers::TaskList},
-    flesh::{Network, lora::Lora},
+    flesh::{
+        modes::lora::{Lora, LoraSettings},
+        transport::{PacketTransport, memory::MemoryMedium, network::Network},
+    },
     owo_colors::OwoColorize,
     port_check::free_local_port,
     serde::{Deserialize, Serialize},
-    std::{collections::HashMap, env, fs::OpenOptions, io::Write, path::Path},
+    std::{
+        collections::{BTreeMap, HashMap},
+        env,
+        fs::OpenOptions,
+        io::Write,
+        path::{Path, PathBuf},
+        sync::Arc,
+    },
+    thiserror::Error,
+    tokio::sync::Mutex,
 };
 
 pub mod app;
@@ -17,7 +25,25 @@ pub mod helpers;
 pub const DNSMASQ_CONFIG: &str = "/tmp/flesh-dnsmasq";
 pub const NGINX_CONFIG: &str = "/tmp/flesh-nginx";
 
-#[derive(Debug, Default, Serialize, Deserialize,Clone)]
+/// Something `Config::validate` found that would stop the apps from starting.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("Subdomain {subdomain} is claimed by more than one app ({})", apps.join(", "))]
+    DuplicateSubdomain { subdomain: String, apps: Vec<String> },
+    #[error("No module found for app {app} at {path:?}")]
+    MissingModule { app: String, path: PathBuf },
+    #[error("{0} isn't installed, or isn't on the PATH")]
+    MissingProgram(&'static str),
+    #[error("Couldn't find a free local port for each of the {0} apps")]
+    NoFreePorts(usize),
+}
+
+/// Whether `program` can be found on the PATH.
+fn installed(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Config {
     apps: HashMap<String, App>,
 }
@@ -31,15 +57,85 @@ impl Config {
 
     pub fn apps(&self) -> &HashMap<String, App> { &self.apps }
 
+    /// Checks everything `start` relies on without starting anything, reporting every problem found
+    /// rather than just the first: that subdomains are unique, that each app's module exists, that
+    /// `dnsmasq` and `nginx` are installed, and that there are ports free for the apps to listen on.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> { self.check(true) }
+
+    /// Like `validate`, only looking for `dnsmasq` and `nginx` if the apps are to be `proxied` through them.
+    fn check(&self, proxied: bool) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+
+        let mut claims = BTreeMap::<&str, Vec<String>>::new();
+        for (name, app) in &self.apps {
+            claims.entry(&app.subdomain).or_default().push(name.clone());
+        }
+
+        for (subdomain, mut apps) in claims.into_iter().filter(|(_, apps)| apps.len() > 1) {
+            apps.sort();
+            errors.push(ConfigError::DuplicateSubdomain { subdomain: subdomain.to_string(), apps });
+        }
+
+        let mut apps = self.apps.iter().collect::<Vec<_>>();
+        apps.sort_by_key(|(name, _)| *name);
+        for (name, app) in apps {
+            if !Path::new(&app.module_path).exists() {
+                errors.push(ConfigError::MissingModule { app: name.clone(), path: PathBuf::from(&app.module_path) });
+            }
+        }
+
+        for program in ["dnsmasq", "nginx"] {
+            if proxied && !installed(program) {
+                errors.push(ConfigError::MissingProgram(program));
+            }
+        }
+
+        if (0..self.apps.len()).any(|_| free_local_port().is_none()) {
+            errors.push(ConfigError::NoFreePorts(self.apps.len()));
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
     pub async fn start(self) -> anyhow::Result<()> {
         // TODO: Specify mode via CLI
-        let network = Network::new(Lora::new(Path::new(&env::var("LORA").expect("Missing LORA env")).to_path_buf(), 6900)?);
+        let device = Path::new(&env::var("LORA").expect("Missing LORA env")).to_path_buf();
+        self.start_with_transport(Lora::new(device, 6900, LoraSettings::default(), false).await?).await
+    }
+
+    /// Runs the apps without any radio, over a `MemoryTransport` connected to `medium`, so apps can be
+    /// developed and tested on machines (and CI) without LoRa hardware. Other nodes reach the apps by
+    /// connecting to the same medium. Nothing's proxied to them, so neither `dnsmasq` nor `nginx` is needed,
+    /// and apps are reached on their own ports.
+    pub async fn start_simulated(self, medium: MemoryMedium) -> anyhow::Result<()> {
+        self.run(medium.connect(), false).await
+    }
+
+    /// Runs the apps over any transport. Apps are handed the network as a `Network<T>`, so must
+    /// be built against the same transport type the manager is started with.
+    pub async fn start_with_transport<T: PacketTransport + Clone + 'static>(self, transport: T) -> anyhow::Result<()> {
+        self.run(transport, true).await
+    }
+
+    /// Runs the apps over `transport`, serving each at its subdomain through `dnsmasq` and `nginx` if `proxied`.
+    async fn run<T: PacketTransport + Clone + 'static>(self, transport: T, proxied: bool) -> anyhow::Result<()> {
+        // Nothing's written or spawned for a config that couldn't run anyway
+        self.check(proxied)
+            .map_err(|errors| anyhow::anyhow!(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")))?;
+
+        let network = Network::new(transport);
         let ports =
             std::iter::repeat_n((), self.apps.len()).map(|_| free_local_port().unwrap() as usize).collect::<Vec<_>>();
 
-        let mut tl = TaskList::new("Start FLESH")
-            .add_task("Write dnsmasq", Self::write_dnsmasq(self.apps.clone()))
-            .add_task("Write nginx", Self::write_nginx(self.apps.clone(), ports.clone()));
+        let mut tl = TaskList::new("Start FLESH");
+        if proxied {
+            tl = tl
+                .add_task("Write dnsmasq", Self::write_dnsmasq(self.apps.clone()))
+                .add_task("Write nginx", Self::write_nginx(self.apps.clone(), ports.clone()));
+        }
 
         let apps = self.apps.clone();
         let running_apps = std::sync::Arc::new(tokio::sync::RwLock::new(HashMap::new()));
@@ -51,22 +147,27 @@ impl Config {
                 let network = network.clone();
                 let app = app.clone();
                 async move {
-                    running_apps.write().await.insert(name.clone(), Arc::new(Mutex::new(app.run(network, *ports.get(i).ok_or(anyhow::anyhow!("No port available"))?).await?)));
-                    Ok::<_,anyhow::Error>(())
+                    running_apps.write().await.insert(
+                        name.clone(),
+                        Arc::new(Mutex::new(
+                            app.run(network, *ports.get(i).ok_or(anyhow::anyhow!("No port available"))?).await?,
+                        )),
+                    );
+                    Ok::<_, anyhow::Error>(())
                 }
             })
         }
-        
 
         tl.await?;
 
         // Run dnsmasq + nginx in the foreground.
-        // Example: spawn dnsmasq and nginx using std::process::Command
-        let mut dnsmasq =
-            tokio::process::Command::new("dnsmasq").arg("--no-daemon").arg("--conf-file").arg(DNSMASQ_CONFIG).spawn()?;
-
-        let mut nginx =
-            tokio::process::Command::new("nginx").arg("-c").arg(NGINX_CONFIG).arg("-g").arg("daemon off;").spawn()?;
+        let proxies = match proxied {
+            true => Some((
+                tokio::process::Command::new("dnsmasq").arg("--no-daemon").arg("--conf-file").arg(DNSMASQ_CONFIG).spawn()?,
+                tokio::process::Command::new("nginx").arg("-c").arg(NGINX_CONFIG).arg("-g").arg("daemon off;").spawn()?,
+            )),
+            false => None,
+        };
 
         println!("{}", "⟶ Running".bright_green().bold());
         loop {
@@ -75,36 +176,60 @@ impl Config {
                 let app = app.lock().await;
                 match app.stream.recv().await? {
                     app::Message::ErrorDone => {
-                        println!("{} {}", "⟵".bright_yellow().bold(), format!("App {name} reported an error").bright_yellow().bold());
+                        println!(
+                            "{} {}",
+                            "⟵".bright_yellow().bold(),
+                            format!("App {name} reported an error").bright_yellow().bold()
+                        );
                         let mut count_error = app.count_error;
                         count_error += 1;
                         if count_error >= 3 {
-                            println!("{} {}", "✖".bright_red().bold(), format!("App {name} has reached the maximum number of errors and will be stopped.",).bright_red().bold());
+                            println!(
+                                "{} {}",
+                                "✖".bright_red().bold(),
+                                format!("App {name} has reached the maximum number of errors and will be stopped.",)
+                                    .bright_red()
+                                    .bold()
+                            );
                             // Remove the app from the list to stop monitoring it
-                            
+
                             apps.remove(&name);
                         }
-                    },
+                    }
                     app::Message::ErrorSignal(sig) => {
-                        println!("{} {}", "⟵".bright_yellow().bold(), format!("App {name} received signal {sig}",).bright_yellow().bold());
+                        println!(
+                            "{} {}",
+                            "⟵".bright_yellow().bold(),
+                            format!("App {name} received signal {sig}",).bright_yellow().bold()
+                        );
                         let mut count_error = app.count_error;
                         count_error += 1;
                         if count_error >= 3 {
-                            println!("{} {}", "✖".bright_red().bold(), format!("App {name} has reached the maximum number of errors and will be stopped.",).bright_red().bold());
+                            println!(
+                                "{} {}",
+                                "✖".bright_red().bold(),
+                                format!("App {name} has reached the maximum number of errors and will be stopped.",)
+                                    .bright_red()
+                                    .bold()
+                            );
                             // Remove the app from the list to stop monitoring it
                             app.stream.send(app::Message::QuitUrAss).await?;
                             apps.remove(&name);
                         }
-                    },
+                    }
                     _ => {}
                 }
-            }   
+            }
             if apps.is_empty() {
                 println!("{}", "✔ All apps have been stopped.".bright_green().bold());
                 break;
             }
         }
-        let _ = tokio::try_join!(dnsmasq.wait(), nginx.wait());
+        // Anything the apps sent on their way out is still to reach the air
+        network.flush().await?;
+        if let Some((mut dnsmasq, mut nginx)) = proxies {
+            let _ = tokio::try_join!(dnsmasq.wait(), nginx.wait());
+        }
 
         Ok(())
     }
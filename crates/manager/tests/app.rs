@@ -0,0 +1,47 @@
+use {
+    manager::app::{App, AppError, find_so},
+    std::{
+        env::{
+            self,
+            consts::{DLL_PREFIX, DLL_SUFFIX},
+        },
+        fs,
+    },
+    uuid::Uuid,
+};
+
+#[tokio::test]
+async fn unreachable_repo_fails_to_clone() {
+    let url = env::temp_dir().join(format!("flesh-missing-{}", Uuid::new_v4())).display().to_string();
+    assert!(matches!(App::new(url).await, Err(AppError::CloneFailed(_))));
+}
+
+#[tokio::test]
+async fn missing_library_is_no_artifact() {
+    let wd = env::temp_dir().join(format!("flesh-app-{}", Uuid::new_v4()));
+    fs::create_dir_all(wd.join("target").join("release")).unwrap();
+    fs::write(wd.join("Cargo.toml"), "[package]\nname = \"chat\"\n").unwrap();
+
+    assert!(matches!(find_so(wd.clone()).await, Err(AppError::NoArtifact(path)) if path.starts_with(&wd)));
+    let _ = fs::remove_dir_all(&wd);
+}
+
+#[tokio::test]
+async fn the_apps_own_library_is_picked() {
+    let wd = env::temp_dir().join(format!("flesh-app-{}", Uuid::new_v4()));
+    let release = wd.join("target").join("release");
+    fs::create_dir_all(&release).unwrap();
+    fs::write(wd.join("Cargo.toml"), "[package]\nname = \"chat-app\"\n").unwrap();
+
+    // Dependencies' cdylibs sit alongside the app's, some sorting before it
+    for name in ["anyhow", "chat_app", "zstd"] {
+        fs::write(release.join(format!("{DLL_PREFIX}{name}{DLL_SUFFIX}")), []).unwrap();
+    }
+
+    assert_eq!(find_so(wd.clone()).await.unwrap(), release.join(format!("{DLL_PREFIX}chat_app{DLL_SUFFIX}")));
+
+    // A library named apart from its package is found by the library's name
+    fs::write(wd.join("Cargo.toml"), "[package]\nname = \"chat-app\"\n\n[lib]\nname = \"zstd\"\n").unwrap();
+    assert_eq!(find_so(wd.clone()).await.unwrap(), release.join(format!("{DLL_PREFIX}zstd{DLL_SUFFIX}")));
+    let _ = fs::remove_dir_all(&wd);
+}
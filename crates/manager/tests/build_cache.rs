@@ -0,0 +1,47 @@
+use {
+    manager::app::{build_hash, cached_build, record_build},
+    std::{env, fs, path::Path, process::Command},
+    uuid::Uuid,
+};
+
+fn git(wd: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(wd)
+        .args(["-c", "user.name=flesh", "-c", "user.email=flesh@localhost"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test]
+async fn unchanged_sources_reuse_the_last_build() {
+    let wd = env::temp_dir().join(format!("flesh-app-{}", Uuid::new_v4()));
+    fs::create_dir_all(&wd).unwrap();
+    fs::write(wd.join("Cargo.lock"), "version = 4\n").unwrap();
+    git(&wd, &["init", "--quiet"]);
+    git(&wd, &["add", "-A"]);
+    git(&wd, &["commit", "--quiet", "-m", "app"]);
+
+    let module = wd.join("libapp.so");
+    fs::write(&module, []).unwrap();
+    let module = module.display().to_string();
+
+    let hash = build_hash(&wd).await.unwrap();
+    assert_eq!(cached_build(&wd, &hash).await, None);
+    record_build(&wd, &hash, &module).await.unwrap();
+    assert_eq!(cached_build(&wd, &hash).await, Some(module.clone()));
+
+    // A new lockfile is new sources, even at the same revision
+    fs::write(wd.join("Cargo.lock"), "version = 4\n\n[[package]]\nname = \"app\"\n").unwrap();
+    let changed = build_hash(&wd).await.unwrap();
+    assert_ne!(changed, hash);
+    assert_eq!(cached_build(&wd, &changed).await, None);
+
+    // Nor is a build that's since been deleted reused
+    fs::remove_file(&module).unwrap();
+    assert_eq!(cached_build(&wd, &hash).await, None);
+
+    let _ = fs::remove_dir_all(&wd);
+    let _ = fs::remove_file(wd.with_extension("build.json"));
+}
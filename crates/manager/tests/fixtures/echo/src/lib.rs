@@ -0,0 +1,26 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, memory::MemoryTransport, network::Network, status::Status},
+    futures::StreamExt,
+    std::ffi::c_void,
+};
+
+/// Called by the manager on the app's own thread, with the network it's running over. This app's built for
+/// `Config::start_simulated`, so the network is over a `MemoryTransport`.
+///
+/// # Safety
+///
+/// `network` has to point to a live `Network<MemoryTransport>`, built from the same flesh as this app.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __flesh_entrypoint(network: *mut c_void, _port: usize) {
+    let network = unsafe { &*(network as *const Network<MemoryTransport>) };
+
+    // The app has its own copy of tokio, so needs its own runtime to drive the network with
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("Failed to start runtime");
+    runtime.block_on(async {
+        let mut messages = network.as_stream();
+        while let Some(m) = messages.next().await {
+            let echo = FLESHMessage::new(Status::Acknowledge).with_body(m.body.clone());
+            let _ = network.reply(&m, echo).await;
+        }
+    });
+}
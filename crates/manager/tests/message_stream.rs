@@ -0,0 +1,50 @@
+use {
+    manager::app::{Message, MessageStream},
+    std::{sync::Arc, time::Duration},
+    tokio::{net::UnixStream, time::timeout},
+};
+
+#[tokio::test]
+async fn send_completes_while_recv_is_pending() {
+    let (ours, theirs) = UnixStream::pair().unwrap();
+    let (ours, theirs) = (Arc::new(MessageStream::new(ours)), MessageStream::new(theirs));
+
+    // Nothing is ever sent our way, so this sits waiting for the whole test
+    let pending = tokio::spawn({
+        let ours = ours.clone();
+        async move { ours.recv().await.is_ok() }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    timeout(Duration::from_secs(1), ours.send(Message::QuitUrAss)).await.expect("send blocked on recv").unwrap();
+    assert!(matches!(theirs.recv().await.unwrap(), Message::QuitUrAss));
+    pending.abort();
+}
+
+#[tokio::test]
+async fn cancelled_recv_loses_nothing() {
+    let (ours, theirs) = UnixStream::pair().unwrap();
+    let (ours, theirs) = (MessageStream::new(ours), MessageStream::new(theirs));
+
+    assert!(timeout(Duration::from_millis(50), ours.recv()).await.is_err());
+
+    theirs.send(Message::ErrorDone).await.unwrap();
+    assert!(matches!(ours.recv().await.unwrap(), Message::ErrorDone));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn app_thread_messages_arrive_whole() {
+    let (ours, theirs) = UnixStream::pair().unwrap();
+    let (ours, theirs) = (MessageStream::new(ours), MessageStream::new(theirs));
+
+    // The app's thread sends from outside the runtime, and several messages can land in one read
+    std::thread::spawn(move || {
+        theirs.blocking_send(Message::ErrorSignal(15)).unwrap();
+        theirs.blocking_send(Message::ErrorLoading("no entrypoint".into())).unwrap();
+    })
+    .join()
+    .unwrap();
+
+    assert!(matches!(ours.recv().await.unwrap(), Message::ErrorSignal(15)));
+    assert!(matches!(ours.recv().await.unwrap(), Message::ErrorLoading(e) if e == "no entrypoint"));
+}
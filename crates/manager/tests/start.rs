@@ -0,0 +1,67 @@
+use {
+    flesh::transport::{encoding::FLESHMessage, memory::MemoryMedium, network::Network, status::Status},
+    futures::StreamExt,
+    manager::{Config, app::App},
+    std::{process::Command, time::Duration},
+    tokio::time::timeout,
+    uuid::Uuid,
+};
+
+fn app(subdomain: &str, module_path: String) -> App {
+    App { subdomain: subdomain.into(), module_path, root_dir: "/tmp".into(), build_hash: None }
+}
+
+/// Builds the echo app in tests/fixtures, returning where its library ended up.
+fn echo_module() -> String {
+    let output = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["build", "--quiet", "--package", "manager-echo", "--message-format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|m| m["reason"] == "compiler-artifact" && m["target"]["name"] == "manager_echo")
+        .find_map(|m| m["filenames"][0].as_str().map(str::to_string))
+        .expect("The echo app wasn't built")
+}
+
+/// A message sent over the simulated medium reaches the app, which answers it, with no radio, dnsmasq or nginx.
+#[tokio::test(flavor = "multi_thread")]
+async fn simulated_apps_answer_messages() {
+    let medium = MemoryMedium::new();
+    let mut config = Config::new();
+    config.add_app("echo".into(), app(&format!("echo-{}", Uuid::new_v4()), echo_module()));
+    let manager = tokio::spawn(config.start_simulated(medium.clone()));
+
+    let node = Network::new(medium.connect());
+    let mut heard = node.as_stream();
+    let answer = timeout(Duration::from_secs(20), async {
+        // The app may still be starting, so keep asking until it answers
+        loop {
+            node.send(FLESHMessage::new(Status::Acknowledge).with_body("ping").with_sender(node.id)).await.unwrap();
+            if let Ok(Some(m)) = timeout(Duration::from_millis(500), heard.next()).await
+                && m.sender != Some(node.id)
+            {
+                break m;
+            }
+        }
+    });
+
+    let answer = answer.await.expect("The app never answered");
+    assert_eq!(answer.body, b"ping");
+    assert!(!manager.is_finished(), "{:?}", manager.await);
+    manager.abort();
+}
+
+#[tokio::test]
+async fn invalid_config_is_refused_before_starting() {
+    let medium = MemoryMedium::new();
+    let mut config = Config::new();
+    config.add_app("wiki".into(), app("wiki", "/nonexistent/libwiki.so".into()));
+    let e = config.start_with_transport(medium.connect()).await.unwrap_err();
+    assert!(e.to_string().contains("No module found for app wiki"), "{e}");
+}
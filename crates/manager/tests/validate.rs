@@ -0,0 +1,33 @@
+use manager::{Config, ConfigError, app::App};
+
+fn app(subdomain: &str, module_path: &str) -> App {
+    App { subdomain: subdomain.into(), module_path: module_path.into(), root_dir: "/tmp".into(), build_hash: None }
+}
+
+#[test]
+fn every_problem_is_reported() {
+    let module = std::env::current_exe().unwrap().display().to_string();
+    let mut config = Config::new();
+    config.add_app("chat".into(), app("shared", &module));
+    config.add_app("wiki".into(), app("shared", &module));
+    config.add_app("maps".into(), app("maps", "/nonexistent/libmaps.so"));
+
+    let errors = config.validate().unwrap_err();
+    assert!(errors.contains(&ConfigError::DuplicateSubdomain {
+        subdomain: "shared".into(),
+        apps: vec!["chat".into(), "wiki".into()]
+    }));
+    assert!(errors.contains(&ConfigError::MissingModule { app: "maps".into(), path: "/nonexistent/libmaps.so".into() }));
+}
+
+#[test]
+fn sound_apps_raise_nothing_of_their_own() {
+    let module = std::env::current_exe().unwrap().display().to_string();
+    let mut config = Config::new();
+    config.add_app("chat".into(), app("chat", &module));
+    config.add_app("wiki".into(), app("wiki", &module));
+
+    // Whether dnsmasq and nginx are installed is down to the machine running the tests
+    let errors = config.validate().err().unwrap_or_default();
+    assert!(errors.iter().all(|e| matches!(e, ConfigError::MissingProgram(_))), "{errors:?}");
+}
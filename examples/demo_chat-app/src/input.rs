@@ -0,0 +1,75 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A line of text being typed, with a cursor that moves and edits a grapheme (what the reader sees
+/// as one character, such as an emoji or an accented letter) at a time, so it never lands inside one.
+#[derive(Debug, Default, Clone)]
+pub struct Input {
+    value: String,
+    /// Graphemes before the cursor
+    cursor: usize,
+}
+
+impl Input {
+    pub fn value(&self) -> &str { &self.value }
+
+    /// How many graphemes are before the cursor.
+    pub fn cursor(&self) -> usize { self.cursor }
+
+    /// Empties the input, returning what was in it.
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.value)
+    }
+
+    /// Types `c` at the cursor, leaving the cursor after it. A combining mark joins the grapheme before it.
+    pub fn insert(&mut self, c: char) {
+        let at = self.offset(self.cursor);
+        self.value.insert(at, c);
+        self.cursor = self.value[..at + c.len_utf8()].graphemes(true).count();
+    }
+
+    /// Removes the grapheme before the cursor, as backspace does.
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete();
+        }
+    }
+
+    /// Removes the grapheme under the cursor, as delete does. Does nothing at the end of the input.
+    pub fn delete(&mut self) {
+        let (start, end) = (self.offset(self.cursor), self.offset(self.cursor + 1));
+        self.value.replace_range(start..end, "");
+    }
+
+    pub fn left(&mut self) { self.cursor = self.cursor.saturating_sub(1) }
+
+    pub fn right(&mut self) { self.cursor = (self.cursor + 1).min(self.len()) }
+
+    pub fn home(&mut self) { self.cursor = 0 }
+
+    pub fn end(&mut self) { self.cursor = self.len() }
+
+    /// How many graphemes are in the input.
+    pub fn len(&self) -> usize { self.value.graphemes(true).count() }
+
+    pub fn is_empty(&self) -> bool { self.value.is_empty() }
+
+    /// The text before the cursor, the grapheme under it (`None` at the end), and the text after it.
+    pub fn split(&self) -> (&str, Option<&str>, &str) {
+        let (start, end) = (self.offset(self.cursor), self.offset(self.cursor + 1));
+        let under = (start < end).then(|| &self.value[start..end]);
+        (&self.value[..start], under, &self.value[end..])
+    }
+
+    /// What fits in `width` graphemes, scrolled along by whole graphemes to keep the cursor in view.
+    pub fn window(&self, width: usize) -> Input {
+        let scrolled = self.cursor.saturating_sub(width);
+        Input { value: self.value[self.offset(scrolled)..].to_string(), cursor: self.cursor - scrolled }
+    }
+
+    /// Byte offset of the `grapheme`th grapheme, the end of the input if there aren't that many.
+    fn offset(&self, grapheme: usize) -> usize {
+        self.value.grapheme_indices(true).nth(grapheme).map_or(self.value.len(), |(i, _)| i)
+    }
+}
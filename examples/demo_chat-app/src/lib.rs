@@ -0,0 +1,45 @@
+use {
+    flesh::transport::encoding::FLESHMessage,
+    std::collections::{HashSet, VecDeque},
+};
+
+pub mod input;
+
+/// Sent messages we keep an eye out for before assuming no echo is coming.
+const REMEMBERED: usize = 64;
+
+/// Messages we sent and showed straight away, so copies echoed back over the radio aren't shown again.
+/// Copies are matched on `FLESHMessage::content_id`, so two people saying the same thing are both shown.
+#[derive(Default)]
+pub struct Echoes {
+    sent: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+}
+
+impl Echoes {
+    /// Notes a message we've shown locally as well as sending.
+    pub fn sent(&mut self, m: &FLESHMessage) {
+        let id = m.content_id();
+        if !self.sent.insert(id) {
+            return;
+        }
+
+        self.order.push_back(id);
+        if self.order.len() > REMEMBERED
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.sent.remove(&oldest);
+        }
+    }
+
+    /// Whether a received message is one of ours coming back, and so already shown.
+    pub fn is_echo(&mut self, m: &FLESHMessage) -> bool {
+        let id = m.content_id();
+        let echoed = self.sent.remove(&id);
+        if echoed {
+            self.order.retain(|other| *other != id);
+        }
+
+        echoed
+    }
+}
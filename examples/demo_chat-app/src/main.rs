@@ -1,11 +1,17 @@
 use {
+    demo::Echoes,
     flesh::{
         modes::lora::{Lora, LoraSettings},
         transport::{PacketTransport, encoding::FLESHMessage, network::Network},
     },
     futures::{SinkExt, StreamExt},
     serde::{Deserialize, Serialize},
-    std::{env, path::Path, time::Duration},
+    std::{
+        env,
+        path::Path,
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
     tokio::{
         net::{TcpListener, TcpStream},
         select, spawn,
@@ -41,17 +47,18 @@ async fn main() -> anyhow::Result<()> {
     let lora = Lora::new(
         Path::new(&env::var("LORA").expect("No LoRa env")).to_path_buf(),
         9600,
-        LoraSettings { spread_factor: 9, frequency_hz: 915_000_000, bandwidth_khz: 10 },
+        LoraSettings { spread_factor: 9, frequency_hz: 915_000_000, bandwidth_khz: 10, ..Default::default() },
         false,
     )
-    .await
-    .expect("Failed to setup LoRa");
+    .await?;
+    lora.ready().await.expect("LoRa module never became ready");
 
     let network = Network::new(lora.clone());
-    let node_id = network.id.clone();
+    let node_id = network.id;
 
     let (to_lora, mut lora_handler) = unbounded_channel::<ChatMessage>();
     let (to_ws, ws_handler) = tokio::sync::broadcast::channel::<ChatMessage>(10);
+    let echoes = Arc::new(Mutex::new(Echoes::default()));
 
     let addr = "127.0.0.1:8080";
     let listener = TcpListener::bind(addr).await?;
@@ -59,11 +66,13 @@ async fn main() -> anyhow::Result<()> {
 
     // Network -> WS
     spawn({
-        let to_ws = to_ws.clone();
+        let (to_ws, echoes) = (to_ws.clone(), echoes.clone());
         async move {
             let to_ws = to_ws.clone();
             network
-                .as_stream()
+                .resilient_stream()
+                // Our own messages were shown as they were sent
+                .filter(move |m| std::future::ready(!echoes.lock().unwrap().is_echo(m)))
                 .filter_map(|m| async move { serde_json::from_slice(&m.body).ok() })
                 .for_each({
                     let to_ws = to_ws.clone();
@@ -84,14 +93,15 @@ async fn main() -> anyhow::Result<()> {
             let encoded = FLESHMessage::new(flesh::transport::status::Status::Acknowledge)
                 .with_body(serde_json::to_vec(&msg).unwrap());
 
-            // Also feedback messages into the ws'.
+            // Also feedback messages into the ws', watching for them coming back so they're only shown once
+            echoes.lock().unwrap().sent(&encoded);
             let _ = to_ws.send(msg);
             lora.send(&encoded.serialize().unwrap()).await.unwrap();
         }
     });
 
     while let Ok((stream, _)) = listener.accept().await {
-        tokio::spawn(handle_connection(stream, node_id.clone(), ws_handler.resubscribe(), to_lora.clone()));
+        tokio::spawn(handle_connection(stream, node_id, ws_handler.resubscribe(), to_lora.clone()));
     }
 
     Ok(())
@@ -170,13 +180,12 @@ async fn handle_connection(
             },
             msg = ws_handler.recv() => {
                 // Handle broadcast Lagged error by skipping, as it's a broadcast
-                if let Ok(msg) = msg {
-                    if let Ok(text) = serde_json::to_string(&msg) {
-                        if let Err(e) = sender.send(Message::Text(text.into())).await {
-                            warn!("Failed to send broadcast to {}: {}", peer_addr, e);
-                            return; // <-- EXIT on send error (broken pipe)
-                        }
-                    }
+                if let Ok(msg) = msg
+                    && let Ok(text) = serde_json::to_string(&msg)
+                    && let Err(e) = sender.send(Message::Text(text.into())).await
+                {
+                    warn!("Failed to send broadcast to {}: {}", peer_addr, e);
+                    return; // <-- EXIT on send error (broken pipe)
                 }
             },
             _ = ping_timer.tick() => {
@@ -0,0 +1,37 @@
+use {
+    demo::Echoes,
+    flesh::transport::{encoding::FLESHMessage, status::Status},
+};
+
+fn over_the_air(m: &FLESHMessage) -> FLESHMessage { FLESHMessage::deserialize(&m.serialize().unwrap()).unwrap() }
+
+#[test]
+fn echoes_collapse_into_the_local_copy() {
+    let mut echoes = Echoes::default();
+    let (ours, theirs, also_ours) = (
+        FLESHMessage::new(Status::Acknowledge).with_body("hello"),
+        FLESHMessage::new(Status::Acknowledge).with_body("hi there"),
+        FLESHMessage::new(Status::Acknowledge).with_body("how are you"),
+    );
+
+    let mut shown = vec![];
+    for (local, m) in [
+        (true, &ours),
+        (true, &also_ours),
+        (false, &over_the_air(&ours)),
+        (false, &theirs),
+        (false, &over_the_air(&also_ours)),
+    ] {
+        match local {
+            true => echoes.sent(m),
+            false if echoes.is_echo(m) => continue,
+            false => {}
+        }
+        shown.push(m.body.clone());
+    }
+
+    assert_eq!(shown, [b"hello".to_vec(), b"how are you".to_vec(), b"hi there".to_vec()]);
+
+    // Once matched, the same text from someone else is shown again
+    assert!(!echoes.is_echo(&over_the_air(&ours)));
+}
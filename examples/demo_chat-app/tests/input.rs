@@ -0,0 +1,71 @@
+use demo::input::Input;
+
+fn typed(text: &str) -> Input {
+    let mut input = Input::default();
+    text.chars().for_each(|c| input.insert(c));
+    input
+}
+
+#[test]
+fn multi_byte_characters_are_inserted_whole() {
+    let mut input = typed("héllo");
+    input.home();
+    input.right();
+    input.insert('🦀');
+    assert_eq!((input.value(), input.cursor()), ("h🦀éllo", 2));
+
+    input.end();
+    input.insert('ü');
+    assert_eq!((input.value(), input.cursor(), input.len()), ("h🦀élloü", 7, 7));
+}
+
+#[test]
+fn combining_marks_join_the_character_before() {
+    let mut input = typed("e");
+    input.insert('\u{301}');
+    assert_eq!((input.len(), input.cursor()), (1, 1));
+
+    input.backspace();
+    assert!(input.is_empty());
+}
+
+#[test]
+fn deleting_removes_whole_graphemes() {
+    let mut input = typed("a👍🏽b");
+    input.left();
+    input.backspace();
+    assert_eq!((input.value(), input.cursor()), ("ab", 1));
+
+    input.home();
+    input.delete();
+    assert_eq!((input.value(), input.cursor()), ("b", 0));
+}
+
+#[test]
+fn delete_at_the_end_does_nothing() {
+    let mut input = typed("ça");
+    input.delete();
+    assert_eq!((input.value(), input.cursor()), ("ça", 2));
+
+    let mut empty = Input::default();
+    empty.delete();
+    empty.backspace();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn cursor_splits_on_grapheme_boundaries() {
+    let mut input = typed("日本語");
+    input.left();
+    assert_eq!(input.split(), ("日本", Some("語"), ""));
+
+    input.end();
+    assert_eq!(input.split(), ("日本語", None, ""));
+}
+
+#[test]
+fn window_scrolls_by_graphemes() {
+    let input = typed("añb🦀c");
+    let window = input.window(2);
+    assert_eq!((window.value(), window.cursor()), ("🦀c", 2));
+}
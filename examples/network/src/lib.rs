@@ -1,6 +1,4 @@
 use flesh::transport::{PacketTransport, network::Network};
 
 /// Start the network app
-pub fn start<T: PacketTransport>(network: &Network<T>, port: usize) {
-    
-}
+pub fn start<T: PacketTransport>(_network: &Network<T>, _port: usize) {}